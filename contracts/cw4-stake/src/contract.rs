@@ -1,159 +1,3919 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, to_json_binary, wasm_execute, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order,
-    Response, StdResult, Uint128,
+    coins, from_json, to_json_binary, to_json_vec, Addr, BankMsg, Binary, ContractInfoResponse,
+    CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, Event, MessageInfo, Order, Reply, Response,
+    StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
 
-use cw2::set_contract_version;
-use cw20::Denom;
+use cw2::{get_contract_version, set_contract_version, ContractVersion};
+use cw20::{Cw20ExecuteMsg, Denom};
 use cw4::{Member, MemberListResponse, MemberResponse, TotalWeightResponse};
 use cw_controllers::Claim;
-use cw_storage_plus::{Bound, Map};
-use cw_utils::{maybe_addr, NativeBalance};
+use cw_storage_plus::Bound;
+use cw_utils::{maybe_addr, parse_execute_response_data, Duration, Expiration, NativeBalance};
+use dao_interface::voting::VotingPowerAtHeightResponse;
+use kujira::CallbackData;
+use sha2::{Digest, Sha256};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, StakedResponse};
-use crate::state::{Config, ADMIN, CLAIMS, CONFIG, DAO_DAO, HOOKS, MEMBERS, STAKE, TOTAL};
+use crate::msg::{
+    AcceptedFundingDenomResponse, AccountStatusResponse, AdminMatchesDaoResponse,
+    AverageBatchSizeResponse, BalanceReconciliationResponse, BatchFeedback, BatchHistoryResponse,
+    BatchStatus, CanExitResponse, ClaimDenomsResponse, ClaimMaturityScheduleResponse,
+    ClaimsBoundedResponse, ClaimsEntry, ClaimsInRangeResponse, ClaimsInfoResponse,
+    ClaimsMapStatsResponse, ClaimsNormalizedResponse, ConfirmDaoDaoResponse,
+    ContractIdentityResponse, DaoDaoExecuteMsg, DebugDumpResponse, DenomMigratableResponse,
+    DiscrepanciesResponse, DualHoldersResponse, EffectiveWeightResponse,
+    EstimatedCompletionResponse, ExecuteMsg, ExportMembersResponse, ExportReceiptsResponse,
+    ForwardingDeltaResponse, HasHooksResponse, InstantiateMsg, InstantiateParamsResponse,
+    InvariantsHoldResponse, IsAdminResponse, IsRegisteredResponse, ListFailedResponse,
+    ListMigratedResponse, MemberChangeCountResponse, MembersAboveWeightResponse, MembersAtResponse,
+    MessageLimitResponse, MigratableNowResponse, MigrateEntry, MigrateMsg, MigratedTotalResponse,
+    MigrationAcceptance, MigrationBreakdownResponse, MigrationCallback, MigrationStatusResponse,
+    MonitorExecuteMsg, NextBatchMessageResponse, NextClaimBatchSumResponse, NormalizedClaim,
+    OrphanClaimsResponse, PauseStateResponse, ProjectedFinalBalanceResponse,
+    ProjectedVotingPowerResponse, QueryMsg, ReceiptRootResponse, RegistryQueryMsg, SimulatedBatch,
+    SourceMigrationCompleteResponse, StakeDistributionResponse, StakeEntry, StakedResponse,
+    StorageLayoutResponse, SummaryResponse, TopClaimHoldersResponse, TopKConcentrationResponse,
+    TopUpNeededResponse, TransferDenomResponse, TxsToFinishResponse,
+};
+use crate::state::{
+    claims_storage, BatchRecord, Config, DestinationVersion, Discrepancy, LastBatch, LastReplyData,
+    MigrationReceipt, PendingBatch, PendingMessage, PendingVerification, PreMigrationSnapshot,
+    PreparedBatch, SnapshotProgress, SnapshotStage, ABORTED, ADMIN, ADMIN_KEY,
+    AUTO_PAUSE_ON_SHORTFALL, BATCH_LOG, BLOCK_MIGRATIONS, CLAIMS, CLAIMS_CURSOR, CLAIMS_KEY,
+    COMPLETION_THRESHOLD, CONFIG, CONFIG_KEY, DAO_DAO, DAO_DAO_CODE_ID, DESTINATION_VERSION,
+    EXCLUDE, FAILED, FUNDS_RECIPIENT, HOOKS, HOOKS_KEY, LAST_BATCH, LAST_BATCH_BLOCK,
+    LAST_REPLY_DATA, MAX_PER_BLOCK, MEMBERS, MIGRATED, MIGRATE_ENTRIES_ENABLED, MIGRATION_BACKING,
+    MIGRATION_DISCREPANCIES, MIGRATION_METRICS, MIGRATION_PROXY, MIGRATION_START,
+    MIN_BATCH_INTERVAL, MONITOR, PAUSED, PAUSE_CLAIM_MIGRATION, PAUSE_STAKE_MIGRATION,
+    PENDING_BATCH, PENDING_CONFIRMATION, PENDING_VERIFICATION, PREPARED_BATCH,
+    PRE_MIGRATION_SNAPSHOT, RECEIPT_ROOT, REGISTRY, SEALED, SNAPSHOT_PROGRESS, STAKE, STAKE_CURSOR,
+    STAKE_KEY, TARGET_OPS, TOTAL, TREASURY,
+};
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw4-stake";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+const MIGRATE_REPLY_ID: u64 = 1;
+const MONITOR_REPLY_ID: u64 = 2;
+
+// cw2 name of the DAO DAO module that handles MigrateStakes
+const DAO_DAO_CONTRACT_NAME: &str = "crates.io:dao-voting-token-staked";
+// newest release of that module, bundled here, which only parses V1 MigrateStakes
+const DAO_DAO_V1_ONLY_VERSION: &str = "2.4.2";
+
+// max number of stakes and claim entries put in a single DAO DAO message
+const MAX_ENTRIES_PER_MESSAGE: usize = 100;
+
+// rough gas cost of migrating one stake: the stake, member and changelog
+// writes plus the receipt
+const GAS_PER_STAKE: u64 = 20_000;
+
+// rough gas cost of migrating one address's claims and writing its receipt
+const GAS_PER_CLAIM: u64 = 10_000;
+
+type AddrStake = (Addr, Uint128);
+type AddrClaims = (Addr, Vec<Claim>);
+
+// max serialized size of one address's claims sent without collapsing them
+const MAX_CLAIMS_PAYLOAD: usize = 16 * 1024;
+
+// entries a batch aims for unless the admin set TARGET_OPS
+const DEFAULT_TARGET_OPS: u64 = 100;
+
+// max number of addresses accepted by a single ImportClaims call
+const MAX_IMPORT_BATCH: usize = 30;
+
+// max number of claims ImportClaims accepts for one address, so every
+// imported entry stays small enough to migrate
+const MAX_CLAIMS_PER_ADDR: usize = 50;
+
+/// Only touches what the message sets, so an upgrade partway through the
+/// migration to DAO DAO keeps its cursor, metrics and receipts.
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> StdResult<Response> {
-    DAO_DAO.save(deps.storage, &msg.dao_dao_addr)?;
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    if let Some(registry) = &msg.registry {
+        REGISTRY.save(deps.storage, registry)?;
+    }
+    let changed = msg.dao_dao_addr.is_some() || msg.registry.is_some();
+    match msg.dao_dao_addr {
+        Some(dao_dao) => DAO_DAO.save(deps.storage, &dao_dao)?,
+        None if !DAO_DAO.exists(deps.storage) => return Err(ContractError::DaoDaoNotSet {}),
+        None => {}
+    }
+    if let Some(registry) = REGISTRY.may_load(deps.storage)?.filter(|_| changed) {
+        let dao_dao = DAO_DAO.load(deps.storage)?;
+        let res: IsRegisteredResponse = deps.querier.query_wasm_smart(
+            registry,
+            &RegistryQueryMsg::IsRegistered {
+                address: dao_dao.to_string(),
+            },
+        )?;
+        if !res.registered {
+            return Err(ContractError::NotRegisteredDao {
+                addr: dao_dao.into_string(),
+            });
+        }
+    }
+    if let Some(start) = msg.migration_start {
+        MIGRATION_START.save(deps.storage, &start)?;
+    }
     Ok(Response::default())
 }
 
-// Note, you can use StdResult in some functions where you do not
-// make use of the custom errors
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn instantiate(
-    mut deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
-    msg: InstantiateMsg,
-) -> Result<Response, ContractError> {
-    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    let api = deps.api;
-    ADMIN.set(deps.branch(), maybe_addr(api, msg.admin)?)?;
+// Note, you can use StdResult in some functions where you do not
+// make use of the custom errors
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let api = deps.api;
+    ADMIN.set(deps.branch(), maybe_addr(api, msg.admin)?)?;
+
+    // min_bond is at least 1, so 0 stake -> non-membership
+    let min_bond = std::cmp::max(msg.min_bond, Uint128::new(1));
+    let min_bond_normalized = min_bond != msg.min_bond;
+
+    let config = Config {
+        denom: msg.denom,
+        tokens_per_weight: msg.tokens_per_weight,
+        min_bond,
+        unbonding_period: msg.unbonding_period,
+    };
+    CONFIG.save(deps.storage, &config)?;
+    TOTAL.save(deps.storage, &0, env.block.height)?;
+
+    Ok(Response::new().add_attribute("min_bond_normalized", min_bond_normalized.to_string()))
+}
+
+// And declare a custom Error variant for the ones where you will want to make use of it
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    if SEALED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::Sealed {});
+    }
+    let runs_batch = matches!(
+        msg,
+        ExecuteMsg::MigrateToDaoDao { .. }
+            | ExecuteMsg::MigrateAndFinalize { .. }
+            | ExecuteMsg::RetryFailed { .. }
+            | ExecuteMsg::MigrateAddresses { .. }
+            | ExecuteMsg::CommitBatch { .. }
+    );
+    if runs_batch || matches!(msg, ExecuteMsg::PrepareBatch { .. }) {
+        assert_migrator(deps.as_ref(), &info.sender)?;
+    }
+    if runs_batch {
+        if let Some(res) = auto_pause_on_shortfall(deps.branch(), &env)? {
+            return Ok(res);
+        }
+    }
+    let api = deps.api;
+    match msg {
+        ExecuteMsg::MigrateToDaoDao {
+            num,
+            num_claims,
+            protect_admin,
+            consolidate,
+            verify,
+            debug,
+            min_weight,
+            funds_to,
+            collapse_claims,
+            callback,
+            until_gas,
+            forfeit_claims,
+            max_claims_per_addr,
+            max_release,
+            confirm,
+            forward_denom,
+            interleave,
+            soonest_first,
+        } => execute_migrate_to_dao_dao(
+            deps,
+            env,
+            info,
+            num,
+            num_claims,
+            BatchOptions {
+                protect_admin: protect_admin.unwrap_or_default(),
+                consolidate: consolidate.unwrap_or_default(),
+                verify: verify.unwrap_or_default(),
+                debug: debug.unwrap_or_default(),
+                min_weight,
+                funds_to,
+                collapse_claims: collapse_claims.unwrap_or_default(),
+                callback,
+                until_gas,
+                forfeit_claims: forfeit_claims.unwrap_or_default(),
+                max_claims_per_addr,
+                max_release,
+                confirm: confirm.unwrap_or_default(),
+                forward_denom,
+                interleave: interleave.unwrap_or_default(),
+                soonest_first: soonest_first.unwrap_or_default(),
+                only: None,
+            },
+        ),
+        ExecuteMsg::MigrateAndFinalize { num, num_claims } => {
+            execute_migrate_and_finalize(deps, env, info, num, num_claims)
+        }
+        ExecuteMsg::RetryFailed { num } => execute_retry_failed(deps, env, info, num),
+        ExecuteMsg::MigrateAddresses { addrs } => execute_migrate_addresses(deps, env, info, addrs),
+        ExecuteMsg::PrepareSnapshot { num } => execute_prepare_snapshot(deps, info, num),
+        ExecuteMsg::PrepareBatch { num, num_claims } => {
+            execute_prepare_batch(deps, info, num, num_claims)
+        }
+        ExecuteMsg::CommitBatch { expected_sum } => {
+            execute_commit_batch(deps, env, info, expected_sum)
+        }
+        ExecuteMsg::ImportClaims {
+            entries,
+            denom,
+            overwrite,
+        } => execute_import_claims(deps, info, entries, denom, overwrite),
+        ExecuteMsg::UpdateDaoDaoCodeId { code_id } => {
+            execute_update_dao_dao_code_id(deps, info, code_id)
+        }
+        ExecuteMsg::UpdateExcluded { add, remove } => {
+            execute_update_excluded(deps, info, add, remove)
+        }
+        ExecuteMsg::UpdateMaxPerBlock { max_per_block } => {
+            execute_update_max_per_block(deps, info, max_per_block)
+        }
+        ExecuteMsg::UpdateMinBatchInterval { interval } => {
+            execute_update_min_batch_interval(deps, info, interval)
+        }
+        ExecuteMsg::UpdateMigrationStart { start } => {
+            execute_update_migration_start(deps, info, start)
+        }
+        ExecuteMsg::UpdateCompletionThreshold { threshold } => {
+            execute_update_completion_threshold(deps, info, threshold)
+        }
+        ExecuteMsg::UpdateTargetOps { target_ops } => {
+            execute_update_target_ops(deps, info, target_ops)
+        }
+        ExecuteMsg::UpdateTreasury { treasury } => execute_update_treasury(deps, info, treasury),
+        ExecuteMsg::UpdateFundsRecipient { recipient } => {
+            execute_update_funds_recipient(deps, info, recipient)
+        }
+        ExecuteMsg::UpdateMigrationProxy { proxy } => {
+            execute_update_migration_proxy(deps, info, proxy)
+        }
+        ExecuteMsg::UpdateMonitor { monitor } => execute_update_monitor(deps, info, monitor),
+        ExecuteMsg::UpdateAutoPauseOnShortfall { enabled } => {
+            execute_update_auto_pause_on_shortfall(deps, info, enabled)
+        }
+        ExecuteMsg::UpdateMigrateEntries { enabled } => {
+            execute_update_migrate_entries(deps, info, enabled)
+        }
+        ExecuteMsg::UpdateDestinationVersion { version } => {
+            execute_update_destination_version(deps, info, version)
+        }
+        ExecuteMsg::AddHook { addr } => {
+            Ok(HOOKS.execute_add_hook(&ADMIN, deps, info, api.addr_validate(&addr)?)?)
+        }
+        ExecuteMsg::RemoveHook { addr } => {
+            Ok(HOOKS.execute_remove_hook(&ADMIN, deps, info, api.addr_validate(&addr)?)?)
+        }
+        ExecuteMsg::AbortMigration {} => execute_abort_migration(deps, env, info),
+        ExecuteMsg::Seal {} => execute_seal(deps, env, info),
+        ExecuteMsg::Fund {} => execute_fund(deps, info),
+        ExecuteMsg::SetTotal { weight } => execute_set_total(deps, env, info, weight),
+        ExecuteMsg::AdjustStake { addr, new_stake } => {
+            execute_adjust_stake(deps, env, info, addr, new_stake)
+        }
+        ExecuteMsg::UpdateConfig {
+            denom,
+            tokens_per_weight,
+            min_bond,
+            unbonding_period,
+        } => execute_update_config(
+            deps,
+            info,
+            denom,
+            tokens_per_weight,
+            min_bond,
+            unbonding_period,
+        ),
+        ExecuteMsg::SweepForeignDenom { denom, to } => {
+            execute_sweep_foreign_denom(deps, env, info, denom, to)
+        }
+        ExecuteMsg::FinalizeResidual { to } => execute_finalize_residual(deps, env, info, to),
+        ExecuteMsg::PruneZeroWeightMembers { num, start_after } => {
+            execute_prune_zero_weight_members(deps, env, info, num, start_after)
+        }
+        ExecuteMsg::UpdatePauseState {
+            paused,
+            stake_migration_paused,
+            claim_migration_paused,
+        } => execute_update_pause_state(
+            deps,
+            info,
+            paused,
+            stake_migration_paused,
+            claim_migration_paused,
+        ),
+    }
+}
+
+/// Batches move stakes and their funds out of the contract, so only the admin
+/// may run them. With no admin set, nobody can.
+fn assert_migrator(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    match ADMIN.get(deps)? {
+        Some(admin) if admin == sender => Ok(()),
+        Some(_) => Err(ContractError::Unauthorized {}),
+        None => Err(ContractError::NoAdmin {}),
+    }
+}
+
+/// Pauses migration if `AUTO_PAUSE_ON_SHORTFALL` is set and the contract
+/// can't back its stakes and claims, returning the response to end the batch
+/// with. Returning an error would revert the pause along with the batch.
+/// What is left to back is `MIGRATION_BACKING` less the funds batches moved,
+/// so no map is scanned, and nothing is checked before the backing is set.
+fn auto_pause_on_shortfall(deps: DepsMut, env: &Env) -> Result<Option<Response>, ContractError> {
+    if !AUTO_PAUSE_ON_SHORTFALL
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        || PAUSED.may_load(deps.storage)?.unwrap_or_default()
+    {
+        return Ok(None);
+    }
+    let Some(backing) = MIGRATION_BACKING.may_load(deps.storage)? else {
+        return Ok(None);
+    };
+    let moved = MIGRATION_METRICS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .total_moved;
+    let shortfall = backing
+        .saturating_sub(moved)
+        .saturating_sub(contract_balance(deps.as_ref(), env)?);
+    if shortfall.is_zero() {
+        return Ok(None);
+    }
+    PAUSED.save(deps.storage, &true)?;
+
+    Ok(Some(
+        Response::new()
+            .add_attribute("action", "migrate")
+            .add_attribute("auto_paused", "true")
+            .add_attribute("shortfall", shortfall),
+    ))
+}
+
+/// Migrates up to `num` addresses whose messages DAO DAO rejected in an
+/// earlier batch. Their stakes and claims were restored on the rejection, and
+/// they leave the failed set once the retry is sent.
+pub fn execute_retry_failed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    num: u64,
+) -> Result<Response, ContractError> {
+    let addrs = FAILED
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(num as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+    for addr in &addrs {
+        FAILED.remove(deps.storage, addr);
+    }
+    let count = addrs.len() as u64;
+    let res = execute_migrate_to_dao_dao(
+        deps,
+        env,
+        info,
+        count,
+        count,
+        BatchOptions {
+            only: Some(addrs),
+            ..BatchOptions::default()
+        },
+    )?;
+    Ok(res.add_attribute("retried", count.to_string()))
+}
+
+pub fn execute_migrate_addresses(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addrs: Vec<String>,
+) -> Result<Response, ContractError> {
+    let addrs = addrs
+        .iter()
+        .map(|addr| deps.api.addr_validate(addr))
+        .collect::<StdResult<Vec<_>>>()?;
+    if let Some(addr) = addrs.iter().find(|addr| EXCLUDE.has(deps.storage, addr)) {
+        return Err(ContractError::AddressExcluded {
+            addr: addr.to_string(),
+        });
+    }
+    let (already_migrated, addrs): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| MIGRATED.has(deps.storage, addr));
+    let already_migrated = (!already_migrated.is_empty()).then(|| {
+        let addrs: Vec<_> = already_migrated.iter().map(Addr::as_str).collect();
+        ("already_migrated", addrs.join(","))
+    });
+    if addrs.is_empty() {
+        return Ok(Response::new()
+            .add_attribute("action", "migrate")
+            .add_attributes(already_migrated));
+    }
+
+    let count = addrs.len() as u64;
+    let res = execute_migrate_to_dao_dao(
+        deps,
+        env,
+        info,
+        count,
+        count,
+        BatchOptions {
+            only: Some(addrs),
+            ..BatchOptions::default()
+        },
+    )?;
+    Ok(res.add_attributes(already_migrated))
+}
+
+pub fn execute_migrate_and_finalize(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    num: u64,
+    num_claims: u64,
+) -> Result<Response, ContractError> {
+    let res = execute_migrate_to_dao_dao(
+        deps.branch(),
+        env,
+        info,
+        num,
+        num_claims,
+        BatchOptions::default(),
+    )?;
+
+    let claims_map = claims_storage();
+    if !STAKE.is_empty(deps.storage) || !claims_map.is_empty(deps.storage) {
+        return Err(ContractError::MigrationNotComplete {});
+    }
+    let hooks = HOOKS.query_hooks(deps.as_ref())?.hooks;
+    for hook in &hooks {
+        HOOKS.remove_hook(deps.storage, Addr::unchecked(hook))?;
+    }
+
+    Ok(res
+        .add_attribute("finalized", "true")
+        .add_attribute("hooks_cleared", hooks.len().to_string()))
+}
+
+pub fn execute_prepare_batch(
+    deps: DepsMut,
+    info: MessageInfo,
+    num: u64,
+    num_claims: u64,
+) -> Result<Response, ContractError> {
+    if num == 0 && num_claims == 0 {
+        return Err(ContractError::EmptyBatch {});
+    }
+    let batch = query_simulate_migration(deps.as_ref(), num, num_claims, false, None)?;
+    PREPARED_BATCH.save(
+        deps.storage,
+        &PreparedBatch {
+            sender: info.sender.clone(),
+            num,
+            num_claims,
+            stakes: batch.stakes,
+            claims: batch.claims,
+            sum: batch.total_sum,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "prepare_batch")
+        .add_attribute("sum", batch.total_sum)
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_commit_batch(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    expected_sum: Uint128,
+) -> Result<Response, ContractError> {
+    let prepared = PREPARED_BATCH
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPreparedBatch {})?;
+    if prepared.sender != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if prepared.sum != expected_sum {
+        return Err(ContractError::PreparedSumMismatch {
+            expected: expected_sum,
+            prepared: prepared.sum,
+        });
+    }
+    let current = query_simulate_migration(
+        deps.as_ref(),
+        prepared.num,
+        prepared.num_claims,
+        false,
+        None,
+    )?;
+    if current.stakes != prepared.stakes
+        || current.claims != prepared.claims
+        || current.total_sum != prepared.sum
+    {
+        return Err(ContractError::PreparedBatchChanged {});
+    }
+    PREPARED_BATCH.remove(deps.storage);
+
+    execute_migrate_to_dao_dao(
+        deps,
+        env,
+        info,
+        prepared.num,
+        prepared.num_claims,
+        BatchOptions::default(),
+    )
+}
+
+/// Fails if the DAO DAO contract reports a cw2 contract name other than the
+/// token staked voting module, or, for `DestinationVersion::V2`, a release of
+/// it that only parses V1. Contracts without cw2 info, and failed lookups,
+/// are let through: only a clear mismatch is rejected.
+fn assert_compatible_destination(
+    deps: Deps,
+    dao_dao: &Addr,
+    destination: &DestinationVersion,
+) -> Result<(), ContractError> {
+    let version = deps
+        .querier
+        .query_wasm_raw(dao_dao, b"contract_info".as_slice())
+        .ok()
+        .flatten()
+        .and_then(|raw| from_json::<ContractVersion>(raw).ok());
+    match version {
+        Some(version) if version.contract != DAO_DAO_CONTRACT_NAME => {
+            Err(ContractError::IncompatibleDestination {
+                found: version.contract,
+            })
+        }
+        Some(version)
+            if *destination == DestinationVersion::V2
+                && !is_newer_release(&version.version, DAO_DAO_V1_ONLY_VERSION) =>
+        {
+            Err(ContractError::UnsupportedDestinationVersion {
+                found: version.version,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Compares dotted release numbers, ignoring any pre-release or build suffix.
+fn is_newer_release(version: &str, than: &str) -> bool {
+    let parts = |v: &str| -> Vec<u64> {
+        v.split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or_default())
+            .collect()
+    };
+    parts(version) > parts(than)
+}
+
+/// Caps a batch to what `gas` covers at the estimated per entry costs. Stakes
+/// are budgeted first, claims get what is left.
+fn fit_batch_to_gas(num: u64, num_claims: u64, gas: u64) -> (u64, u64) {
+    let num = num.min(gas / GAS_PER_STAKE);
+    let left = gas - num * GAS_PER_STAKE;
+    (num, num_claims.min(left / GAS_PER_CLAIM))
+}
+
+/// Splits `budget` between `stakes` and `claims` available entries taken in
+/// turn, stakes first. A side that runs short leaves the rest to the other.
+fn interleave_budget(budget: usize, stakes: usize, claims: usize) -> (usize, usize) {
+    let stakes = stakes.min(budget.div_ceil(2).max(budget.saturating_sub(claims)));
+    (stakes, claims.min(budget - stakes))
+}
+
+/// Optional behaviour of a `MigrateToDaoDao` batch, see `ExecuteMsg`.
+#[derive(Default)]
+pub struct BatchOptions {
+    pub protect_admin: bool,
+    pub consolidate: bool,
+    pub verify: bool,
+    pub debug: bool,
+    pub min_weight: Option<u64>,
+    pub funds_to: Option<String>,
+    pub collapse_claims: bool,
+    pub callback: Option<CallbackData>,
+    pub until_gas: Option<u64>,
+    pub forfeit_claims: bool,
+    pub max_claims_per_addr: Option<u32>,
+    pub max_release: Option<Expiration>,
+    pub confirm: bool,
+    pub forward_denom: Option<String>,
+    pub interleave: bool,
+    pub soonest_first: bool,
+    /// Migrate the stakes and claims of exactly these addresses instead of
+    /// selecting them from storage.
+    pub only: Option<Vec<Addr>>,
+}
+
+pub fn execute_migrate_to_dao_dao(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    num: u64,
+    num_claims: u64,
+    opts: BatchOptions,
+) -> Result<Response, ContractError> {
+    let (num, num_claims) = match opts.until_gas {
+        Some(gas) => fit_batch_to_gas(num, num_claims, gas),
+        None => (num, num_claims),
+    };
+    if num == 0 && num_claims == 0 {
+        return Err(ContractError::EmptyBatch {});
+    }
+    if ABORTED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::MigrationAborted {});
+    }
+    if let Some(start) = MIGRATION_START.may_load(deps.storage)? {
+        if env.block.height < start {
+            return Err(ContractError::MigrationNotStarted { start });
+        }
+    }
+    let pause = query_pause_state(deps.as_ref())?;
+    if pause.paused {
+        return Err(ContractError::Paused {});
+    }
+    // an interleaved batch may draw on either map, whatever the split
+    if (num > 0 || opts.interleave) && pause.stake_migration_paused {
+        return Err(ContractError::StakeMigrationPaused {});
+    }
+    if (num_claims > 0 || opts.interleave) && pause.claim_migration_paused {
+        return Err(ContractError::ClaimMigrationPaused {});
+    }
+    if opts.consolidate
+        && !MIGRATE_ENTRIES_ENABLED
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+    {
+        return Err(ContractError::MigrateEntriesDisabled {});
+    }
+    record_batch_interval(deps.storage, &env)?;
+    let config = load_config(deps.storage)?;
+    // Funds leave under the override, accounting stays in `config.denom`.
+    let forward = match &opts.forward_denom {
+        Some(denom) => {
+            validate_native_denom(denom)?;
+            Denom::Native(denom.clone())
+        }
+        None => config.denom.clone(),
+    };
+    let dao_dao = DAO_DAO
+        .may_load(deps.storage)?
+        .ok_or(ContractError::DaoDaoNotSet {})?;
+    if let Some(expected) = DAO_DAO_CODE_ID.may_load(deps.storage)? {
+        let found = deps.querier.query_wasm_contract_info(&dao_dao)?.code_id;
+        if found != expected {
+            return Err(ContractError::UnexpectedCodeId { expected, found });
+        }
+    }
+    let version = DESTINATION_VERSION
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    assert_compatible_destination(deps.as_ref(), &dao_dao, &version)?;
+    // messages go through the proxy if one is set
+    let proxy = MIGRATION_PROXY.may_load(deps.storage)?;
+    let target = proxy.clone().unwrap_or_else(|| dao_dao.clone());
+    if !PRE_MIGRATION_SNAPSHOT.exists(deps.storage) {
+        return Err(ContractError::SnapshotNotTaken {});
+    }
+    let funds_to = match opts.funds_to.as_deref() {
+        Some(addr) => Some(deps.api.addr_validate(addr)?),
+        None => FUNDS_RECIPIENT.may_load(deps.storage)?,
+    };
+    if funds_to.is_some() && opts.callback.is_some() {
+        return Err(ContractError::CallbackWithFundsTo {});
+    }
+    let treasury = if opts.forfeit_claims {
+        let treasury = TREASURY
+            .may_load(deps.storage)?
+            .ok_or(ContractError::TreasuryNotSet {})?;
+        Some(treasury)
+    } else {
+        None
+    };
+    // A protected admin is skipped without counting towards the batch size, so
+    // bulk batches never strip the admin's own stake. It is migrated by a
+    // final batch sent without `protect_admin`.
+    let protected = if opts.protect_admin {
+        ADMIN.get(deps.as_ref())?
+    } else {
+        None
+    };
+    let (weights, claims, claims_exhausted) = match &opts.only {
+        Some(addrs) => {
+            let (weights, claims) = collect_addresses(deps.as_ref(), addrs)?;
+            (weights, claims, None)
+        }
+        None => {
+            let (num, num_claims) = if opts.interleave {
+                (num + num_claims, num + num_claims)
+            } else {
+                (num, num_claims)
+            };
+            let stake_cursor = STAKE_CURSOR.may_load(deps.storage)?.flatten();
+            let (mut weights, mut next_stake) = collect_stakes(
+                deps.as_ref(),
+                num,
+                protected.as_ref(),
+                opts.min_weight,
+                stake_cursor.as_ref(),
+            )?;
+            let claims_cursor = CLAIMS_CURSOR.may_load(deps.storage)?.flatten();
+            let (mut claims, mut claims_exhausted) = if opts.soonest_first {
+                let (claims, truncated) =
+                    collect_claims_by_maturity(deps.as_ref(), num_claims, protected.as_ref())?;
+                let exhausted = !truncated && (claims.len() as u64) < num_claims;
+                (claims, (num_claims > 0).then_some(exhausted))
+            } else {
+                let claims = collect_claims(
+                    deps.as_ref(),
+                    num_claims,
+                    protected.as_ref(),
+                    claims_cursor.as_ref(),
+                )?;
+                // the scan reached the end of the claims map
+                let exhausted = (claims.len() as u64) < num_claims;
+                (claims, (num_claims > 0).then_some(exhausted))
+            };
+            let mut cursor = next_claims_cursor(&claims, num_claims);
+            if opts.interleave {
+                let (stake_count, claim_count) =
+                    interleave_budget(num as usize, weights.len(), claims.len());
+                // claims left out of the split are still to migrate
+                claims_exhausted =
+                    claims_exhausted.map(|exhausted| exhausted && claim_count == claims.len());
+                if stake_count < weights.len() {
+                    weights.truncate(stake_count);
+                    next_stake = weights
+                        .last()
+                        .map(|(addr, _)| addr.clone())
+                        .or(stake_cursor);
+                }
+                if claim_count < claims.len() {
+                    claims.truncate(claim_count);
+                    // resume after the last claim taken
+                    cursor = claims
+                        .last()
+                        .map(|(addr, _)| addr.clone())
+                        .or(claims_cursor);
+                }
+            }
+            if num > 0 {
+                STAKE_CURSOR.save(deps.storage, &next_stake)?;
+            }
+            // a maturity ordered batch doesn't follow the cursor
+            if num_claims > 0 && !opts.soonest_first {
+                CLAIMS_CURSOR.save(deps.storage, &cursor)?;
+            }
+            (weights, claims, claims_exhausted)
+        }
+    };
+    // Collapsed and forfeited claims always fit, otherwise oversized claim
+    // vectors stay behind rather than failing the batch.
+    let (claims, oversized, capped) = if opts.collapse_claims || opts.forfeit_claims {
+        (claims, vec![], vec![])
+    } else {
+        let (claims, oversized) = defer_oversized_claims(claims)?;
+        let (claims, capped) = defer_claims_over_count(claims, opts.max_claims_per_addr);
+        (claims, oversized, capped)
+    };
+    // Forfeited claims never reach DAO DAO, their funds go to the treasury.
+    let (claims, forfeited) = if opts.forfeit_claims {
+        (vec![], claims)
+    } else {
+        (claims, vec![])
+    };
+    if let Some(max_release) = &opts.max_release {
+        assert_claims_release_before(&claims, max_release)?;
+    }
+    let ops = (weights.len() + claims.len()) as u64;
+    record_block_migrations(deps.storage, &env, ops)?;
+
+    // Remove all members. The snapshot changelog keeps only the first value
+    // of a height, so an earlier write in this block (an `AdjustStake`, say)
+    // still reads as the pre-block weight there and the removal as the next.
+    let mut sum = Uint128::zero();
+    let mut weight_sum = 0u64;
+    let mut removed_stakes = vec![];
+    for (addr, weight) in &weights {
+        STAKE.remove(deps.storage, addr);
+        let vote_weight = MEMBERS.may_load(deps.storage, addr)?.unwrap_or_default();
+        MEMBERS
+            .remove(deps.storage, addr, env.block.height)
+            .map_err(|err| ContractError::RemoveMember {
+                addr: addr.to_string(),
+                reason: err.to_string(),
+            })?;
+        sum += weight;
+        weight_sum += vote_weight;
+        record_receipt(deps.storage, &env, addr, vote_weight, *weight)?;
+        removed_stakes.push((addr.clone(), *weight, vote_weight));
+    }
+    let total = TOTAL.load(deps.storage)?;
+    let total = total
+        .checked_sub(weight_sum)
+        .ok_or(ContractError::TotalWeightUnderflow {
+            total,
+            removed: weight_sum,
+        })?;
+    TOTAL.save(deps.storage, &total, env.block.height)?;
+
+    // Also migrate claims
+    let claims_map = claims_storage();
+    for (addr, claims) in &claims {
+        let amount: Uint128 = claims.iter().map(|c| c.amount).sum();
+        sum += amount;
+        claims_map.remove(deps.storage, addr.clone());
+        record_receipt(deps.storage, &env, addr, 0, amount)?;
+    }
+    let mut forfeited_sum = Uint128::zero();
+    let mut forfeited_attrs = vec![];
+    for (addr, claims) in &forfeited {
+        let amount: Uint128 = claims.iter().map(|c| c.amount).sum();
+        forfeited_sum += amount;
+        claims_map.remove(deps.storage, addr.clone());
+        forfeited_attrs.push(("forfeited", format!("{addr}:{amount}")));
+    }
+    adjust_backing(deps.storage, Uint128::zero(), forfeited_sum)?;
+
+    let mut metrics = MIGRATION_METRICS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    metrics.batches_run += 1;
+    metrics.stakes_migrated += weights.len() as u64;
+    metrics.claims_migrated += claims.len() as u64;
+    metrics.total_moved += sum;
+    metrics.forwarded_weight += weight_sum;
+    // Catches accounting errors that compound across batches. A migration
+    // started before the backing was tracked has none to check against.
+    if let Some(backing) = MIGRATION_BACKING.may_load(deps.storage)? {
+        if metrics.total_moved > backing {
+            return Err(ContractError::ForwardExceedsBacking {
+                forwarded: metrics.total_moved,
+                backing,
+            });
+        }
+    }
+    MIGRATION_METRICS.save(deps.storage, &metrics)?;
+    BATCH_LOG.save(
+        deps.storage,
+        metrics.batches_run,
+        &BatchRecord {
+            batch_index: metrics.batches_run,
+            sender: info.sender.clone(),
+            height: env.block.height,
+            stake_count: weights.len() as u64,
+            claim_count: claims.len() as u64,
+        },
+    )?;
+    LAST_BATCH.save(
+        deps.storage,
+        &LastBatch {
+            height: env.block.height,
+            sender: info.sender.clone(),
+            stake_count: weights.len() as u64,
+            claim_count: claims.len() as u64,
+            sum,
+            remaining: total,
+        },
+    )?;
+
+    let (stake_count, claim_count) = (weights.len() as u64, claims.len() as u64);
+    let removed_claims = opts.callback.as_ref().map(|_| claims.clone());
+    let claims = if opts.collapse_claims {
+        claims.into_iter().map(collapse_claims).collect()
+    } else {
+        claims
+    };
+    // A batch moving no tokens sends nothing: DAO DAO rejects a
+    // `MigrateStakes` without funds and cw20 rejects a zero `Send`.
+    let outgoing = match sum.is_zero() {
+        true => vec![],
+        false => split_migration(weights, claims, opts.consolidate, &version)?,
+    };
+    // with no message to reply to, the callback is sent right away
+    let mut immediate_callback = None;
+    if let (Some(callback), true) = (&opts.callback, outgoing.is_empty()) {
+        immediate_callback = Some(callback.to_message(
+            &info.sender,
+            MigrationCallback {
+                batch: metrics.batches_run,
+                status: BatchStatus::Success,
+                failed: 0,
+                dao_dao: dao_dao.clone(),
+                migrated_total: metrics.total_moved,
+            },
+            vec![],
+        )?);
+    } else if let Some(callback) = opts.callback.clone() {
+        let removed_claims = removed_claims.unwrap_or_default();
+        let messages = outgoing
+            .iter()
+            .map(|out| PendingMessage {
+                stakes: removed_stakes
+                    .iter()
+                    .filter(|(addr, _, _)| out.stake_addrs.contains(addr))
+                    .cloned()
+                    .collect(),
+                claims: removed_claims
+                    .iter()
+                    .filter(|(addr, _)| out.claim_addrs.contains(addr))
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+        PENDING_BATCH.save(
+            deps.storage,
+            &PendingBatch {
+                batch: metrics.batches_run,
+                callback_to: info.sender.clone(),
+                callback,
+                messages,
+                succeeded: 0,
+                failed: 0,
+            },
+        )?;
+    }
+    if opts.confirm && !outgoing.is_empty() {
+        let messages: Vec<Vec<AddrStake>> = outgoing
+            .iter()
+            .map(|out| {
+                removed_stakes
+                    .iter()
+                    .filter(|(addr, _, _)| out.stake_addrs.contains(addr))
+                    .map(|(addr, stake, _)| (addr.clone(), *stake))
+                    .collect()
+            })
+            .collect();
+        PENDING_CONFIRMATION.save(deps.storage, &messages)?;
+    }
+    if opts.verify && !outgoing.is_empty() {
+        PENDING_VERIFICATION.save(
+            deps.storage,
+            &PendingVerification {
+                batch: metrics.batches_run,
+                remaining: outgoing.len() as u64,
+                sent: outgoing.iter().map(|out| out.staked).sum(),
+                accepted: Some(Uint128::zero()),
+            },
+        )?;
+    }
+
+    let denom = transfer_denom(&forward);
+    // The sends below only settle after this call returns, so the balance
+    // queried here still includes the batch.
+    let balance_after = contract_balance(deps.as_ref(), &env)?
+        .saturating_sub(sum)
+        .saturating_sub(forfeited_sum);
+    let mut res = Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("amount", coin_to_string(sum, &denom))
+        .add_attribute("denom_type", denom_type(&forward))
+        .add_attribute("remaining_total", total.to_string())
+        .add_attribute("migrated_at", env.block.height.to_string())
+        .add_attribute("contract_balance_after", balance_after.to_string())
+        .add_attribute(
+            "receipt_root",
+            query_receipt_root(deps.storage)?.root.to_base64(),
+        )
+        .add_attributes((!oversized.is_empty()).then(|| ("oversized_claims", oversized.join(","))))
+        .add_attributes((!capped.is_empty()).then(|| ("capped_claims", capped.join(","))))
+        .add_attributes(
+            claims_exhausted.map(|exhausted| ("claims_exhausted", exhausted.to_string())),
+        )
+        .add_attributes(forfeited_attrs)
+        .set_data(to_json_binary(&batch_feedback(
+            deps.as_ref(),
+            ops,
+            num,
+            num_claims,
+        )?)?);
+    // with a completion threshold, dust stakes may still remain
+    if migration_complete(deps.storage)? {
+        res = res.add_event(
+            Event::new("migration_complete").add_attribute("remaining_total", total.to_string()),
+        );
+    }
+    if let Some(treasury) = &treasury {
+        if !forfeited_sum.is_zero() {
+            res = res
+                .add_message(send_tokens(&forward, treasury, forfeited_sum)?)
+                .add_event(funds_routed(treasury, &denom, forfeited_sum));
+        }
+    }
+    // With an override the whole batch is paid to `funds_to` up front and the
+    // DAO DAO messages carry no funds.
+    if let Some(funds_to) = &funds_to {
+        if !sum.is_zero() {
+            res = res
+                .add_message(send_tokens(&forward, funds_to, sum)?)
+                .add_event(funds_routed(funds_to, &denom, sum));
+        }
+        res = res.add_attribute("funds_to", funds_to);
+    } else if !sum.is_zero() {
+        res = res.add_event(funds_routed(&target, &denom, sum));
+    }
+    if let Some(proxy) = &proxy {
+        res = res
+            .add_attribute("proxy", proxy)
+            .add_attribute("destination", &dao_dao);
+    }
+    for out in outgoing {
+        if opts.debug {
+            res = res.add_attribute(
+                "outgoing_payload",
+                String::from_utf8_lossy(out.msg.as_slice()),
+            );
+        }
+        let execute = match funds_to {
+            Some(_) => WasmMsg::Execute {
+                contract_addr: target.to_string(),
+                msg: out.msg,
+                funds: vec![],
+            }
+            .into(),
+            None => build_transfer(&forward, out.amount, &target, out.msg)?,
+        };
+        res = res.add_submessage(if opts.callback.is_some() {
+            SubMsg::reply_always(execute, MIGRATE_REPLY_ID)
+        } else {
+            SubMsg::reply_on_success(execute, MIGRATE_REPLY_ID)
+        });
+    }
+    if let Some(callback) = immediate_callback {
+        res = res.add_message(callback);
+    }
+    if let Some(monitor) = MONITOR.may_load(deps.storage)? {
+        let notification = WasmMsg::Execute {
+            contract_addr: monitor.into_string(),
+            msg: to_json_binary(&MonitorExecuteMsg::BatchNotification {
+                index: metrics.batches_run,
+                stake_count,
+                claim_count,
+                remaining: total,
+            })?,
+            funds: vec![],
+        };
+        res = res.add_submessage(SubMsg::reply_on_error(notification, MONITOR_REPLY_ID));
+    }
+    Ok(res)
+}
+
+/// Accounts for funds a batch sends to `recipient`, one event per recipient.
+fn funds_routed(recipient: &Addr, denom: &str, amount: Uint128) -> Event {
+    Event::new("funds_routed")
+        .add_attribute("recipient", recipient)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+}
+
+/// Suggests the next batch sizes by scaling the requested ones by
+/// `target_ops / ops`. A batch that migrated nothing keeps its sizes.
+fn batch_feedback(deps: Deps, ops: u64, num: u64, num_claims: u64) -> StdResult<BatchFeedback> {
+    let target_ops = TARGET_OPS
+        .may_load(deps.storage)?
+        .unwrap_or(DEFAULT_TARGET_OPS);
+    let scale = |requested: u64| match ops {
+        0 => requested,
+        _ => Uint128::from(requested)
+            .multiply_ratio(target_ops, ops)
+            .u128() as u64,
+    };
+    Ok(BatchFeedback {
+        ops,
+        target_ops,
+        next_num: scale(num),
+        next_num_claims: scale(num_claims),
+    })
+}
+
+/// Delivers `msg` to `to` along with `amount` of `denom`: attached as funds for
+/// a native denom, or as a cw20 `Send` whose hook carries `msg`, in which case
+/// `to` must handle cw20 `Receive`.
+pub fn build_transfer(
+    denom: &Denom,
+    amount: Uint128,
+    to: &Addr,
+    msg: Binary,
+) -> StdResult<CosmosMsg> {
+    Ok(match denom {
+        Denom::Native(denom) => WasmMsg::Execute {
+            contract_addr: to.to_string(),
+            msg,
+            funds: coins(amount.u128(), denom),
+        }
+        .into(),
+        Denom::Cw20(token) => WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: to.to_string(),
+                amount,
+                msg,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    })
+}
+
+/// Merges an address's claims into one for their summed amount, released at
+/// the latest `release_at`. Claims mixing height and time expirations cannot
+/// be ordered and are left as they are.
+pub fn collapse_claims((addr, claims): (Addr, Vec<Claim>)) -> (Addr, Vec<Claim>) {
+    let mut iter = claims.iter();
+    let Some(first) = iter.next() else {
+        return (addr, claims);
+    };
+    let mut release_at = first.release_at;
+    for claim in iter {
+        match claim.release_at.partial_cmp(&release_at) {
+            Some(Ordering::Greater) => release_at = claim.release_at,
+            Some(_) => {}
+            None => return (addr, claims),
+        }
+    }
+    let amount = claims.iter().map(|claim| claim.amount).sum();
+    (addr, vec![Claim { amount, release_at }])
+}
+
+/// One DAO DAO message of a batch.
+struct OutgoingMigration {
+    msg: Binary,
+    /// tokens sent along with the message
+    amount: Uint128,
+    /// staked tokens among `amount`, the rest being claims
+    staked: Uint128,
+    /// addresses whose stake is in the message
+    stake_addrs: Vec<Addr>,
+    /// addresses whose claims are in the message
+    claim_addrs: Vec<Addr>,
+}
+
+/// `MigrateStakes` in the shape `version` takes.
+fn migrate_stakes_msg(
+    version: &DestinationVersion,
+    weights: Vec<(Addr, Uint128)>,
+    claims: Vec<(Addr, Vec<Claim>)>,
+) -> StdResult<Binary> {
+    match version {
+        DestinationVersion::V1 => {
+            to_json_binary(&dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+                weights,
+                claims,
+            })
+        }
+        DestinationVersion::V2 => to_json_binary(&DaoDaoExecuteMsg::MigrateStakes {
+            stakes: weights
+                .into_iter()
+                .map(|(address, amount)| StakeEntry { address, amount })
+                .collect(),
+            claims: claims
+                .into_iter()
+                .map(|(address, claims)| ClaimsEntry { address, claims })
+                .collect(),
+        }),
+    }
+}
+
+/// Splits a batch into messages of at most `MAX_ENTRIES_PER_MESSAGE` entries
+/// so that large batches stay within message size limits. Always returns at
+/// least one message.
+fn split_migration(
+    mut weights: Vec<(Addr, Uint128)>,
+    mut claims: Vec<(Addr, Vec<Claim>)>,
+    consolidate: bool,
+    version: &DestinationVersion,
+) -> StdResult<Vec<OutgoingMigration>> {
+    let claimed = |claims: &[Claim]| claims.iter().map(|c| c.amount).sum::<Uint128>();
+    let mut outgoing = vec![];
+
+    if consolidate {
+        let mut entries = consolidate_entries(weights, claims);
+        loop {
+            let chunk: Vec<_> = entries
+                .drain(..entries.len().min(MAX_ENTRIES_PER_MESSAGE))
+                .collect();
+            let staked = chunk.iter().map(|entry| entry.weight).sum();
+            let amount = chunk
+                .iter()
+                .fold(staked, |acc, entry| acc + claimed(&entry.claims));
+            let stake_addrs = chunk
+                .iter()
+                .filter(|entry| !entry.weight.is_zero())
+                .map(|entry| entry.addr.clone())
+                .collect();
+            let claim_addrs = chunk
+                .iter()
+                .filter(|entry| !entry.claims.is_empty())
+                .map(|entry| entry.addr.clone())
+                .collect();
+            outgoing.push(OutgoingMigration {
+                msg: to_json_binary(&DaoDaoExecuteMsg::MigrateEntries { entries: chunk })?,
+                amount,
+                staked,
+                stake_addrs,
+                claim_addrs,
+            });
+            if entries.is_empty() {
+                return Ok(outgoing);
+            }
+        }
+    }
+
+    loop {
+        let weights_chunk: Vec<_> = weights
+            .drain(..weights.len().min(MAX_ENTRIES_PER_MESSAGE))
+            .collect();
+        let claims_chunk: Vec<_> = claims
+            .drain(
+                ..claims
+                    .len()
+                    .min(MAX_ENTRIES_PER_MESSAGE - weights_chunk.len()),
+            )
+            .collect();
+        let staked = weights_chunk.iter().map(|(_, weight)| *weight).sum();
+        let amount = claims_chunk
+            .iter()
+            .fold(staked, |acc, (_, claims)| acc + claimed(claims));
+        let stake_addrs = weights_chunk.iter().map(|(addr, _)| addr.clone()).collect();
+        let claim_addrs = claims_chunk.iter().map(|(addr, _)| addr.clone()).collect();
+        outgoing.push(OutgoingMigration {
+            msg: migrate_stakes_msg(version, weights_chunk, claims_chunk)?,
+            amount,
+            staked,
+            stake_addrs,
+            claim_addrs,
+        });
+        if weights.is_empty() && claims.is_empty() {
+            return Ok(outgoing);
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        MIGRATE_REPLY_ID => reply_migrate(deps, env, msg),
+        // telemetry must not block migration
+        MONITOR_REPLY_ID => {
+            Ok(Response::new().add_attribute("monitor_failed", msg.result.unwrap_err()))
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+fn reply_migrate(mut deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let result = msg.result.into_result();
+    let mut res = Response::new();
+    // staked tokens of a failed message, restored and so no longer sent
+    let mut failed_staked = None;
+    if let Some(pending) = PENDING_BATCH.may_load(deps.storage)? {
+        let (callback_res, staked) =
+            reply_callback(deps.branch(), &env, pending, result.as_ref().err())?;
+        res = callback_res;
+        failed_staked = staked;
+    }
+    if PENDING_CONFIRMATION.exists(deps.storage) {
+        reply_confirm(deps.branch(), &env, result.is_ok())?;
+    }
+
+    let accepted = match result {
+        Ok(response) => {
+            let data = match response.data {
+                Some(data) => parse_execute_response_data(&data)?.data,
+                None => None,
+            };
+            let batch = MIGRATION_METRICS
+                .may_load(deps.storage)?
+                .unwrap_or_default()
+                .batches_run;
+            LAST_REPLY_DATA.save(
+                deps.storage,
+                &LastReplyData {
+                    batch,
+                    data: data.clone(),
+                },
+            )?;
+            // only a verified batch reads the data, anything but a
+            // `MigrationAcceptance` is taken as no data
+            if PENDING_VERIFICATION.exists(deps.storage) {
+                Ok(data
+                    .and_then(|data| from_json::<MigrationAcceptance>(&data).ok())
+                    .map(|acceptance| acceptance.weight))
+            } else {
+                Ok(None)
+            }
+        }
+        Err(_) => Err(failed_staked.unwrap_or_default()),
+    };
+    reply_verify(deps, accepted, res)
+}
+
+/// Tracks the outcome of each message of a batch sent with a callback. A
+/// failed message has its stakes and claims restored, as its funds never left
+/// the contract. After the last reply the callback is sent with the status of
+/// the whole batch.
+fn reply_callback(
+    deps: DepsMut,
+    env: &Env,
+    mut pending: PendingBatch,
+    error: Option<&String>,
+) -> Result<(Response, Option<Uint128>), ContractError> {
+    let mut res = Response::new()
+        .add_attribute("action", "migrate_reply")
+        .add_attribute("batch", pending.batch.to_string());
+    let message = pending.messages.remove(0);
+    let failed_staked = match error {
+        None => {
+            pending.succeeded += 1;
+            None
+        }
+        Some(error) => {
+            pending.failed += 1;
+            restore_message(deps.storage, env, &message)?;
+            res = res.add_attribute("error", error);
+            Some(message.stakes.iter().map(|(_, stake, _)| *stake).sum())
+        }
+    };
+
+    if !pending.messages.is_empty() {
+        PENDING_BATCH.save(deps.storage, &pending)?;
+        return Ok((res, failed_staked));
+    }
+    PENDING_BATCH.remove(deps.storage);
+    let status = match (pending.succeeded, pending.failed) {
+        (_, 0) => BatchStatus::Success,
+        (0, _) => BatchStatus::Failure,
+        _ => BatchStatus::Partial,
+    };
+    let callback = pending.callback.to_message(
+        &pending.callback_to,
+        MigrationCallback {
+            batch: pending.batch,
+            status,
+            failed: pending.failed,
+            dao_dao: DAO_DAO.load(deps.storage)?,
+            migrated_total: query_migrated_total(deps.as_ref())?.amount,
+        },
+        vec![],
+    )?;
+    Ok((res.add_message(callback), failed_staked))
+}
+
+/// Puts back what a failed batch message removed from storage.
+fn restore_message(
+    storage: &mut dyn Storage,
+    env: &Env,
+    message: &PendingMessage,
+) -> StdResult<()> {
+    let mut metrics = MIGRATION_METRICS.may_load(storage)?.unwrap_or_default();
+    let mut weight_sum = 0u64;
+    if !message.stakes.is_empty() {
+        STAKE_CURSOR.save(storage, &None)?;
+    }
+    for (addr, stake, weight) in &message.stakes {
+        FAILED.save(storage, addr, &Empty {})?;
+        STAKE.save(storage, addr, stake)?;
+        MEMBERS.save(storage, addr, weight, env.block.height)?;
+        weight_sum += weight;
+        unrecord_receipt(storage, addr, *weight, *stake)?;
+        metrics.total_moved -= *stake;
+    }
+    TOTAL.update(storage, env.block.height, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default() + weight_sum)
+    })?;
+    metrics.forwarded_weight -= weight_sum;
+
+    let claims_map = claims_storage();
+    if !message.claims.is_empty() {
+        CLAIMS_CURSOR.save(storage, &None)?;
+    }
+    for (addr, claims) in &message.claims {
+        FAILED.save(storage, addr, &Empty {})?;
+        claims_map.save(storage, addr.clone(), claims)?;
+        let amount = claims.iter().map(|c| c.amount).sum();
+        unrecord_receipt(storage, addr, 0, amount)?;
+        metrics.total_moved -= amount;
+    }
+
+    metrics.stakes_migrated -= message.stakes.len() as u64;
+    metrics.claims_migrated -= message.claims.len() as u64;
+    MIGRATION_METRICS.save(storage, &metrics)
+}
+
+/// Queries DAO DAO for the voting power of each address whose stake the
+/// replied message carried, marking its receipt confirmed if the power covers
+/// the stake sent. Nothing is recorded for a failed message.
+fn reply_confirm(deps: DepsMut, env: &Env, succeeded: bool) -> StdResult<()> {
+    let mut messages = PENDING_CONFIRMATION.load(deps.storage)?;
+    let stakes = messages.remove(0);
+    if messages.is_empty() {
+        PENDING_CONFIRMATION.remove(deps.storage);
+    } else {
+        PENDING_CONFIRMATION.save(deps.storage, &messages)?;
+    }
+    if !succeeded {
+        return Ok(());
+    }
+
+    let dao_dao = DAO_DAO.load(deps.storage)?;
+    for (addr, stake) in stakes {
+        // Voting power is read as of the start of a block, so the next height
+        // is asked for to include this block's migration.
+        let res: VotingPowerAtHeightResponse = deps.querier.query_wasm_smart(
+            &dao_dao,
+            &dao_voting_token_staked::msg::QueryMsg::VotingPowerAtHeight {
+                address: addr.to_string(),
+                height: Some(env.block.height + 1),
+            },
+        )?;
+        MIGRATED.update(deps.storage, &addr, |receipt| -> StdResult<_> {
+            let mut receipt = receipt.ok_or_else(|| StdError::not_found("MigrationReceipt"))?;
+            receipt.confirmed = Some(res.power >= stake);
+            Ok(receipt)
+        })?;
+    }
+    Ok(())
+}
+
+/// Adds up the weight DAO DAO reports as accepted for each message of the
+/// batch and, after the last one, records a discrepancy if the total differs
+/// from what was sent or any message reported nothing. `accepted` is the
+/// staked tokens of the message if it failed.
+fn reply_verify(
+    deps: DepsMut,
+    accepted: Result<Option<Uint128>, Uint128>,
+    res: Response,
+) -> Result<Response, ContractError> {
+    let Some(mut pending) = PENDING_VERIFICATION.may_load(deps.storage)? else {
+        return Ok(res);
+    };
+    match accepted {
+        Ok(accepted) => pending.accepted = pending.accepted.zip(accepted).map(|(a, b)| a + b),
+        Err(restored) => pending.sent -= restored,
+    }
+    pending.remaining = pending.remaining.saturating_sub(1);
+
+    let mut res = res
+        .add_attribute("action", "verify_migration")
+        .add_attribute("batch", pending.batch.to_string());
+    if pending.remaining > 0 {
+        PENDING_VERIFICATION.save(deps.storage, &pending)?;
+        return Ok(res);
+    }
+    PENDING_VERIFICATION.remove(deps.storage);
+    // A destination returning no `MigrationAcceptance`, like the bundled
+    // dao-voting-token-staked, or data of its own, leaves nothing to check
+    // against.
+    if pending.accepted.is_none() {
+        res = res.add_attribute("verify_skipped", "no acceptance data");
+    } else if pending.accepted != Some(pending.sent) {
+        MIGRATION_DISCREPANCIES.save(
+            deps.storage,
+            pending.batch,
+            &Discrepancy {
+                sent: pending.sent,
+                accepted: pending.accepted,
+            },
+        )?;
+        res = res.add_attribute("discrepancy", "true");
+    }
+    Ok(res)
+}
+
+/// Adds a migrated stake or claims to the address's receipt.
+fn record_receipt(
+    storage: &mut dyn Storage,
+    env: &Env,
+    addr: &Addr,
+    weight: u64,
+    amount: Uint128,
+) -> StdResult<()> {
+    let mut receipt = MIGRATED
+        .may_load(storage, addr)?
+        .unwrap_or_else(|| MigrationReceipt {
+            addr: addr.clone(),
+            weight: 0,
+            amount: Uint128::zero(),
+            height: env.block.height,
+            confirmed: None,
+        });
+    receipt.weight += weight;
+    receipt.amount += amount;
+    receipt.height = env.block.height;
+    receipt.confirmed = None;
+    chain_receipt_root(storage, RECEIPT_MIGRATED, addr, weight, amount)?;
+    MIGRATED.save(storage, addr, &receipt)
+}
+
+const RECEIPT_MIGRATED: u8 = 0;
+const RECEIPT_RESTORED: u8 = 1;
+
+/// Hashes an entry into `RECEIPT_ROOT`, as documented on
+/// `QueryMsg::ReceiptRoot`.
+fn chain_receipt_root(
+    storage: &mut dyn Storage,
+    tag: u8,
+    addr: &Addr,
+    weight: u64,
+    amount: Uint128,
+) -> StdResult<()> {
+    let root = query_receipt_root(storage)?.root;
+    let digest = Sha256::new()
+        .chain_update(root.as_slice())
+        .chain_update([tag])
+        .chain_update(weight.to_be_bytes())
+        .chain_update(amount.u128().to_be_bytes())
+        .chain_update(addr.as_bytes())
+        .finalize();
+    RECEIPT_ROOT.save(storage, &Binary::from(digest.as_slice()))
+}
+
+/// Takes a restored stake or claims back off the address's receipt, removing
+/// it once nothing is left.
+fn unrecord_receipt(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    weight: u64,
+    amount: Uint128,
+) -> StdResult<()> {
+    let Some(mut receipt) = MIGRATED.may_load(storage, addr)? else {
+        return Ok(());
+    };
+    chain_receipt_root(storage, RECEIPT_RESTORED, addr, weight, amount)?;
+    receipt.weight = receipt.weight.saturating_sub(weight);
+    receipt.amount = receipt.amount.saturating_sub(amount);
+    if receipt.weight == 0 && receipt.amount.is_zero() {
+        MIGRATED.remove(storage, addr);
+        Ok(())
+    } else {
+        MIGRATED.save(storage, addr, &receipt)
+    }
+}
+
+fn is_skipped(storage: &dyn Storage, protected: Option<&Addr>, addr: &Addr) -> bool {
+    protected == Some(addr) || EXCLUDE.has(storage, addr)
+}
+
+/// Selects up to `num` stakes, as token amounts, in address order after
+/// `start_after`, skipping excluded addresses, the protected admin and
+/// members below `min_weight` without counting them towards `num`. Reads at
+/// most `MAX_DISTRIBUTION_SCAN` stakes, and also returns the last one read if
+/// any are left after it, for the next batch to resume from.
+fn collect_stakes(
+    deps: Deps,
+    num: u64,
+    protected: Option<&Addr>,
+    min_weight: Option<u64>,
+    start_after: Option<&Addr>,
+) -> StdResult<(Vec<AddrStake>, Option<Addr>)> {
+    let mut entries = STAKE.range(
+        deps.storage,
+        start_after.map(Bound::exclusive),
+        None,
+        Order::Ascending,
+    );
+    let mut stakes = vec![];
+    let mut last_read = None;
+    let mut read = 0;
+    while (stakes.len() as u64) < num && read < MAX_DISTRIBUTION_SCAN {
+        let Some(item) = entries.next() else {
+            break;
+        };
+        read += 1;
+        let (addr, stake) = item?;
+        last_read = Some(addr.clone());
+        if is_skipped(deps.storage, protected, &addr) {
+            continue;
+        }
+        if let Some(min_weight) = min_weight {
+            if MEMBERS.may_load(deps.storage, &addr)?.unwrap_or_default() < min_weight {
+                continue;
+            }
+        }
+        stakes.push((addr, stake));
+    }
+    Ok((stakes, last_read.filter(|_| entries.next().is_some())))
+}
+
+/// Selects up to `num_claims` addresses from the claims map, with the same
+/// skipping rules as `collect_stakes`.
+fn collect_claims(
+    deps: Deps,
+    num_claims: u64,
+    protected: Option<&Addr>,
+    start_after: Option<&Addr>,
+) -> StdResult<Vec<(Addr, Vec<Claim>)>> {
+    let claims_map = claims_storage();
+    let start = start_after.map(|addr| Bound::exclusive(addr.clone()));
+    claims_map
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((addr, _)) if is_skipped(deps.storage, protected, addr)))
+        .take(num_claims as usize)
+        .collect()
+}
+
+/// Selects up to `num_claims` addresses among the first
+/// `MAX_DISTRIBUTION_SCAN` of the claims map, soonest maturing first, with the
+/// same skipping rules as `collect_claims`. Also returns whether the claims map
+/// holds more addresses than were read.
+fn collect_claims_by_maturity(
+    deps: Deps,
+    num_claims: u64,
+    protected: Option<&Addr>,
+) -> StdResult<(Vec<AddrClaims>, bool)> {
+    let claims_map = claims_storage();
+    let mut all = claims_map.range(deps.storage, None, None, Order::Ascending);
+    let mut claims = all
+        .by_ref()
+        .filter(|item| !matches!(item, Ok((addr, _)) if is_skipped(deps.storage, protected, addr)))
+        .take(MAX_DISTRIBUTION_SCAN)
+        .collect::<StdResult<Vec<_>>>()?;
+    let truncated = all.next().is_some();
+    // stable, so equal release points stay in address order
+    claims.sort_by_key(|(_, claims)| {
+        claims
+            .iter()
+            .map(|claim| release_key(&claim.release_at))
+            .min()
+    });
+    claims.truncate(num_claims as usize);
+    Ok((claims, truncated))
+}
+
+/// Sort key of a `release_at`, as `Expiration` is only partially ordered:
+/// heights before times and `Never` last, each kind by its value.
+fn release_key(release_at: &Expiration) -> (u8, u64) {
+    match release_at {
+        Expiration::AtHeight(height) => (0, *height),
+        Expiration::AtTime(time) => (1, time.nanos()),
+        Expiration::Never {} => (2, 0),
+    }
+}
+
+/// Splits off addresses whose claims serialize to more than
+/// `MAX_CLAIMS_PAYLOAD` bytes, returning them by address. DAO DAO replaces an
+/// address's claims on every `MigrateStakes`, so such claims can't be spread
+/// over several messages and are left in place instead. They can be migrated
+/// by a batch with `collapse_claims`.
+fn defer_oversized_claims(claims: Vec<AddrClaims>) -> StdResult<(Vec<AddrClaims>, Vec<String>)> {
+    let mut fitting = vec![];
+    let mut oversized = vec![];
+    for (addr, claims) in claims {
+        if to_json_vec(&claims)?.len() > MAX_CLAIMS_PAYLOAD {
+            oversized.push(addr.to_string());
+        } else {
+            fitting.push((addr, claims));
+        }
+    }
+    Ok((fitting, oversized))
+}
+
+/// Splits off addresses holding more than `max` claims, returning them by
+/// address. As with oversized claims, they are left in place whole rather than
+/// migrated in part.
+fn defer_claims_over_count(
+    claims: Vec<AddrClaims>,
+    max: Option<u32>,
+) -> (Vec<AddrClaims>, Vec<String>) {
+    let Some(max) = max else {
+        return (claims, vec![]);
+    };
+    let (capped, fitting): (Vec<_>, Vec<_>) = claims
+        .into_iter()
+        .partition(|(_, claims)| claims.len() > max as usize);
+    (
+        fitting,
+        capped
+            .into_iter()
+            .map(|(addr, _)| addr.to_string())
+            .collect(),
+    )
+}
+
+/// Fails on the first address holding a claim released after `max_release`.
+/// Claims counted in the other unit than `max_release` can't be compared and
+/// pass.
+fn assert_claims_release_before(
+    claims: &[AddrClaims],
+    max_release: &Expiration,
+) -> Result<(), ContractError> {
+    for (addr, claims) in claims {
+        if claims.iter().any(|claim| claim.release_at > *max_release) {
+            return Err(ContractError::ClaimReleaseTooFar {
+                addr: addr.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Stakes and claims held by `addrs`, for a batch retrying those addresses.
+fn collect_addresses(deps: Deps, addrs: &[Addr]) -> StdResult<(Vec<AddrStake>, Vec<AddrClaims>)> {
+    let claims_map = claims_storage();
+    let mut weights = vec![];
+    let mut claims = vec![];
+    for addr in addrs {
+        if let Some(stake) = STAKE.may_load(deps.storage, addr)? {
+            weights.push((addr.clone(), stake));
+        }
+        if let Some(addr_claims) = claims_map.may_load(deps.storage, addr.clone())? {
+            claims.push((addr.clone(), addr_claims));
+        }
+    }
+    Ok((weights, claims))
+}
+
+/// Cursor to store after a claims batch: its last address, or `None` once the
+/// batch came up short and the end of the claims map was reached.
+fn next_claims_cursor(claims: &[(Addr, Vec<Claim>)], num_claims: u64) -> Option<Addr> {
+    match claims.last() {
+        Some((addr, _)) if claims.len() as u64 == num_claims => Some(addr.clone()),
+        _ => None,
+    }
+}
+
+/// Adds `count` migrated entries to the current block's tally, rejecting the
+/// batch if the tally would exceed `MAX_PER_BLOCK`. The tally restarts
+/// whenever the block height advances.
+fn record_block_migrations(
+    storage: &mut dyn Storage,
+    env: &Env,
+    count: u64,
+) -> Result<(), ContractError> {
+    let Some(max) = MAX_PER_BLOCK.may_load(storage)? else {
+        return Ok(());
+    };
+    let migrated = match BLOCK_MIGRATIONS.may_load(storage)? {
+        Some((height, migrated)) if height == env.block.height => migrated,
+        _ => 0,
+    } + count;
+    if migrated > max {
+        return Err(ContractError::BlockLimitExceeded { max });
+    }
+    BLOCK_MIGRATIONS.save(storage, &(env.block.height, migrated))?;
+    Ok(())
+}
+
+/// Fails if the minimum interval since the previous batch has not elapsed yet,
+/// otherwise records this batch's block as the previous one.
+fn record_batch_interval(storage: &mut dyn Storage, env: &Env) -> Result<(), ContractError> {
+    if let (Some(interval), Some(last)) = (
+        MIN_BATCH_INTERVAL.may_load(storage)?,
+        LAST_BATCH_BLOCK.may_load(storage)?,
+    ) {
+        let earliest = interval.after(&last);
+        if !earliest.is_expired(&env.block) {
+            return Err(ContractError::BatchTooSoon { earliest });
+        }
+    }
+    LAST_BATCH_BLOCK.save(storage, &env.block)?;
+    Ok(())
+}
+
+/// Groups a batch by address so that an address with both a stake and claims
+/// is sent to DAO DAO as a single entry. Entries are ordered by address.
+pub fn consolidate_entries(
+    weights: Vec<(Addr, Uint128)>,
+    claims: Vec<(Addr, Vec<Claim>)>,
+) -> Vec<MigrateEntry> {
+    let mut entries: BTreeMap<Addr, MigrateEntry> = BTreeMap::new();
+    for (addr, weight) in weights {
+        entries.insert(
+            addr.clone(),
+            MigrateEntry {
+                addr,
+                weight,
+                claims: vec![],
+            },
+        );
+    }
+    for (addr, claims) in claims {
+        entries
+            .entry(addr.clone())
+            .or_insert_with(|| MigrateEntry {
+                addr,
+                weight: Uint128::zero(),
+                claims: vec![],
+            })
+            .claims
+            .extend(claims);
+    }
+    entries.into_values().collect()
+}
+
+/// The denom string put on the wire when forwarding funds: the native denom,
+/// or the cw20 contract address.
+pub fn transfer_denom(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => denom.clone(),
+        Denom::Cw20(addr) => addr.to_string(),
+    }
+}
+
+/// Moves `MIGRATION_BACKING` by stakes or claims written or removed outside a
+/// batch. Nothing is tracked before the snapshot sets it. A snapshot still
+/// being prepared is restarted, as it may have read the entry already.
+fn adjust_backing(storage: &mut dyn Storage, added: Uint128, removed: Uint128) -> StdResult<()> {
+    SNAPSHOT_PROGRESS.remove(storage);
+    if let Some(backing) = MIGRATION_BACKING.may_load(storage)? {
+        MIGRATION_BACKING.save(storage, &(backing + added).saturating_sub(removed))?;
+    }
+    Ok(())
+}
+
+/// Loads the config, failing with `ConfigNotFound` rather than a storage
+/// error so clients can tell an uninitialized contract apart.
+fn load_config(storage: &dyn Storage) -> Result<Config, ContractError> {
+    CONFIG
+        .may_load(storage)?
+        .ok_or(ContractError::ConfigNotFound {})
+}
+
+/// Rejects strings that can't be a native denom: 3 to 128 characters, starting
+/// with a letter, then letters, digits or `/:._-`.
+fn validate_native_denom(denom: &str) -> Result<(), ContractError> {
+    let mut chars = denom.chars();
+    let valid = (3..=128).contains(&denom.len())
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c));
+    if !valid {
+        return Err(ContractError::InvalidForwardDenom {
+            denom: denom.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether funds are forwarded as native tokens or as a cw20 `Send`.
+pub fn denom_type(denom: &Denom) -> &'static str {
+    match denom {
+        Denom::Native(_) => "native",
+        Denom::Cw20(_) => "cw20",
+    }
+}
+
+pub fn execute_update_dao_dao_code_id(
+    deps: DepsMut,
+    info: MessageInfo,
+    code_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match code_id {
+        Some(code_id) => DAO_DAO_CODE_ID.save(deps.storage, &code_id)?,
+        None => DAO_DAO_CODE_ID.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_dao_dao_code_id")
+        .add_attribute(
+            "code_id",
+            code_id.map_or_else(|| "none".to_string(), |id| id.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_max_per_block(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_per_block: Option<u64>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match max_per_block {
+        Some(max) => MAX_PER_BLOCK.save(deps.storage, &max)?,
+        None => MAX_PER_BLOCK.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_max_per_block")
+        .add_attribute(
+            "max_per_block",
+            max_per_block.map_or_else(|| "none".to_string(), |max| max.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_min_batch_interval(
+    deps: DepsMut,
+    info: MessageInfo,
+    interval: Option<Duration>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match interval {
+        Some(interval) => MIN_BATCH_INTERVAL.save(deps.storage, &interval)?,
+        None => MIN_BATCH_INTERVAL.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_min_batch_interval")
+        .add_attribute(
+            "interval",
+            interval.map_or_else(|| "none".to_string(), |interval| interval.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_migration_start(
+    deps: DepsMut,
+    info: MessageInfo,
+    start: Option<u64>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match start {
+        Some(start) => MIGRATION_START.save(deps.storage, &start)?,
+        None => MIGRATION_START.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_migration_start")
+        .add_attribute(
+            "start",
+            start.map_or_else(|| "none".to_string(), |start| start.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_completion_threshold(
+    deps: DepsMut,
+    info: MessageInfo,
+    threshold: Option<u64>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match threshold {
+        Some(threshold) => COMPLETION_THRESHOLD.save(deps.storage, &threshold)?,
+        None => COMPLETION_THRESHOLD.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_completion_threshold")
+        .add_attribute(
+            "threshold",
+            threshold.map_or_else(|| "none".to_string(), |threshold| threshold.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_target_ops(
+    deps: DepsMut,
+    info: MessageInfo,
+    target_ops: Option<u64>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    match target_ops {
+        Some(target) => TARGET_OPS.save(deps.storage, &target)?,
+        None => TARGET_OPS.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_target_ops")
+        .add_attribute(
+            "target_ops",
+            target_ops.map_or_else(|| "none".to_string(), |target| target.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_treasury(
+    deps: DepsMut,
+    info: MessageInfo,
+    treasury: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let treasury = treasury
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    match &treasury {
+        Some(treasury) => TREASURY.save(deps.storage, treasury)?,
+        None => TREASURY.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_treasury")
+        .add_attribute(
+            "treasury",
+            treasury.map_or_else(|| "none".to_string(), |treasury| treasury.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_auto_pause_on_shortfall(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if enabled {
+        AUTO_PAUSE_ON_SHORTFALL.save(deps.storage, &true)?;
+    } else {
+        AUTO_PAUSE_ON_SHORTFALL.remove(deps.storage);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_auto_pause_on_shortfall")
+        .add_attribute("enabled", enabled.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_migrate_entries(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if enabled {
+        MIGRATE_ENTRIES_ENABLED.save(deps.storage, &true)?;
+    } else {
+        MIGRATE_ENTRIES_ENABLED.remove(deps.storage);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_migrate_entries")
+        .add_attribute("enabled", enabled.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_destination_version(
+    deps: DepsMut,
+    info: MessageInfo,
+    version: DestinationVersion,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if let Some(dao_dao) = DAO_DAO.may_load(deps.storage)? {
+        assert_compatible_destination(deps.as_ref(), &dao_dao, &version)?;
+    }
+    DESTINATION_VERSION.save(deps.storage, &version)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_destination_version")
+        .add_attribute("version", format!("{version:?}"))
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_monitor(
+    deps: DepsMut,
+    info: MessageInfo,
+    monitor: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let monitor = monitor
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    match &monitor {
+        Some(monitor) => MONITOR.save(deps.storage, monitor)?,
+        None => MONITOR.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_monitor")
+        .add_attribute(
+            "monitor",
+            monitor.map_or_else(|| "none".to_string(), |monitor| monitor.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_migration_proxy(
+    deps: DepsMut,
+    info: MessageInfo,
+    proxy: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let proxy = proxy
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    match &proxy {
+        Some(proxy) => MIGRATION_PROXY.save(deps.storage, proxy)?,
+        None => MIGRATION_PROXY.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_migration_proxy")
+        .add_attribute(
+            "proxy",
+            proxy.map_or_else(|| "none".to_string(), |proxy| proxy.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_funds_recipient(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let recipient = recipient
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    match &recipient {
+        Some(recipient) => FUNDS_RECIPIENT.save(deps.storage, recipient)?,
+        None => FUNDS_RECIPIENT.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_funds_recipient")
+        .add_attribute(
+            "recipient",
+            recipient.map_or_else(|| "none".to_string(), |recipient| recipient.to_string()),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_set_total(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    weight: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if !PAUSED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::NotPaused {});
+    }
+    let previous = TOTAL.may_load(deps.storage)?.unwrap_or_default();
+    TOTAL.save(deps.storage, &weight, env.block.height)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_total")
+        .add_attribute("warning", "total weight overridden by admin")
+        .add_attribute("sender", info.sender.as_str())
+        .add_event(
+            Event::new("total_overridden")
+                .add_attribute("previous", previous.to_string())
+                .add_attribute("total", weight.to_string())
+                .add_attribute("sender", info.sender),
+        ))
+}
+
+pub fn execute_adjust_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: String,
+    new_stake: Uint128,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if !PAUSED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::NotPaused {});
+    }
+    let addr = deps.api.addr_validate(&addr)?;
+    let config = load_config(deps.storage)?;
+    let height = env.block.height;
+
+    let previous_stake = STAKE.may_load(deps.storage, &addr)?.unwrap_or_default();
+    if new_stake.is_zero() {
+        STAKE.remove(deps.storage, &addr);
+    } else {
+        STAKE.save(deps.storage, &addr, &new_stake)?;
+    }
+    adjust_backing(deps.storage, new_stake, previous_stake)?;
+
+    let previous_weight = MEMBERS.may_load(deps.storage, &addr)?.unwrap_or_default();
+    // same rule as bonding: below min_bond is no membership at all
+    let weight = if new_stake < config.min_bond {
+        MEMBERS.remove(deps.storage, &addr, height)?;
+        0
+    } else {
+        let weight = (new_stake / config.tokens_per_weight).u128() as u64;
+        MEMBERS.save(deps.storage, &addr, &weight, height)?;
+        weight
+    };
+    TOTAL.update(deps.storage, height, |total| -> StdResult<_> {
+        let total = total.unwrap_or_default();
+        Ok(total.saturating_sub(previous_weight).saturating_add(weight))
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "adjust_stake")
+        .add_attribute("warning", "stake overridden by admin")
+        .add_attribute("sender", info.sender.as_str())
+        .add_event(
+            Event::new("stake_overridden")
+                .add_attribute("addr", addr)
+                .add_attribute("previous_stake", previous_stake)
+                .add_attribute("stake", new_stake)
+                .add_attribute("previous_weight", previous_weight.to_string())
+                .add_attribute("weight", weight.to_string())
+                .add_attribute("sender", info.sender),
+        ))
+}
+
+pub fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: Option<Denom>,
+    tokens_per_weight: Option<Uint128>,
+    min_bond: Option<Uint128>,
+    unbonding_period: Option<Duration>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if !PAUSED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::NotPaused {});
+    }
+    if tokens_per_weight.is_some_and(|tokens| tokens.is_zero()) {
+        return Err(ContractError::ZeroTokensPerWeight {});
+    }
+    if min_bond.is_some_and(|min_bond| min_bond.is_zero()) {
+        return Err(ContractError::ZeroMinBond {});
+    }
+    // there is no re-index of member weights to run after such a change
+    if (tokens_per_weight.is_some() || min_bond.is_some()) && !STAKE.is_empty(deps.storage) {
+        return Err(ContractError::StakesHeld {});
+    }
+    // held funds are forwarded, and protected from sweeping, in the staking denom
+    if denom.is_some() && !(STAKE.is_empty(deps.storage) && claims_storage().is_empty(deps.storage))
+    {
+        return Err(ContractError::StakingFundsHeld {});
+    }
+    if let Some(Denom::Cw20(addr)) = &denom {
+        deps.api.addr_validate(addr.as_str())?;
+    }
+
+    let mut config = load_config(deps.storage)?;
+    let mut res = Response::new().add_attribute("action", "update_config");
+    if let Some(denom) = denom {
+        res = res.add_attribute("denom", transfer_denom(&denom));
+        config.denom = denom;
+    }
+    if let Some(tokens_per_weight) = tokens_per_weight {
+        res = res.add_attribute("tokens_per_weight", tokens_per_weight);
+        config.tokens_per_weight = tokens_per_weight;
+    }
+    if let Some(min_bond) = min_bond {
+        res = res.add_attribute("min_bond", min_bond);
+        config.min_bond = min_bond;
+    }
+    if let Some(unbonding_period) = unbonding_period {
+        res = res.add_attribute("unbonding_period", unbonding_period.to_string());
+        config.unbonding_period = unbonding_period;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(res.add_attribute("sender", info.sender))
+}
+
+pub fn execute_sweep_foreign_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    to: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let to = deps.api.addr_validate(&to)?;
+    let config = load_config(deps.storage)?;
+    if matches!(&config.denom, Denom::Native(staking) if *staking == denom) {
+        return Err(ContractError::CannotSweepStakingDenom { denom });
+    }
+
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, &denom)?
+        .amount;
+    let mut res = Response::new()
+        .add_attribute("action", "sweep_foreign_denom")
+        .add_attribute("amount", coin_to_string(balance, &denom))
+        .add_attribute("to", to.as_str())
+        .add_attribute("sender", info.sender);
+    if !balance.is_zero() {
+        res = res.add_message(send_tokens(&Denom::Native(denom), &to, balance)?);
+    }
+    Ok(res)
+}
+
+pub fn execute_finalize_residual(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let to = deps.api.addr_validate(&to)?;
+    let claims_map = claims_storage();
+    if !STAKE.is_empty(deps.storage) || !claims_map.is_empty(deps.storage) {
+        return Err(ContractError::MigrationNotComplete {});
+    }
+
+    let config = load_config(deps.storage)?;
+    let residual = contract_balance(deps.as_ref(), &env)?;
+    let mut res = Response::new()
+        .add_attribute("action", "finalize_residual")
+        .add_attribute(
+            "amount",
+            coin_to_string(residual, &transfer_denom(&config.denom)),
+        )
+        .add_attribute("to", to.as_str())
+        .add_attribute("sender", info.sender);
+    if !residual.is_zero() {
+        res = res.add_message(send_tokens(&config.denom, &to, residual)?);
+    }
+    Ok(res)
+}
+
+pub fn execute_update_excluded(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    for addr in &add {
+        let addr = deps.api.addr_validate(addr)?;
+        EXCLUDE.save(deps.storage, &addr, &Empty {})?;
+    }
+    for addr in &remove {
+        let addr = deps.api.addr_validate(addr)?;
+        EXCLUDE.remove(deps.storage, &addr);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_excluded")
+        .add_attribute("added", add.len().to_string())
+        .add_attribute("removed", remove.len().to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_pause_state(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: Option<bool>,
+    stake_migration_paused: Option<bool>,
+    claim_migration_paused: Option<bool>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if let Some(paused) = paused {
+        PAUSED.save(deps.storage, &paused)?;
+    }
+    if let Some(paused) = stake_migration_paused {
+        PAUSE_STAKE_MIGRATION.save(deps.storage, &paused)?;
+    }
+    if let Some(paused) = claim_migration_paused {
+        PAUSE_CLAIM_MIGRATION.save(deps.storage, &paused)?;
+    }
+    let pause = query_pause_state(deps.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_pause_state")
+        .add_attribute("paused", pause.paused.to_string())
+        .add_attribute(
+            "stake_migration_paused",
+            pause.stake_migration_paused.to_string(),
+        )
+        .add_attribute(
+            "claim_migration_paused",
+            pause.claim_migration_paused.to_string(),
+        )
+        .add_attribute("sender", info.sender))
+}
+
+/// Permanently stops migration. Batches already sent to DAO DAO are not
+/// reversed as their funds have left the contract.
+pub fn execute_abort_migration(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    ABORTED.save(deps.storage, &true)?;
+
+    let batches_run = MIGRATION_METRICS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .batches_run;
+    Ok(Response::new()
+        .add_attribute("action", "abort_migration")
+        .add_attribute("batches_run", batches_run.to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_fund(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let denom = match load_config(deps.storage)?.denom {
+        Denom::Native(denom) => denom,
+        Denom::Cw20(addr) => return Err(ContractError::MissingDenom(addr.to_string())),
+    };
+    let amount = must_pay_funds(&NativeBalance(info.funds), &denom)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "fund")
+        .add_attribute("funded", coin_to_string(amount, &denom))
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_seal(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if !STAKE.is_empty(deps.storage) || !claims_storage().is_empty(deps.storage) {
+        return Err(ContractError::MigrationNotComplete {});
+    }
+    let balance = contract_balance(deps.as_ref(), &env)?;
+    if !balance.is_zero() {
+        return Err(ContractError::BalanceNotEmpty { balance });
+    }
+    SEALED.save(deps.storage, &true)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "seal")
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+/// Removes up to `num` members holding zero weight, refunding any stake they
+/// still have below `min_bond`. `TOTAL` is unaffected.
+pub fn execute_prune_zero_weight_members(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    num: u64,
+    start_after: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let config = load_config(deps.storage)?;
+    let start = maybe_addr(deps.api, start_after)?;
+
+    // The bond handler removed the member of a stake below `min_bond`, so
+    // such stakes are only found in `STAKE`.
+    let mut pruned = vec![];
+    let next = {
+        let mut last_read = None;
+        let mut entries = STAKE.range(
+            deps.storage,
+            start.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        );
+        let mut read = 0;
+        while (pruned.len() as u64) < num && read < MAX_DISTRIBUTION_SCAN {
+            let Some(item) = entries.next() else {
+                break;
+            };
+            read += 1;
+            let (addr, stake) = item?;
+            last_read = Some(addr.clone());
+            if stake < config.min_bond || (stake / config.tokens_per_weight).is_zero() {
+                pruned.push((addr, stake));
+            }
+        }
+        last_read.filter(|_| entries.next().is_some())
+    };
+
+    let mut refunds = vec![];
+    for (addr, stake) in &pruned {
+        MEMBERS.remove(deps.storage, addr, env.block.height)?;
+        STAKE.remove(deps.storage, addr);
+        adjust_backing(deps.storage, Uint128::zero(), *stake)?;
+        if !stake.is_zero() {
+            refunds.push(send_tokens(&config.denom, addr, *stake)?);
+        }
+    }
+
+    Ok(Response::new()
+        .add_messages(refunds)
+        .add_attribute("action", "prune_zero_weight_members")
+        .add_attribute("pruned", pruned.len().to_string())
+        .add_attributes(next.map(|next| ("next_start_after", next)))
+        .add_attribute("sender", info.sender))
+}
+
+/// A plain transfer of `amount` of `denom` to `to`.
+fn send_tokens(denom: &Denom, to: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(match denom {
+        Denom::Native(denom) => BankMsg::Send {
+            to_address: to.to_string(),
+            amount: coins(amount.u128(), denom),
+        }
+        .into(),
+        Denom::Cw20(addr) => WasmMsg::Execute {
+            contract_addr: addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: to.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    })
+}
+
+/// Reads up to `num` entries of the stake, member and claims maps, in that
+/// order, into the pre-migration snapshot. The full maps are only read once,
+/// spread over as many calls as they need, before any state has been removed.
+pub fn execute_prepare_snapshot(
+    deps: DepsMut,
+    info: MessageInfo,
+    num: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if PRE_MIGRATION_SNAPSHOT.exists(deps.storage) {
+        return Err(ContractError::SnapshotTaken {});
+    }
+    let mut progress = SNAPSHOT_PROGRESS
+        .may_load(deps.storage)?
+        .unwrap_or(SnapshotProgress {
+            stage: SnapshotStage::Stakes,
+            start_after: None,
+            total_staked: Uint128::zero(),
+            member_count: 0,
+            claim_count: 0,
+            total_claimed: Uint128::zero(),
+        });
+    let claims_map = claims_storage();
+
+    let mut read = 0;
+    let complete = loop {
+        if read == num {
+            break false;
+        }
+        let left = (num - read) as usize;
+        let start_after = progress.start_after.take();
+        let keys = match progress.stage {
+            SnapshotStage::Stakes => STAKE
+                .range(
+                    deps.storage,
+                    start_after.as_ref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(left)
+                .map(|item| {
+                    let (addr, stake) = item?;
+                    progress.total_staked += stake;
+                    Ok(addr)
+                })
+                .collect::<StdResult<Vec<_>>>()?,
+            SnapshotStage::Members => MEMBERS
+                .keys(
+                    deps.storage,
+                    start_after.as_ref().map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(left)
+                .collect::<StdResult<Vec<_>>>()?,
+            SnapshotStage::Claims => claims_map
+                .range(
+                    deps.storage,
+                    start_after.map(Bound::exclusive),
+                    None,
+                    Order::Ascending,
+                )
+                .take(left)
+                .map(|item| {
+                    let (addr, claims) = item?;
+                    progress.claim_count += claims.len() as u64;
+                    progress.total_claimed +=
+                        claims.iter().map(|claim| claim.amount).sum::<Uint128>();
+                    Ok(addr)
+                })
+                .collect::<StdResult<Vec<_>>>()?,
+        };
+        read += keys.len() as u64;
+        if let SnapshotStage::Members = progress.stage {
+            progress.member_count += keys.len() as u64;
+        }
+        if keys.len() == left {
+            // the map may hold more, resume after the last key read
+            progress.start_after = keys.last().cloned();
+            break false;
+        }
+        progress.stage = match progress.stage {
+            SnapshotStage::Stakes => SnapshotStage::Members,
+            SnapshotStage::Members => SnapshotStage::Claims,
+            SnapshotStage::Claims => break true,
+        };
+    };
+
+    if complete {
+        let snapshot = PreMigrationSnapshot {
+            total_weight: TOTAL.load(deps.storage)?,
+            total_staked: progress.total_staked,
+            member_count: progress.member_count,
+            claim_count: progress.claim_count,
+        };
+        PRE_MIGRATION_SNAPSHOT.save(deps.storage, &snapshot)?;
+        MIGRATION_BACKING.save(
+            deps.storage,
+            &(progress.total_staked + progress.total_claimed),
+        )?;
+        SNAPSHOT_PROGRESS.remove(deps.storage);
+    } else {
+        SNAPSHOT_PROGRESS.save(deps.storage, &progress)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "prepare_snapshot")
+        .add_attribute("read", read.to_string())
+        .add_attribute("complete", complete.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+/// Writes claims recovered from an external snapshot into the claims map so
+/// they can be picked up by `MigrateToDaoDao`. Existing claims for an address
+/// are only replaced when `overwrite` is set.
+pub fn execute_import_claims(
+    deps: DepsMut,
+    info: MessageInfo,
+    entries: Vec<(String, Vec<Claim>)>,
+    denom: Denom,
+    overwrite: bool,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    if entries.len() > MAX_IMPORT_BATCH {
+        return Err(ContractError::ImportTooLarge {
+            max: MAX_IMPORT_BATCH as u64,
+        });
+    }
+    assert_claim_denom(&load_config(deps.storage)?.denom, &denom)?;
+
+    let claims_map = claims_storage();
+    for (addr, claims) in &entries {
+        let addr = deps.api.addr_validate(addr)?;
+        if claims.len() > MAX_CLAIMS_PER_ADDR {
+            return Err(ContractError::TooManyClaims {
+                addr: addr.into_string(),
+            });
+        }
+        if !overwrite && claims_map.has(deps.storage, addr.clone()) {
+            return Err(ContractError::ClaimsExist {
+                addr: addr.into_string(),
+            });
+        }
+        let replaced: Uint128 = claims_map
+            .may_load(deps.storage, addr.clone())?
+            .unwrap_or_default()
+            .iter()
+            .map(|claim| claim.amount)
+            .sum();
+        let imported = claims.iter().map(|claim| claim.amount).sum();
+        adjust_backing(deps.storage, imported, replaced)?;
+        claims_map.save(deps.storage, addr, claims)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "import_claims")
+        .add_attribute("imported", entries.len().to_string())
+        .add_attribute("sender", info.sender))
+}
+
+/// Claims only record an amount, so anything written into the claims map must
+/// be known to be held in the configured denom or migration would send the
+/// wrong funds for it.
+pub fn assert_claim_denom(config_denom: &Denom, denom: &Denom) -> Result<(), ContractError> {
+    if config_denom != denom {
+        return Err(ContractError::ClaimDenomMismatch {
+            expected: transfer_denom(config_denom),
+            found: transfer_denom(denom),
+        });
+    }
+    Ok(())
+}
+
+pub fn must_pay_funds(balance: &NativeBalance, denom: &str) -> Result<Uint128, ContractError> {
+    match balance.0.len() {
+        0 => Err(ContractError::NoFunds {}),
+        1 => {
+            let balance = &balance.0;
+            let payment = balance[0].amount;
+            if balance[0].denom == denom {
+                Ok(payment)
+            } else {
+                Err(ContractError::MissingDenom(denom.to_string()))
+            }
+        }
+        _ => Err(ContractError::ExtraDenoms(denom.to_string())),
+    }
+}
+
+#[inline]
+fn coin_to_string(amount: Uint128, denom: &str) -> String {
+    format!("{} {}", amount, denom)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Member {
+            addr,
+            at_height: height,
+        } => to_json_binary(&query_member(deps, addr, height)?),
+        QueryMsg::EffectiveWeight { address } => {
+            to_json_binary(&query_effective_weight(deps, address)?)
+        }
+        QueryMsg::MemberChangeCount { address } => {
+            to_json_binary(&query_member_change_count(deps, address)?)
+        }
+        QueryMsg::MembersAt { addrs, height } => {
+            to_json_binary(&query_members_at(deps, addrs, height)?)
+        }
+        QueryMsg::ExportMembers { start_after, limit } => {
+            to_json_binary(&export_members(deps, start_after, limit)?)
+        }
+        QueryMsg::ListMembers { start_after, limit } => {
+            to_json_binary(&list_members(deps, start_after, limit)?)
+        }
+        QueryMsg::UnmigratedMembers { start_after, limit } => {
+            to_json_binary(&list_members(deps, start_after, limit)?)
+        }
+        QueryMsg::MembersAboveWeight {
+            weight,
+            start_after,
+            limit,
+        } => to_json_binary(&list_members_above_weight(
+            deps,
+            weight,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::TotalWeight {} => to_json_binary(&query_total_weight(deps)?),
+        QueryMsg::TotalWeightAt { height } => to_json_binary(&query_total_weight_at(deps, height)?),
+        QueryMsg::Claims { address } => {
+            to_json_binary(&CLAIMS.query_claims(deps, &deps.api.addr_validate(&address)?)?)
+        }
+        QueryMsg::ClaimsBounded { address, max } => {
+            to_json_binary(&query_claims_bounded(deps, address, max)?)
+        }
+        QueryMsg::ClaimsInHeightRange { from, to, limit } => {
+            to_json_binary(&query_claims_in_height_range(deps, from, to, limit)?)
+        }
+        QueryMsg::ClaimsByType {
+            height_based,
+            limit,
+        } => to_json_binary(&query_claims_by_type(deps, height_based, limit)?),
+        QueryMsg::NextMaturity { address } => to_json_binary(&query_next_maturity(deps, address)?),
+        QueryMsg::ClaimsInfo { address } => to_json_binary(&query_claims_info(deps, address)?),
+        QueryMsg::ClaimsMapStats { sample } => {
+            to_json_binary(&query_claims_map_stats(deps, sample)?)
+        }
+        QueryMsg::ClaimMaturitySchedule { limit } => {
+            to_json_binary(&query_claim_maturity_schedule(deps, limit)?)
+        }
+        QueryMsg::TopClaimHolders { limit } => {
+            to_json_binary(&query_top_claim_holders(deps, limit)?)
+        }
+        QueryMsg::ReceiptRoot {} => to_json_binary(&query_receipt_root(deps.storage)?),
+        QueryMsg::DualHolders {} => to_json_binary(&query_dual_holders(deps)?),
+        QueryMsg::TopKConcentration { k } => to_json_binary(&query_top_k_concentration(deps, k)?),
+        QueryMsg::ClaimsNormalized { address } => {
+            to_json_binary(&query_claims_normalized(deps, address)?)
+        }
+        QueryMsg::Staked { address } => to_json_binary(&query_staked(deps, address)?),
+        QueryMsg::Admin {} => to_json_binary(&ADMIN.query_admin(deps)?),
+        QueryMsg::IsAdmin { address } => to_json_binary(&query_is_admin(deps, address)?),
+        QueryMsg::Hooks {} => to_json_binary(&HOOKS.query_hooks(deps)?),
+        QueryMsg::HasHooks {} => to_json_binary(&HasHooksResponse {
+            has_hooks: !HOOKS.query_hooks(deps)?.hooks.is_empty(),
+        }),
+        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::InstantiateParams {} => to_json_binary(&query_instantiate_params(deps)?),
+        QueryMsg::ProjectedVotingPower {} => to_json_binary(&query_projected_voting_power(deps)?),
+        QueryMsg::ListMigrated { start_after, limit } => {
+            to_json_binary(&list_migrated(deps, start_after, limit)?)
+        }
+        QueryMsg::ExportReceipts { start_after, limit } => {
+            to_json_binary(&query_export_receipts(deps, start_after, limit)?)
+        }
+        QueryMsg::BatchHistory { start_after, limit } => {
+            to_json_binary(&query_batch_history(deps, start_after, limit)?)
+        }
+        QueryMsg::LastReplyData {} => to_json_binary(&LAST_REPLY_DATA.may_load(deps.storage)?),
+        QueryMsg::ListDiscrepancies { start_after, limit } => {
+            to_json_binary(&list_discrepancies(deps, start_after, limit)?)
+        }
+        QueryMsg::CanExit { address } => to_json_binary(&query_can_exit(deps, address)?),
+        QueryMsg::AccountStatus { address } => {
+            to_json_binary(&query_account_status(deps, env, address)?)
+        }
+        QueryMsg::PauseState {} => to_json_binary(&query_pause_state(deps)?),
+        QueryMsg::MigratableNow {} => to_json_binary(&query_migratable_now(deps, env)?),
+        QueryMsg::Sealed {} => to_json_binary(&SEALED.may_load(deps.storage)?.unwrap_or_default()),
+        QueryMsg::TopUpNeeded {} => to_json_binary(&query_top_up_needed(deps, env)?),
+        QueryMsg::ProjectedFinalBalance {} => {
+            to_json_binary(&query_projected_final_balance(deps, env)?)
+        }
+        QueryMsg::BalanceReconciliation {} => {
+            to_json_binary(&query_balance_reconciliation(deps, env)?)
+        }
+        QueryMsg::InvariantsHold {} => to_json_binary(&query_invariants_hold(deps, env)?),
+        QueryMsg::AllBalances {} => {
+            to_json_binary(&deps.querier.query_all_balances(env.contract.address)?)
+        }
+        QueryMsg::StakeDistribution { buckets } => {
+            to_json_binary(&query_stake_distribution(deps, buckets)?)
+        }
+        QueryMsg::DaoDaoInfo {} => to_json_binary(&query_dao_dao_info(deps)?),
+        QueryMsg::ConfirmDaoDao { addr } => to_json_binary(&query_confirm_dao_dao(deps, addr)?),
+        QueryMsg::AdminMatchesDao {} => to_json_binary(&query_admin_matches_dao(deps)?),
+        QueryMsg::StorageLayout {} => to_json_binary(&query_storage_layout()),
+        QueryMsg::ContractInfo {} => to_json_binary(&query_contract_info(deps, env)?),
+        QueryMsg::TransferDenom {} => to_json_binary(&query_transfer_denom(deps)?),
+        QueryMsg::DenomMigratable {} => to_json_binary(&query_denom_migratable(deps)?),
+        QueryMsg::AcceptedFundingDenom {} => {
+            let denom = CONFIG.load(deps.storage)?.denom;
+            to_json_binary(&AcceptedFundingDenomResponse {
+                denom: transfer_denom(&denom),
+                via_fund: matches!(denom, Denom::Native(_)),
+            })
+        }
+        QueryMsg::ClaimDenoms {} => to_json_binary(&query_claim_denoms(deps)?),
+        QueryMsg::OrphanClaims { start_after, limit } => {
+            to_json_binary(&list_orphan_claims(deps, start_after, limit)?)
+        }
+        QueryMsg::ListFailed { start_after, limit } => {
+            to_json_binary(&list_failed(deps, start_after, limit)?)
+        }
+        QueryMsg::MigrationStatus {} => to_json_binary(&query_migration_status(deps)?),
+        // unlike `MigrationStatus`, dust under the completion threshold still
+        // counts as left to migrate
+        QueryMsg::SourceMigrationComplete {} => to_json_binary(&SourceMigrationCompleteResponse {
+            complete: STAKE.is_empty(deps.storage) && claims_storage().is_empty(deps.storage),
+        }),
+        QueryMsg::UpcomingBatches { num, batches } => {
+            to_json_binary(&query_upcoming_batches(deps, num, batches)?)
+        }
+        QueryMsg::NextBatchMessage { num, num_claims } => {
+            to_json_binary(&query_next_batch_message(deps, num, num_claims)?)
+        }
+        QueryMsg::WouldExceedMessageLimit {
+            num,
+            num_claims,
+            limit_bytes,
+        } => to_json_binary(&query_would_exceed_message_limit(
+            deps,
+            num,
+            num_claims,
+            limit_bytes,
+        )?),
+        QueryMsg::SimulateMigration {
+            num,
+            num_claims,
+            protect_admin,
+            min_weight,
+        } => to_json_binary(&query_simulate_migration(
+            deps,
+            num,
+            num_claims,
+            protect_admin.unwrap_or_default(),
+            min_weight,
+        )?),
+        QueryMsg::NextClaimBatchSum { num_claims } => {
+            to_json_binary(&query_next_claim_batch_sum(deps, num_claims)?)
+        }
+        QueryMsg::LastBatch {} => to_json_binary(&LAST_BATCH.may_load(deps.storage)?),
+        QueryMsg::PreMigrationSnapshot {} => {
+            to_json_binary(&PRE_MIGRATION_SNAPSHOT.may_load(deps.storage)?)
+        }
+        QueryMsg::EstimatedCompletion { num } => {
+            to_json_binary(&query_estimated_completion(deps, env, num)?)
+        }
+        QueryMsg::TxsToFinish { num, num_claims } => {
+            to_json_binary(&query_txs_to_finish(deps, num, num_claims)?)
+        }
+        QueryMsg::Summary {} => to_json_binary(&query_summary(deps)?),
+        QueryMsg::DebugDump {} => to_json_binary(&query_debug_dump(deps)?),
+        QueryMsg::MigratedTotal {} => to_json_binary(&query_migrated_total(deps)?),
+        QueryMsg::AverageBatchSize {} => to_json_binary(&query_average_batch_size(deps)?),
+        QueryMsg::MigrationBreakdown {} => to_json_binary(&query_migration_breakdown(deps)?),
+        QueryMsg::ForwardingDelta {} => to_json_binary(&query_forwarding_delta(deps)?),
+        QueryMsg::MigrationMetrics {} => to_json_binary(
+            &MIGRATION_METRICS
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+fn query_total_weight(deps: Deps) -> StdResult<TotalWeightResponse> {
+    let weight = TOTAL.load(deps.storage)?;
+    Ok(TotalWeightResponse { weight })
+}
+
+/// Total weight at the start of `height`. Heights from before `TOTAL` was
+/// snapshotted return the value it held when snapshotting began.
+fn query_total_weight_at(deps: Deps, height: u64) -> StdResult<TotalWeightResponse> {
+    let weight = TOTAL
+        .may_load_at_height(deps.storage, height)?
+        .unwrap_or_default();
+    Ok(TotalWeightResponse { weight })
+}
+
+/// Weight that will become voting power on DAO DAO: the current total less
+/// the weight of excluded members, which are never migrated by a batch.
+fn query_projected_voting_power(deps: Deps) -> StdResult<ProjectedVotingPowerResponse> {
+    let mut entries = EXCLUDE.keys(deps.storage, None, None, Order::Ascending);
+    let excluded = entries.by_ref().take(MAX_DISTRIBUTION_SCAN).try_fold(
+        0u64,
+        |acc, addr| -> StdResult<_> {
+            Ok(acc + MEMBERS.may_load(deps.storage, &addr?)?.unwrap_or_default())
+        },
+    )?;
+    let truncated = entries.next().is_some();
+    let weight = TOTAL.load(deps.storage)?.saturating_sub(excluded);
+    Ok(ProjectedVotingPowerResponse { weight, truncated })
+}
+
+pub fn query_pause_state(deps: Deps) -> StdResult<PauseStateResponse> {
+    Ok(PauseStateResponse {
+        paused: PAUSED.may_load(deps.storage)?.unwrap_or_default(),
+        stake_migration_paused: PAUSE_STAKE_MIGRATION
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        claim_migration_paused: PAUSE_CLAIM_MIGRATION
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+    })
+}
+
+fn query_migratable_now(deps: Deps, env: Env) -> StdResult<MigratableNowResponse> {
+    let pause = query_pause_state(deps)?;
+    let started = MIGRATION_START
+        .may_load(deps.storage)?
+        .is_none_or(|start| env.block.height >= start);
+    let open = started
+        && !pause.paused
+        && !SEALED.may_load(deps.storage)?.unwrap_or_default()
+        && !ABORTED.may_load(deps.storage)?.unwrap_or_default();
+    Ok(MigratableNowResponse {
+        stakes: open && !pause.stake_migration_paused,
+        claims: open && !pause.claim_migration_paused,
+    })
+}
+
+/// Whether the address has anything it could unstake or claim, for frontends
+/// to decide which exit actions to offer. `reason` explains the first
+/// action that is unavailable.
+fn query_account_status(deps: Deps, env: Env, address: String) -> StdResult<AccountStatusResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let mut pending_claims = Uint128::zero();
+    let mut claimable_now = Uint128::zero();
+    for claim in CLAIMS.query_claims(deps, &addr)?.claims {
+        if claim.release_at.is_expired(&env.block) {
+            claimable_now += claim.amount;
+        } else {
+            pending_claims += claim.amount;
+        }
+    }
+
+    Ok(AccountStatusResponse {
+        staked: STAKE.may_load(deps.storage, &addr)?.unwrap_or_default(),
+        weight: MEMBERS.may_load(deps.storage, &addr)?.unwrap_or_default(),
+        pending_claims,
+        claimable_now,
+        migrated: MIGRATED.has(deps.storage, &addr),
+    })
+}
+
+/// This contract has no unbond or claim message, so stakes and claims only
+/// leave it through migration to DAO DAO and nobody can exit directly.
+fn query_can_exit(deps: Deps, address: String) -> StdResult<CanExitResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let holds =
+        STAKE.has(deps.storage, &addr) || !CLAIMS.query_claims(deps, &addr)?.claims.is_empty();
+    let reason = if SEALED.may_load(deps.storage)?.unwrap_or_default() {
+        "contract is sealed"
+    } else if ABORTED.may_load(deps.storage)?.unwrap_or_default() {
+        "migration is aborted"
+    } else if !holds {
+        "nothing staked or claimed, or already migrated"
+    } else {
+        "exit only via migration"
+    };
+    Ok(CanExitResponse {
+        can_unstake: false,
+        can_claim: false,
+        reason: Some(reason.to_string()),
+    })
+}
+
+/// Compares the tokens the contract should hold for stakes and pending
+/// claims with what it actually holds.
+fn query_balance_reconciliation(deps: Deps, env: Env) -> StdResult<BalanceReconciliationResponse> {
+    let (expected, truncated) = accounted_total(deps)?;
+    let actual = contract_balance(deps, &env)?;
+    Ok(BalanceReconciliationResponse {
+        expected,
+        actual,
+        reconciled: expected == actual,
+        truncated,
+    })
+}
+
+fn query_invariants_hold(deps: Deps, env: Env) -> StdResult<InvariantsHoldResponse> {
+    let mut truncated = false;
+
+    let mut members = MEMBERS.range(deps.storage, None, None, Order::Ascending);
+    let mut weights = 0u64;
+    let mut no_orphans = true;
+    for item in members.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (addr, weight) = item?;
+        weights += weight;
+        no_orphans &= STAKE.has(deps.storage, &addr);
+    }
+    truncated |= members.next().is_some();
+
+    let mut stakes = STAKE.range(deps.storage, None, None, Order::Ascending);
+    let mut liabilities = Uint128::zero();
+    for item in stakes.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        liabilities += item?.1;
+    }
+    truncated |= stakes.next().is_some();
+
+    let claims_map = claims_storage();
+    let mut claims = claims_map.range(deps.storage, None, None, Order::Ascending);
+    for item in claims.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        liabilities += item?.1.iter().map(|claim| claim.amount).sum::<Uint128>();
+    }
+    truncated |= claims.next().is_some();
+
+    let total_matches_weights = TOTAL.may_load(deps.storage)?.unwrap_or_default() == weights;
+    let balance_backs_liabilities = contract_balance(deps, &env)? >= liabilities;
+    Ok(InvariantsHoldResponse {
+        total_matches_weights,
+        balance_backs_liabilities,
+        no_orphans,
+        all_ok: total_matches_weights && balance_backs_liabilities && no_orphans && !truncated,
+        truncated,
+    })
+}
+
+/// What would be left in the contract once every stake and claim has been
+/// migrated. Anything above zero are funds the accounting doesn't know about.
+/// A shortfall is reported as zero, see `BalanceReconciliation` for it.
+fn query_projected_final_balance(deps: Deps, env: Env) -> StdResult<ProjectedFinalBalanceResponse> {
+    let (accounted, truncated) = accounted_total(deps)?;
+    let projected = contract_balance(deps, &env)?.saturating_sub(accounted);
+    Ok(ProjectedFinalBalanceResponse {
+        projected,
+        truncated,
+    })
+}
+
+fn query_top_up_needed(deps: Deps, env: Env) -> StdResult<TopUpNeededResponse> {
+    let (accounted, truncated) = accounted_total(deps)?;
+    let shortfall = accounted.saturating_sub(contract_balance(deps, &env)?);
+    Ok(TopUpNeededResponse {
+        shortfall,
+        denom: CONFIG.load(deps.storage)?.denom,
+        truncated,
+    })
+}
+
+/// Total of all stakes and pending claims, and whether it was cut short.
+/// Once the snapshot has set `MIGRATION_BACKING`, it is the backing less the
+/// funds batches moved. Before, it is summed from at most
+/// `MAX_DISTRIBUTION_SCAN` entries of each of the stake and claims maps.
+fn accounted_total(deps: Deps) -> StdResult<(Uint128, bool)> {
+    if let Some(backing) = MIGRATION_BACKING.may_load(deps.storage)? {
+        let moved = MIGRATION_METRICS
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .total_moved;
+        return Ok((backing.saturating_sub(moved), false));
+    }
+    let mut stakes = STAKE.range(deps.storage, None, None, Order::Ascending);
+    let mut total = Uint128::zero();
+    for item in stakes.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        total += item?.1;
+    }
+    let mut truncated = stakes.next().is_some();
+    let claims_map = claims_storage();
+    let mut claims = claims_map.range(deps.storage, None, None, Order::Ascending);
+    for item in claims.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        total += item?.1.iter().map(|claim| claim.amount).sum::<Uint128>();
+    }
+    truncated |= claims.next().is_some();
+    Ok((total, truncated))
+}
+
+/// The contract's balance of the staking denom.
+fn contract_balance(deps: Deps, env: &Env) -> StdResult<Uint128> {
+    match CONFIG.load(deps.storage)?.denom {
+        Denom::Native(denom) => Ok(deps
+            .querier
+            .query_balance(&env.contract.address, denom)?
+            .amount),
+        Denom::Cw20(addr) => {
+            let res: cw20::BalanceResponse = deps.querier.query_wasm_smart(
+                addr,
+                &cw20::Cw20QueryMsg::Balance {
+                    address: env.contract.address.to_string(),
+                },
+            )?;
+            Ok(res.balance)
+        }
+    }
+}
+
+/// Counts stakers per stake size bucket. `buckets` are boundaries: a stake
+/// falls into bucket `i` if it is at least `buckets[i - 1]` and below
+/// `buckets[i]`, with one bucket below the first boundary and one from the
+/// last boundary up. Only the first `MAX_DISTRIBUTION_SCAN` stakes are read.
+fn query_stake_distribution(
+    deps: Deps,
+    mut buckets: Vec<Uint128>,
+) -> StdResult<StakeDistributionResponse> {
+    buckets.sort();
+    let mut counts = vec![0u64; buckets.len() + 1];
+    let mut total = Uint128::zero();
+    let mut stakers = 0u64;
+
+    let mut stakes = STAKE.range(deps.storage, None, None, Order::Ascending);
+    for item in stakes.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (_, stake) = item?;
+        counts[buckets.partition_point(|boundary| *boundary <= stake)] += 1;
+        total += stake;
+        stakers += 1;
+    }
+    let truncated = stakes.next().is_some();
+
+    let average = if stakers == 0 {
+        Uint128::zero()
+    } else {
+        total / Uint128::from(stakers)
+    };
+    Ok(StakeDistributionResponse {
+        buckets,
+        counts,
+        stakers,
+        total,
+        average,
+        truncated,
+    })
+}
+
+/// Aggregates claim amounts by `release_at`. Only the claims of the first
+/// `MAX_DISTRIBUTION_SCAN` addresses are read.
+fn query_claim_maturity_schedule(
+    deps: Deps,
+    limit: u32,
+) -> StdResult<ClaimMaturityScheduleResponse> {
+    let mut schedule: BTreeMap<(u8, u64), (Expiration, Uint128)> = BTreeMap::new();
+    let claims_map = claims_storage();
+    let mut claims = claims_map.range(deps.storage, None, None, Order::Ascending);
+    for item in claims.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (_, claims) = item?;
+        for claim in claims {
+            schedule
+                .entry(release_key(&claim.release_at))
+                .or_insert((claim.release_at, Uint128::zero()))
+                .1 += claim.amount;
+        }
+    }
+    let truncated = claims.next().is_some();
+
+    Ok(ClaimMaturityScheduleResponse {
+        schedule: schedule.into_values().take(limit as usize).collect(),
+        truncated,
+    })
+}
+
+fn query_top_claim_holders(deps: Deps, limit: u32) -> StdResult<TopClaimHoldersResponse> {
+    let claims_map = claims_storage();
+    let mut claims = claims_map.range(deps.storage, None, None, Order::Ascending);
+    let mut holders = claims
+        .by_ref()
+        .take(MAX_DISTRIBUTION_SCAN)
+        .map(|item| item.map(|(addr, claims)| (addr, claims.len() as u64)))
+        .collect::<StdResult<Vec<_>>>()?;
+    let truncated = claims.next().is_some();
+    // stable, so equal counts stay in address order
+    holders.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    holders.truncate(limit as usize);
+
+    Ok(TopClaimHoldersResponse { holders, truncated })
+}
+
+fn query_receipt_root(storage: &dyn Storage) -> StdResult<ReceiptRootResponse> {
+    let root = RECEIPT_ROOT
+        .may_load(storage)?
+        .unwrap_or_else(|| Binary::from([0u8; 32]));
+    Ok(ReceiptRootResponse { root })
+}
+
+/// Only the first `MAX_DISTRIBUTION_SCAN` addresses with claims are read.
+fn query_dual_holders(deps: Deps) -> StdResult<DualHoldersResponse> {
+    let claims_map = claims_storage();
+    let mut claims = claims_map.keys(deps.storage, None, None, Order::Ascending);
+    let mut count = 0;
+    for addr in claims.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        if STAKE.has(deps.storage, &addr?) {
+            count += 1;
+        }
+    }
+    let truncated = claims.next().is_some();
+
+    Ok(DualHoldersResponse { count, truncated })
+}
+
+fn query_top_k_concentration(deps: Deps, k: u32) -> StdResult<TopKConcentrationResponse> {
+    let k = k.min(MAX_LIMIT) as usize;
+    let mut members = MEMBERS.range(deps.storage, None, None, Order::Ascending);
+    let mut weights = members
+        .by_ref()
+        .take(MAX_DISTRIBUTION_SCAN)
+        .map(|item| item.map(|(_, weight)| weight))
+        .collect::<StdResult<Vec<_>>>()?;
+    let truncated = members.next().is_some();
+    weights.sort_unstable_by_key(|weight| std::cmp::Reverse(*weight));
+    let top_k_weight: u64 = weights.iter().take(k).sum();
+
+    let total_weight = TOTAL.may_load(deps.storage)?.unwrap_or_default();
+    let fraction = if total_weight == 0 {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(top_k_weight, total_weight)
+    };
+    Ok(TopKConcentrationResponse {
+        top_k_weight,
+        total_weight,
+        fraction,
+        truncated,
+    })
+}
+
+fn query_dao_dao_info(deps: Deps) -> StdResult<ContractInfoResponse> {
+    let dao_dao = DAO_DAO
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::not_found("DAO DAO address"))?;
+    deps.querier.query_wasm_contract_info(dao_dao)
+}
+
+fn query_denom_migratable(deps: Deps) -> StdResult<DenomMigratableResponse> {
+    Ok(match CONFIG.load(deps.storage)?.denom {
+        Denom::Native(_) => DenomMigratableResponse {
+            migratable: true,
+            reason: None,
+        },
+        Denom::Cw20(_) => DenomMigratableResponse {
+            migratable: false,
+            reason: Some(
+                "dao-voting-token-staked only accepts native funds and cw20-stake can't \
+                 stake on behalf of other addresses"
+                    .to_string(),
+            ),
+        },
+    })
+}
+
+fn query_transfer_denom(deps: Deps) -> StdResult<TransferDenomResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(TransferDenomResponse {
+        denom: transfer_denom(&config.denom),
+    })
+}
+
+fn query_next_claim_batch_sum(deps: Deps, num_claims: u64) -> StdResult<NextClaimBatchSumResponse> {
+    let batch = query_simulate_migration(deps, 0, num_claims, false, None)?;
+    Ok(NextClaimBatchSumResponse {
+        sum: batch.claim_sum,
+    })
+}
 
-    // min_bond is at least 1, so 0 stake -> non-membership
-    let min_bond = std::cmp::max(msg.min_bond, Uint128::new(1));
+/// The stakes and claims a `MigrateToDaoDao` batch with these options would
+/// take right now, selected the same way as the batch itself.
+fn query_simulate_migration(
+    deps: Deps,
+    num: u64,
+    num_claims: u64,
+    protect_admin: bool,
+    min_weight: Option<u64>,
+) -> StdResult<SimulatedBatch> {
+    let num = num.min(MAX_DISTRIBUTION_SCAN as u64);
+    let num_claims = num_claims.min(MAX_DISTRIBUTION_SCAN as u64);
+    let protected = if protect_admin {
+        ADMIN.get(deps)?
+    } else {
+        None
+    };
+    let stake_cursor = STAKE_CURSOR.may_load(deps.storage)?.flatten();
+    let (stakes, next_stake) = collect_stakes(
+        deps,
+        num,
+        protected.as_ref(),
+        min_weight,
+        stake_cursor.as_ref(),
+    )?;
+    let claims_cursor = CLAIMS_CURSOR.may_load(deps.storage)?.flatten();
+    let claims = collect_claims(deps, num_claims, protected.as_ref(), claims_cursor.as_ref())?;
+    let (claims, _) = defer_oversized_claims(claims)?;
 
-    let config = Config {
-        denom: msg.denom,
-        tokens_per_weight: msg.tokens_per_weight,
-        min_bond,
-        unbonding_period: msg.unbonding_period,
+    let stake_sum = stakes.iter().map(|(_, stake)| *stake).sum();
+    let claim_sum = claims
+        .iter()
+        .flat_map(|(_, claims)| claims.iter().map(|c| c.amount))
+        .sum();
+    let next_stake_cursor = if num > 0 { next_stake } else { stake_cursor };
+    let next_claim_cursor = if num_claims > 0 {
+        next_claims_cursor(&claims, num_claims)
+    } else {
+        claims_cursor
     };
-    CONFIG.save(deps.storage, &config)?;
-    TOTAL.save(deps.storage, &0)?;
+    let stakes = stakes
+        .into_iter()
+        .map(|(addr, _)| {
+            let weight = MEMBERS.may_load(deps.storage, &addr)?.unwrap_or_default();
+            Ok((addr, weight))
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(SimulatedBatch {
+        stakes,
+        claims,
+        stake_sum,
+        claim_sum,
+        total_sum: stake_sum + claim_sum,
+        next_stake_cursor,
+        next_claim_cursor,
+    })
+}
 
-    Ok(Response::default())
+fn query_would_exceed_message_limit(
+    deps: Deps,
+    num: u64,
+    num_claims: u64,
+    limit_bytes: u64,
+) -> StdResult<MessageLimitResponse> {
+    let num = num.min(MAX_DISTRIBUTION_SCAN as u64);
+    let num_claims = num_claims.min(MAX_DISTRIBUTION_SCAN as u64);
+    let stake_cursor = STAKE_CURSOR.may_load(deps.storage)?.flatten();
+    let (stakes, _) = collect_stakes(deps, num, None, None, stake_cursor.as_ref())?;
+    let claims_cursor = CLAIMS_CURSOR.may_load(deps.storage)?.flatten();
+    let claims = collect_claims(deps, num_claims, None, claims_cursor.as_ref())?;
+    let (claims, _) = defer_oversized_claims(claims)?;
+
+    let version = DESTINATION_VERSION
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let size_bytes = split_migration(stakes, claims, false, &version)?
+        .iter()
+        .map(|out| out.msg.len() as u64)
+        .max()
+        .unwrap_or_default();
+    Ok(MessageLimitResponse {
+        size_bytes,
+        exceeds: size_bytes > limit_bytes,
+    })
 }
 
-// And declare a custom Error variant for the ones where you will want to make use of it
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(
-    deps: DepsMut,
-    env: Env,
-    _info: MessageInfo,
-    msg: ExecuteMsg,
-) -> Result<Response, ContractError> {
-    match msg {
-        ExecuteMsg::MigrateToDaoDao { num, num_claims } => {
-            let config = CONFIG.load(deps.storage)?;
-            let iter = STAKE.range(deps.storage, None, None, Order::Ascending);
-            let weights = iter.take(num as usize).collect::<StdResult<Vec<_>>>()?;
-            // remove all members
-            let mut sum = Uint128::zero();
-            let mut weight_sum = 0u64;
-            for (addr, weight) in &weights {
-                STAKE.remove(deps.storage, addr);
-                let vote_weight = MEMBERS.may_load(deps.storage, addr)?.unwrap_or_default();
-                MEMBERS.remove(deps.storage, addr, env.block.height)?;
-                sum += weight;
-                weight_sum += vote_weight;
+fn query_next_batch_message(
+    deps: Deps,
+    num: u64,
+    num_claims: u64,
+) -> StdResult<NextBatchMessageResponse> {
+    let num = num.min(MAX_DISTRIBUTION_SCAN as u64);
+    let num_claims = num_claims.min(MAX_DISTRIBUTION_SCAN as u64);
+    let config = CONFIG.load(deps.storage)?;
+    let dao_dao = DAO_DAO
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::not_found("DAO DAO address"))?;
+    let target = MIGRATION_PROXY.may_load(deps.storage)?.unwrap_or(dao_dao);
+    let stake_cursor = STAKE_CURSOR.may_load(deps.storage)?.flatten();
+    let (stakes, _) = collect_stakes(deps, num, None, None, stake_cursor.as_ref())?;
+    let claims_cursor = CLAIMS_CURSOR.may_load(deps.storage)?.flatten();
+    let claims = collect_claims(deps, num_claims, None, claims_cursor.as_ref())?;
+    let (claims, _) = defer_oversized_claims(claims)?;
+
+    let version = DESTINATION_VERSION
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let outgoing = split_migration(stakes, claims, false, &version)?;
+    if outgoing.iter().all(|out| out.amount.is_zero()) {
+        return Ok(NextBatchMessageResponse { messages: vec![] });
+    }
+
+    let mut messages = vec![];
+    match FUNDS_RECIPIENT.may_load(deps.storage)? {
+        Some(recipient) => {
+            let sum: Uint128 = outgoing.iter().map(|out| out.amount).sum();
+            if !sum.is_zero() {
+                messages.push(send_tokens(&config.denom, &recipient, sum)?);
             }
-            let total = TOTAL.load(deps.storage)? - weight_sum;
-            TOTAL.save(deps.storage, &total)?;
-
-            // Also migrate claims
-            let claims_map: Map<Addr, Vec<Claim>> = Map::new("claims");
-            let iter = claims_map.range(deps.storage, None, None, Order::Ascending);
-            let claims = iter
-                .take(num_claims as usize)
-                .collect::<StdResult<Vec<_>>>()?;
-
-            for (addr, claims) in &claims {
-                claims.iter().for_each(|c| sum += c.amount);
-                claims_map.remove(deps.storage, addr.clone());
+            for out in outgoing {
+                messages.push(
+                    WasmMsg::Execute {
+                        contract_addr: target.to_string(),
+                        msg: out.msg,
+                        funds: vec![],
+                    }
+                    .into(),
+                );
+            }
+        }
+        None => {
+            for out in outgoing {
+                messages.push(build_transfer(&config.denom, out.amount, &target, out.msg)?);
             }
+        }
+    }
+    Ok(NextBatchMessageResponse { messages })
+}
+
+fn query_upcoming_batches(deps: Deps, num: u64, batches: u64) -> StdResult<Vec<Vec<String>>> {
+    if num == 0 {
+        return Ok(vec![]);
+    }
+    let batches = batches.min(MAX_UPCOMING_BATCHES);
+    let count = num
+        .saturating_mul(batches)
+        .min(MAX_DISTRIBUTION_SCAN as u64);
+    let stake_cursor = STAKE_CURSOR.may_load(deps.storage)?.flatten();
+    let (stakes, _) = collect_stakes(deps, count, None, None, stake_cursor.as_ref())?;
+    Ok(stakes
+        .chunks(num as usize)
+        .map(|batch| batch.iter().map(|(addr, _)| addr.to_string()).collect())
+        .collect())
+}
 
-            let msg = dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { weights, claims };
+/// Reads at most `MAX_DISTRIBUTION_SCAN` entries from each of the stake,
+/// member and claims maps.
+fn query_debug_dump(deps: Deps) -> StdResult<DebugDumpResponse> {
+    Ok(DebugDumpResponse {
+        contract_version: get_contract_version(deps.storage)?,
+        config: CONFIG.load(deps.storage)?,
+        admin: ADMIN.get(deps)?,
+        dao_dao: DAO_DAO.may_load(deps.storage)?,
+        destination_version: DESTINATION_VERSION
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        total_weight: TOTAL.load(deps.storage)?,
+        pause: query_pause_state(deps)?,
+        sealed: SEALED.may_load(deps.storage)?.unwrap_or_default(),
+        aborted: ABORTED.may_load(deps.storage)?.unwrap_or_default(),
+        migration_start: MIGRATION_START.may_load(deps.storage)?,
+        min_batch_interval: MIN_BATCH_INTERVAL.may_load(deps.storage)?,
+        max_per_block: MAX_PER_BLOCK.may_load(deps.storage)?,
+        completion_threshold: COMPLETION_THRESHOLD.may_load(deps.storage)?,
+        treasury: TREASURY.may_load(deps.storage)?,
+        funds_recipient: FUNDS_RECIPIENT.may_load(deps.storage)?,
+        migration_proxy: MIGRATION_PROXY.may_load(deps.storage)?,
+        monitor: MONITOR.may_load(deps.storage)?,
+        auto_pause_on_shortfall: AUTO_PAUSE_ON_SHORTFALL
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        migrate_entries_enabled: MIGRATE_ENTRIES_ENABLED
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        metrics: MIGRATION_METRICS
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        claims_cursor: CLAIMS_CURSOR.may_load(deps.storage)?.flatten(),
+        last_batch: LAST_BATCH.may_load(deps.storage)?,
+        has_prepared_batch: PREPARED_BATCH.exists(deps.storage),
+        has_pending_batch: PENDING_BATCH.exists(deps.storage),
+    })
+}
 
-            let denom = if let Denom::Native(denom) = &config.denom {
-                denom.as_str()
-            } else {
-                unreachable!("CW20 not supported on Kujira");
-            };
-            let execute =
-                wasm_execute(DAO_DAO.load(deps.storage)?, &msg, coins(sum.u128(), denom))?;
+fn query_summary(deps: Deps) -> StdResult<SummaryResponse> {
+    let mut truncated = false;
 
-            Ok(Response::new()
-                .add_message(execute)
-                .add_attribute("action", "migrate"))
-        }
+    let mut stakes = STAKE.range(deps.storage, None, None, Order::Ascending);
+    let mut total_staked = Uint128::zero();
+    for item in stakes.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        total_staked += item?.1;
+    }
+    truncated |= stakes.next().is_some();
+
+    let mut members = MEMBERS.keys(deps.storage, None, None, Order::Ascending);
+    let member_count = members.by_ref().take(MAX_DISTRIBUTION_SCAN).count() as u64;
+    truncated |= members.next().is_some();
+
+    let claims_map = claims_storage();
+    let mut claims = claims_map.range(deps.storage, None, None, Order::Ascending);
+    let mut total_claims = Uint128::zero();
+    let mut claim_count = 0u64;
+    for item in claims.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (_, claims) = item?;
+        total_claims += claims.iter().map(|claim| claim.amount).sum::<Uint128>();
+        claim_count += claims.len() as u64;
     }
+    truncated |= claims.next().is_some();
+
+    Ok(SummaryResponse {
+        total_weight: TOTAL.may_load(deps.storage)?.unwrap_or_default(),
+        total_staked,
+        member_count,
+        total_claims,
+        claim_count,
+        dao_dao: DAO_DAO.may_load(deps.storage)?,
+        migration_complete: migration_complete(deps.storage)?,
+        truncated,
+    })
 }
 
-pub fn must_pay_funds(balance: &NativeBalance, denom: &str) -> Result<Uint128, ContractError> {
-    match balance.0.len() {
-        0 => Err(ContractError::NoFunds {}),
-        1 => {
-            let balance = &balance.0;
-            let payment = balance[0].amount;
-            if balance[0].denom == denom {
-                Ok(payment)
-            } else {
-                Err(ContractError::MissingDenom(denom.to_string()))
-            }
+fn query_forwarding_delta(deps: Deps) -> StdResult<ForwardingDeltaResponse> {
+    let remaining_total = TOTAL.load(deps.storage)?;
+    let forwarded_weight = MIGRATION_METRICS
+        .may_load(deps.storage)?
+        .map_or(0, |metrics| metrics.forwarded_weight);
+    // nothing was forwarded before the snapshot of the first batch
+    let initial_total = PRE_MIGRATION_SNAPSHOT
+        .may_load(deps.storage)?
+        .map_or(remaining_total, |snapshot| snapshot.total_weight);
+    Ok(ForwardingDeltaResponse {
+        initial_total,
+        forwarded_weight,
+        remaining_total,
+        consistent: initial_total == forwarded_weight + remaining_total,
+    })
+}
+
+fn query_migration_breakdown(deps: Deps) -> StdResult<MigrationBreakdownResponse> {
+    let migrated_amount = query_migrated_total(deps)?.amount;
+    let (remaining_amount, truncated) = accounted_total(deps)?;
+    Ok(MigrationBreakdownResponse {
+        migrated_amount,
+        remaining_amount,
+        total_amount: migrated_amount + remaining_amount,
+        truncated,
+    })
+}
+
+fn query_average_batch_size(deps: Deps) -> StdResult<AverageBatchSizeResponse> {
+    let metrics = MIGRATION_METRICS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let average = |count: u64| match metrics.batches_run {
+        0 => Decimal::zero(),
+        batches => Decimal::from_ratio(count, batches),
+    };
+    Ok(AverageBatchSizeResponse {
+        avg_stakes: average(metrics.stakes_migrated),
+        avg_claims: average(metrics.claims_migrated),
+    })
+}
+
+fn query_migrated_total(deps: Deps) -> StdResult<MigratedTotalResponse> {
+    let metrics = MIGRATION_METRICS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    Ok(MigratedTotalResponse {
+        amount: metrics.total_moved,
+        stake_count: metrics.stakes_migrated,
+        claim_count: metrics.claims_migrated,
+    })
+}
+
+/// Counts the addresses of `keys` that `keep` accepts, reading at most
+/// `MAX_DISTRIBUTION_SCAN` of them, and whether any were left unread.
+fn count_bounded(
+    mut keys: impl Iterator<Item = StdResult<Addr>>,
+    keep: impl Fn(&Addr) -> bool,
+) -> StdResult<(u64, bool)> {
+    let mut count = 0u64;
+    for addr in keys.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        if keep(&addr?) {
+            count += 1;
         }
-        _ => Err(ContractError::ExtraDenoms(denom.to_string())),
     }
+    Ok((count, keys.next().is_some()))
 }
 
-#[inline]
-fn coin_to_string(amount: Uint128, denom: &str) -> String {
-    format!("{} {}", amount, denom)
+/// Counts the stakes and claim addresses left and assumes each block migrates
+/// `num` of them. Reads at most `MAX_DISTRIBUTION_SCAN` entries from each of
+/// the stake and claims maps.
+fn query_estimated_completion(
+    deps: Deps,
+    env: Env,
+    num: u64,
+) -> StdResult<Option<EstimatedCompletionResponse>> {
+    let batches_run = MIGRATION_METRICS
+        .may_load(deps.storage)?
+        .map_or(0, |metrics| metrics.batches_run);
+    if batches_run == 0 || num == 0 {
+        return Ok(None);
+    }
+    let (stakes, stakes_truncated) = count_bounded(
+        STAKE.keys(deps.storage, None, None, Order::Ascending),
+        |_| true,
+    )?;
+    let claims_map = claims_storage();
+    let (claims, claims_truncated) = count_bounded(
+        claims_map.keys(deps.storage, None, None, Order::Ascending),
+        |_| true,
+    )?;
+    Ok(Some(EstimatedCompletionResponse {
+        height: env.block.height + (stakes + claims).div_ceil(num),
+        truncated: stakes_truncated || claims_truncated,
+    }))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Member {
-            addr,
-            at_height: height,
-        } => to_json_binary(&query_member(deps, addr, height)?),
-        QueryMsg::ListMembers { start_after, limit } => {
-            to_json_binary(&list_members(deps, start_after, limit)?)
-        }
-        QueryMsg::TotalWeight {} => to_json_binary(&query_total_weight(deps)?),
-        QueryMsg::Claims { address } => {
-            to_json_binary(&CLAIMS.query_claims(deps, &deps.api.addr_validate(&address)?)?)
+/// Reads at most `MAX_DISTRIBUTION_SCAN` entries from each of the stake and
+/// claims maps.
+fn query_txs_to_finish(deps: Deps, num: u64, num_claims: u64) -> StdResult<TxsToFinishResponse> {
+    let included = |addr: &Addr| !EXCLUDE.has(deps.storage, addr);
+    let (stakes, stakes_truncated) = count_bounded(
+        STAKE.keys(deps.storage, None, None, Order::Ascending),
+        included,
+    )?;
+    let claims_map = claims_storage();
+    let (claims, claims_truncated) = count_bounded(
+        claims_map.keys(deps.storage, None, None, Order::Ascending),
+        included,
+    )?;
+    let batches = |remaining: u64, size: u64| match (remaining, size) {
+        (0, _) => Ok(0),
+        (_, 0) => Err(StdError::generic_err(
+            "A batch size of zero never migrates what is left",
+        )),
+        _ => Ok(remaining.div_ceil(size)),
+    };
+    let mut txs = batches(stakes, num)?.max(batches(claims, num_claims)?);
+    if let Some(max) = MAX_PER_BLOCK.may_load(deps.storage)? {
+        txs = txs.max(batches(stakes + claims, max)?);
+    }
+    Ok(TxsToFinishResponse {
+        txs,
+        truncated: stakes_truncated || claims_truncated,
+    })
+}
+
+fn query_instantiate_params(deps: Deps) -> StdResult<InstantiateParamsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(InstantiateParamsResponse {
+        min_bond_weight: (config.min_bond / config.tokens_per_weight).u128() as u64,
+        admin: ADMIN.get(deps)?,
+        config,
+    })
+}
+
+fn query_is_admin(deps: Deps, address: String) -> StdResult<IsAdminResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    Ok(IsAdminResponse {
+        is_admin: ADMIN.is_admin(deps, &addr)?,
+    })
+}
+
+fn query_confirm_dao_dao(deps: Deps, addr: String) -> StdResult<ConfirmDaoDaoResponse> {
+    let dao_dao = DAO_DAO.may_load(deps.storage)?;
+    Ok(ConfirmDaoDaoResponse {
+        matches: dao_dao.is_some_and(|dao_dao| dao_dao.as_str() == addr),
+    })
+}
+
+fn query_admin_matches_dao(deps: Deps) -> StdResult<AdminMatchesDaoResponse> {
+    let (Some(admin), Some(dao_dao)) = (ADMIN.get(deps)?, DAO_DAO.may_load(deps.storage)?) else {
+        return Ok(AdminMatchesDaoResponse { matches: false });
+    };
+    let dao_admin = deps.querier.query_wasm_contract_info(dao_dao)?.admin;
+    Ok(AdminMatchesDaoResponse {
+        matches: dao_admin.is_some_and(|dao_admin| dao_admin == admin.as_str()),
+    })
+}
+
+/// Reads at most `MAX_DISTRIBUTION_SCAN` entries from each of the stake and
+/// claims maps.
+fn query_migration_status(deps: Deps) -> StdResult<MigrationStatusResponse> {
+    let mut truncated = false;
+    let mut remaining_amount = Uint128::zero();
+
+    let mut stakes = STAKE.range(deps.storage, None, None, Order::Ascending);
+    let mut remaining_stakes = 0u64;
+    for item in stakes.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        remaining_amount += item?.1;
+        remaining_stakes += 1;
+    }
+    truncated |= stakes.next().is_some();
+
+    let claims_map = claims_storage();
+    let mut claims = claims_map.range(deps.storage, None, None, Order::Ascending);
+    let mut remaining_claims = 0u64;
+    for item in claims.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (_, claims) = item?;
+        remaining_amount += claims.iter().map(|claim| claim.amount).sum::<Uint128>();
+        remaining_claims += 1;
+    }
+    truncated |= claims.next().is_some();
+
+    Ok(MigrationStatusResponse {
+        claims_cursor: CLAIMS_CURSOR.may_load(deps.storage)?.flatten(),
+        stakes_complete: remaining_stakes == 0,
+        claims_complete: remaining_claims == 0,
+        complete: migration_complete(deps.storage)?,
+        remaining_stakes,
+        remaining_claims,
+        total_weight: TOTAL.may_load(deps.storage)?.unwrap_or_default(),
+        remaining_amount,
+        truncated,
+    })
+}
+
+/// No claims are left, and neither are stakes unless the remaining total
+/// weight is below `COMPLETION_THRESHOLD`.
+fn migration_complete(storage: &dyn Storage) -> StdResult<bool> {
+    let claims_map = claims_storage();
+    if !claims_map.is_empty(storage) {
+        return Ok(false);
+    }
+    let threshold = COMPLETION_THRESHOLD.may_load(storage)?.unwrap_or_default();
+    let total = TOTAL.may_load(storage)?.unwrap_or_default();
+    Ok(STAKE.is_empty(storage) || total < threshold)
+}
+
+fn query_storage_layout() -> StorageLayoutResponse {
+    StorageLayoutResponse {
+        stake: STAKE_KEY.to_string(),
+        members: cw4::MEMBERS_KEY.to_string(),
+        total: cw4::TOTAL_KEY.to_string(),
+        claims: CLAIMS_KEY.to_string(),
+        config: CONFIG_KEY.to_string(),
+        admin: ADMIN_KEY.to_string(),
+        hooks: HOOKS_KEY.to_string(),
+    }
+}
+
+fn query_contract_info(deps: Deps, env: Env) -> StdResult<ContractIdentityResponse> {
+    Ok(ContractIdentityResponse {
+        address: env.contract.address,
+        code_version: get_contract_version(deps.storage)?,
+    })
+}
+
+fn query_claim_denoms(deps: Deps) -> StdResult<ClaimDenomsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ClaimDenomsResponse {
+        denoms: vec![config.denom],
+    })
+}
+
+/// Up to `limit` claims whose `release_at` matches, in address order. Reads
+/// at most `MAX_DISTRIBUTION_SCAN` addresses of the claims map.
+fn scan_claims(
+    deps: Deps,
+    limit: u32,
+    matches: impl Fn(&Expiration) -> bool,
+) -> StdResult<ClaimsInRangeResponse> {
+    let limit = limit.min(MAX_LIMIT) as usize;
+    let claims_map = claims_storage();
+    let mut entries = claims_map.range(deps.storage, None, None, Order::Ascending);
+    let mut claims = vec![];
+    for item in entries.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (addr, addr_claims) = item?;
+        claims.extend(
+            addr_claims
+                .into_iter()
+                .filter(|claim| matches(&claim.release_at))
+                .map(|claim| (addr.clone(), claim)),
+        );
+        if claims.len() >= limit {
+            break;
         }
-        QueryMsg::Staked { address } => to_json_binary(&query_staked(deps, address)?),
-        QueryMsg::Admin {} => to_json_binary(&ADMIN.query_admin(deps)?),
-        QueryMsg::Hooks {} => to_json_binary(&HOOKS.query_hooks(deps)?),
-        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
     }
+    let truncated = claims.len() > limit || entries.next().is_some();
+    claims.truncate(limit);
+    Ok(ClaimsInRangeResponse { claims, truncated })
 }
 
-fn query_total_weight(deps: Deps) -> StdResult<TotalWeightResponse> {
-    let weight = TOTAL.load(deps.storage)?;
-    Ok(TotalWeightResponse { weight })
+/// Claims releasing at a height within `[from, to]`, in address order.
+/// Time based claims are ignored.
+fn query_claims_in_height_range(
+    deps: Deps,
+    from: u64,
+    to: u64,
+    limit: u32,
+) -> StdResult<ClaimsInRangeResponse> {
+    scan_claims(
+        deps,
+        limit,
+        |release_at| matches!(release_at, Expiration::AtHeight(height) if (from..=to).contains(height)),
+    )
+}
+
+fn query_claims_by_type(
+    deps: Deps,
+    height_based: bool,
+    limit: u32,
+) -> StdResult<ClaimsInRangeResponse> {
+    scan_claims(deps, limit, |release_at| match release_at {
+        Expiration::AtHeight(_) => height_based,
+        Expiration::AtTime(_) => !height_based,
+        Expiration::Never {} => false,
+    })
+}
+
+/// Earliest `release_at` among the address's claims. Height and time
+/// expirations cannot be ordered against each other, so the first one seen is
+/// kept when both kinds are present.
+fn query_next_maturity(deps: Deps, address: String) -> StdResult<Option<Expiration>> {
+    let addr = deps.api.addr_validate(&address)?;
+    let next = CLAIMS
+        .query_claims(deps, &addr)?
+        .claims
+        .into_iter()
+        .map(|claim| claim.release_at)
+        .reduce(|next, release_at| if release_at < next { release_at } else { next });
+    Ok(next)
+}
+
+fn query_claims_info(deps: Deps, address: String) -> StdResult<ClaimsInfoResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let claims = CLAIMS.query_claims(deps, &addr)?.claims;
+    let oldest = claims
+        .iter()
+        .map(|claim| claim.release_at)
+        .reduce(|oldest, release_at| {
+            if release_at < oldest {
+                release_at
+            } else {
+                oldest
+            }
+        });
+    let newest = claims
+        .iter()
+        .map(|claim| claim.release_at)
+        .reduce(|newest, release_at| {
+            if release_at > newest {
+                release_at
+            } else {
+                newest
+            }
+        });
+    Ok(ClaimsInfoResponse {
+        count: claims.len() as u64,
+        oldest,
+        newest,
+    })
+}
+
+fn query_claims_map_stats(deps: Deps, sample: u32) -> StdResult<ClaimsMapStatsResponse> {
+    let sample = (sample as usize).min(MAX_DISTRIBUTION_SCAN);
+    let mut sampled = 0u64;
+    let mut total_size = 0u64;
+    let mut max_size = 0u64;
+    for item in claims_storage()
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(sample)
+    {
+        let (_, claims) = item?;
+        // the same measure `defer_oversized_claims` holds against
+        // `MAX_CLAIMS_PAYLOAD`
+        let size = to_json_vec(&claims)?.len() as u64;
+        sampled += 1;
+        total_size += size;
+        max_size = max_size.max(size);
+    }
+    Ok(ClaimsMapStatsResponse {
+        sampled,
+        average_size: total_size.checked_div(sampled).unwrap_or_default(),
+        max_size,
+    })
+}
+
+fn query_claims_bounded(deps: Deps, address: String, max: u32) -> StdResult<ClaimsBoundedResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let mut claims = CLAIMS.query_claims(deps, &addr)?.claims;
+    let truncated = claims.len() > max as usize;
+    claims.truncate(max as usize);
+    Ok(ClaimsBoundedResponse { claims, truncated })
+}
+
+fn query_claims_normalized(deps: Deps, address: String) -> StdResult<ClaimsNormalizedResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let claims = CLAIMS
+        .query_claims(deps, &addr)?
+        .claims
+        .into_iter()
+        .map(|claim| {
+            let (release_height, release_time) = match claim.release_at {
+                Expiration::AtHeight(height) => (Some(height), None),
+                Expiration::AtTime(time) => (None, Some(time.seconds())),
+                Expiration::Never {} => (None, None),
+            };
+            NormalizedClaim {
+                amount: claim.amount,
+                release_height,
+                release_time,
+            }
+        })
+        .collect();
+    Ok(ClaimsNormalizedResponse { claims })
 }
 
 pub fn query_staked(deps: Deps, addr: String) -> StdResult<StakedResponse> {
@@ -172,10 +3932,98 @@ fn query_member(deps: Deps, addr: String, height: Option<u64>) -> StdResult<Memb
     Ok(MemberResponse { weight })
 }
 
+// max number of entries read from a map by any scanning query
+const MAX_DISTRIBUTION_SCAN: usize = 1000;
+
+// max number of batches listed by UpcomingBatches
+const MAX_UPCOMING_BATCHES: u64 = 10;
+
+/// The stored weight of `address` next to the one its stake gives under the
+/// current config.
+fn query_effective_weight(deps: Deps, address: String) -> StdResult<EffectiveWeightResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let config = CONFIG.load(deps.storage)?;
+    let stake = STAKE.may_load(deps.storage, &addr)?.unwrap_or_default();
+    let computed = if stake < config.min_bond {
+        0
+    } else {
+        (stake / config.tokens_per_weight).u128() as u64
+    };
+    let stored = MEMBERS.may_load(deps.storage, &addr)?;
+    Ok(EffectiveWeightResponse {
+        stored,
+        computed,
+        consistent: stored.unwrap_or_default() == computed,
+    })
+}
+
+fn query_member_change_count(deps: Deps, address: String) -> StdResult<MemberChangeCountResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let mut heights =
+        MEMBERS
+            .changelog()
+            .prefix(&addr)
+            .keys_raw(deps.storage, None, None, Order::Ascending);
+    let count = heights.by_ref().take(MAX_DISTRIBUTION_SCAN).count() as u64;
+    Ok(MemberChangeCountResponse {
+        count,
+        truncated: heights.next().is_some(),
+    })
+}
+
+/// Weights of the given addresses at the start of `height`, `None` for
+/// addresses that weren't members then.
+fn query_members_at(deps: Deps, addrs: Vec<String>, height: u64) -> StdResult<MembersAtResponse> {
+    if addrs.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!(
+            "At most {MAX_LIMIT} addresses can be queried at once"
+        )));
+    }
+    let members = addrs
+        .into_iter()
+        .map(|addr| {
+            let weight = MEMBERS.may_load_at_height(
+                deps.storage,
+                &deps.api.addr_validate(&addr)?,
+                height,
+            )?;
+            Ok((addr, weight))
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(MembersAtResponse { members })
+}
+
 // settings for pagination
 const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
 
+// page size of ExportMembers, larger than MAX_LIMIT as it is read off chain
+const MAX_EXPORT_LIMIT: u32 = 500;
+const DEFAULT_EXPORT_LIMIT: u32 = 100;
+
+fn export_members(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ExportMembersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_EXPORT_LIMIT).min(MAX_EXPORT_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let start = addr.as_ref().map(Bound::exclusive);
+
+    let mut members = MEMBERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit + 1)
+        .collect::<StdResult<Vec<_>>>()?;
+    let next = if members.len() > limit {
+        members.truncate(limit);
+        members.last().map(|(addr, _)| addr.clone())
+    } else {
+        None
+    };
+
+    Ok(ExportMembersResponse { members, next })
+}
+
 fn list_members(
     deps: Deps,
     start_after: Option<String>,
@@ -198,3 +4046,151 @@ fn list_members(
 
     Ok(MemberListResponse { members })
 }
+
+fn list_members_above_weight(
+    deps: Deps,
+    weight: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<MembersAboveWeightResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let start = addr.as_ref().map(Bound::exclusive);
+
+    let mut entries = MEMBERS.range(deps.storage, start, None, Order::Ascending);
+    let mut members = vec![];
+    let mut last_read = None;
+    for item in entries.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (addr, member_weight) = item?;
+        last_read = Some(addr.clone());
+        if member_weight <= weight {
+            continue;
+        }
+        members.push(Member {
+            addr: addr.into(),
+            weight: member_weight,
+        });
+        if members.len() == limit {
+            break;
+        }
+    }
+
+    Ok(MembersAboveWeightResponse {
+        members,
+        next_start_after: last_read.filter(|_| entries.next().is_some()),
+    })
+}
+
+fn list_discrepancies(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<DiscrepanciesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let discrepancies = MIGRATION_DISCREPANCIES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    Ok(DiscrepanciesResponse { discrepancies })
+}
+
+fn query_export_receipts(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ExportReceiptsResponse> {
+    let receipts = list_migrated(deps, start_after, limit)?.migrated;
+    let csv = receipts
+        .iter()
+        .map(|r| format!("{},{},{},{}", r.addr, r.weight, r.amount, r.height))
+        .collect::<Vec<_>>()
+        .join(";");
+    Ok(ExportReceiptsResponse { receipts, csv })
+}
+
+fn list_migrated(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListMigratedResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let start = addr.as_ref().map(Bound::exclusive);
+
+    let migrated = MIGRATED
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, receipt)| receipt))
+        .collect::<StdResult<_>>()?;
+
+    Ok(ListMigratedResponse { migrated })
+}
+
+/// Reads at most `MAX_DISTRIBUTION_SCAN` addresses of the claims map per
+/// page, so a page may hold fewer than `limit` entries before the end.
+fn list_orphan_claims(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OrphanClaimsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = maybe_addr(deps.api, start_after)?.map(Bound::exclusive);
+
+    let claims_map = claims_storage();
+    let mut entries = claims_map.range(deps.storage, start, None, Order::Ascending);
+    let mut claims = vec![];
+    let mut last_read = None;
+    for item in entries.by_ref().take(MAX_DISTRIBUTION_SCAN) {
+        let (addr, addr_claims) = item?;
+        last_read = Some(addr.clone());
+        if STAKE.has(deps.storage, &addr) || MEMBERS.may_load(deps.storage, &addr)?.is_some() {
+            continue;
+        }
+        claims.push((addr, addr_claims));
+        if claims.len() == limit {
+            break;
+        }
+    }
+
+    Ok(OrphanClaimsResponse {
+        claims,
+        next_start_after: last_read.filter(|_| entries.next().is_some()),
+    })
+}
+
+fn list_failed(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListFailedResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = maybe_addr(deps.api, start_after)?;
+    let start = addr.as_ref().map(Bound::exclusive);
+
+    let failed = FAILED
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    Ok(ListFailedResponse { failed })
+}
+
+fn query_batch_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<BatchHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let batches = BATCH_LOG
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<_>>()?;
+
+    Ok(BatchHistoryResponse { batches })
+}
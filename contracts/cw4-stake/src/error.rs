@@ -1,7 +1,8 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 use cw_controllers::{AdminError, HookError};
+use cw_utils::{Expiration, ParseReplyError};
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
@@ -14,12 +15,18 @@ pub enum ContractError {
     #[error("{0}")]
     Hook(#[from] HookError),
 
+    #[error("{0}")]
+    ParseReply(#[from] ParseReplyError),
+
     #[error("Disabled during Migration")]
     MigrationDisabled {},
 
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("No admin is set, so no migration batch can be authorized")]
+    NoAdmin {},
+
     #[error("No claims that can be released currently")]
     NothingToClaim {},
 
@@ -37,4 +44,138 @@ pub enum ContractError {
 
     #[error("No data in ReceiveMsg")]
     NoData {},
+
+    #[error("Migration is paused")]
+    Paused {},
+
+    #[error("Cannot sweep the staking denom {denom}")]
+    CannotSweepStakingDenom { denom: String },
+
+    #[error("Stakes or claims are left after the batch, cannot finalize")]
+    MigrationNotComplete {},
+
+    #[error("No batch has been prepared")]
+    NoPreparedBatch {},
+
+    #[error("Stakes or claims changed since the batch was prepared")]
+    PreparedBatchChanged {},
+
+    #[error("Prepared batch sends {prepared}, expected {expected}")]
+    PreparedSumMismatch {
+        expected: Uint128,
+        prepared: Uint128,
+    },
+
+    #[error("Migration must be paused first")]
+    NotPaused {},
+
+    #[error("Stake migration is paused")]
+    StakeMigrationPaused {},
+
+    #[error("Claim migration is paused")]
+    ClaimMigrationPaused {},
+
+    #[error("Cannot migrate more than {max} entries in one block")]
+    BlockLimitExceeded { max: u64 },
+
+    #[error("Migration starts at height {start}")]
+    MigrationNotStarted { start: u64 },
+
+    #[error("Too soon since the last batch, next batch allowed at {earliest}")]
+    BatchTooSoon { earliest: Expiration },
+
+    #[error("A callback cannot be used together with funds_to")]
+    CallbackWithFundsTo {},
+
+    #[error("{denom} is not a valid native denom")]
+    InvalidForwardDenom { denom: String },
+
+    #[error("{addr} has a claim released after the allowed maximum")]
+    ClaimReleaseTooFar { addr: String },
+
+    #[error("{addr} is excluded from migration")]
+    AddressExcluded { addr: String },
+
+    #[error("No treasury set to receive forfeited claims")]
+    TreasuryNotSet {},
+
+    #[error("Config not found")]
+    ConfigNotFound {},
+
+    #[error(
+        "Batch would bring funds sent to DAO DAO to {forwarded}, above the {backing} ever held"
+    )]
+    ForwardExceedsBacking {
+        forwarded: Uint128,
+        backing: Uint128,
+    },
+
+    #[error("Total weight {total} is below the {removed} removed by the batch")]
+    TotalWeightUnderflow { total: u64, removed: u64 },
+
+    #[error("DAO DAO address not set, run the contract migration first")]
+    DaoDaoNotSet {},
+
+    #[error("{addr} is not a DAO registered with the registry")]
+    NotRegisteredDao { addr: String },
+
+    #[error("Failed to remove member {addr}: {reason}")]
+    RemoveMember { addr: String, reason: String },
+
+    #[error("Migration has been aborted")]
+    MigrationAborted {},
+
+    #[error("Contract is sealed")]
+    Sealed {},
+
+    #[error("Contract still holds {balance}, cannot seal")]
+    BalanceNotEmpty { balance: Uint128 },
+
+    #[error("Claims are in {found}, expected {expected}")]
+    ClaimDenomMismatch { expected: String, found: String },
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Batch must migrate at least one stake or claim")]
+    EmptyBatch {},
+
+    #[error("The pre-migration snapshot must be prepared before migrating")]
+    SnapshotNotTaken {},
+
+    #[error("The pre-migration snapshot is already taken")]
+    SnapshotTaken {},
+
+    #[error("MigrateEntries batches are not enabled")]
+    MigrateEntriesDisabled {},
+
+    #[error("DAO DAO contract is {found}, which cannot handle MigrateStakes")]
+    IncompatibleDestination { found: String },
+
+    #[error("DAO DAO contract runs version {found}, which cannot handle V2 MigrateStakes")]
+    UnsupportedDestinationVersion { found: String },
+
+    #[error("DAO DAO contract has code id {found}, expected {expected}")]
+    UnexpectedCodeId { expected: u64, found: u64 },
+
+    #[error("Claims already exist for {addr}, set overwrite to replace them")]
+    ClaimsExist { addr: String },
+
+    #[error("Cannot import more than {max} addresses at once")]
+    ImportTooLarge { max: u64 },
+
+    #[error("Too many claims for {addr}, at most 50 can be imported per address")]
+    TooManyClaims { addr: String },
+
+    #[error("tokens_per_weight must be greater than zero")]
+    ZeroTokensPerWeight {},
+
+    #[error("min_bond must be at least 1")]
+    ZeroMinBond {},
+
+    #[error("Stakes are held, changing how weights are computed would leave them stale")]
+    StakesHeld {},
+
+    #[error("Stakes or claims are held in the staking denom, it can't change")]
+    StakingFundsHeld {},
 }
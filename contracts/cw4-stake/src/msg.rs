@@ -1,12 +1,17 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Binary, CosmosMsg, Decimal, Uint128};
 
+use cw2::ContractVersion;
 use cw20::Denom;
-pub use cw_controllers::ClaimsResponse;
-use cw_utils::Duration;
+use cw4::Member;
+pub use cw_controllers::{Claim, ClaimsResponse};
+use cw_utils::{Duration, Expiration};
 use kujira::CallbackData;
 
-use crate::state::Config;
+use crate::state::{
+    BatchRecord, Config, DestinationVersion, Discrepancy, LastBatch, LastReplyData,
+    MigrationMetrics, MigrationReceipt, PreMigrationSnapshot,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -16,19 +21,326 @@ pub struct InstantiateMsg {
     pub min_bond: Uint128,
     pub unbonding_period: Duration,
 
-    // admin can only add/remove hooks, not change other parameters
+    // admin manages hooks and the migration, and may change these parameters
+    // through `UpdateConfig`
     pub admin: Option<String>,
 }
 
 #[cw_serde]
 pub struct MigrateMsg {
-    pub dao_dao_addr: Addr,
+    /// DAO DAO contract to migrate to. Required on the first upgrade, left
+    /// as it is if unset on a later one.
+    pub dao_dao_addr: Option<Addr>,
+    /// Height migration batches may start at, see `UpdateMigrationStart`.
+    /// Left as it is if unset.
+    pub migration_start: Option<u64>,
+    /// DAO DAO registry the DAO DAO contract must be registered with, asked
+    /// with a `RegistryQueryMsg`. Left as it is if unset.
+    pub registry: Option<Addr>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    /// Migrates a batch of user stakes to DAO DAO.
-    MigrateToDaoDao { num: u64, num_claims: u64 },
+    /// Admin only. Migrates the next `num` stakes and the claims of the next
+    /// `num_claims` addresses to DAO DAO, see the README for how they are
+    /// carried over. With `num_claims` above zero, the `claims_exhausted`
+    /// attribute tells whether the claims map was read to its end.
+    MigrateToDaoDao {
+        num: u64,
+        num_claims: u64,
+        /// Skip the admin's stake and claims, leaving them for a final batch.
+        protect_admin: Option<bool>,
+        /// Send one `MigrateEntries` entry per address, once enabled with
+        /// `UpdateMigrateEntries`.
+        consolidate: Option<bool>,
+        /// Record a discrepancy if the `MigrationAcceptance` replied differs
+        /// from the weight sent.
+        verify: Option<bool>,
+        /// Add the JSON message sent as an `outgoing_payload` attribute.
+        debug: Option<bool>,
+        /// Skip members lighter than this, without counting them in `num`.
+        min_weight: Option<u64>,
+        /// Pay the funds here instead of the `UpdateFundsRecipient` one.
+        funds_to: Option<String>,
+        /// Merge each address's claims into one, released at the latest.
+        collapse_claims: Option<bool>,
+        /// Send a `MigrationCallback` once DAO DAO has handled the batch.
+        callback: Option<CallbackData>,
+        /// Shrink `num` and `num_claims` to an estimate of this much gas.
+        until_gas: Option<u64>,
+        /// Send the claims' funds to the `UpdateTreasury` treasury instead.
+        forfeit_claims: Option<bool>,
+        /// Leave out addresses holding more than this many claims.
+        max_claims_per_addr: Option<u32>,
+        /// Reject the batch if a claim it migrates is released after this.
+        max_release: Option<Expiration>,
+        /// Record in each receipt whether DAO DAO's voting power covers it.
+        confirm: Option<bool>,
+        /// Send the funds as this native denom instead of the configured one.
+        forward_denom: Option<String>,
+        /// Share `num + num_claims` between stakes and claims, taken in turn.
+        interleave: Option<bool>,
+        /// Take the soonest maturing claims of the first 1000 addresses.
+        soonest_first: Option<bool>,
+    },
+    /// Admin only. Runs a `MigrateToDaoDao` batch with default options and,
+    /// as the batch must leave no stakes or claims behind, then removes all
+    /// hooks. Meant for the final batch, in a single governance action.
+    MigrateAndFinalize { num: u64, num_claims: u64 },
+    /// Admin only. Migrates up to `num` addresses from the failed set, those whose
+    /// messages DAO DAO rejected in a batch sent with a `callback`.
+    RetryFailed { num: u64 },
+    /// Admin only. Migrates the stakes and claims of exactly these addresses, with
+    /// default batch options. Fails if any of them is excluded. Addresses
+    /// that already have a migration receipt are skipped and listed in the
+    /// `already_migrated` attribute, so a retried call sends nothing twice.
+    /// Claims they still hold are left to regular batches.
+    MigrateAddresses { addrs: Vec<String> },
+    /// Admin only. Reads up to `num` entries of the stake, member and claims
+    /// maps into the pre-migration snapshot, resuming where the previous call
+    /// stopped, until all are read. No batch runs before the snapshot is
+    /// complete. Stakes or claims written while it is prepared restart it.
+    PrepareSnapshot { num: u64 },
+    /// Admin only. First step of a two step migration batch. Records the stakes and
+    /// claims a `MigrateToDaoDao` with these sizes would send, and their sum,
+    /// without sending anything. Replaces any earlier prepared batch.
+    PrepareBatch { num: u64, num_claims: u64 },
+    /// Admin only. Sends the batch prepared by the same sender, as a `MigrateToDaoDao`
+    /// with default options. Fails if the selected stakes or claims changed
+    /// since it was prepared, or if the prepared sum isn't `expected_sum`.
+    CommitBatch { expected_sum: Uint128 },
+    /// Admin only. Writes claims recovered from an external snapshot into the
+    /// claims map. Existing claims are only replaced if `overwrite` is set.
+    /// `denom` is the denom the claims are held in and must match the
+    /// configured one. At most 50 claims are accepted per address.
+    ImportClaims {
+        entries: Vec<(String, Vec<Claim>)>,
+        denom: Denom,
+        overwrite: bool,
+    },
+    /// Admin only. Sets the code id the DAO DAO contract must be running for
+    /// `MigrateToDaoDao` to proceed. `None` disables the check.
+    UpdateDaoDaoCodeId { code_id: Option<u64> },
+    /// Admin only. Limits how many stakes and claim entries may be migrated
+    /// within a single block. `None` removes the limit.
+    UpdateMaxPerBlock { max_per_block: Option<u64> },
+    /// Admin only. Requires at least `interval` between the start of two
+    /// migration batches, to pace the migration. `None` removes the limit.
+    UpdateMinBatchInterval { interval: Option<Duration> },
+    /// Admin only. Rejects migration batches before height `start`, to hold
+    /// to an announced schedule. `None` allows them right away.
+    UpdateMigrationStart { start: Option<u64> },
+    /// Admin only. Treats migration as complete once the remaining total
+    /// weight is below `threshold` and no claims are left, even though dust
+    /// stakes remain behind. `None` requires every stake to be migrated.
+    UpdateCompletionThreshold { threshold: Option<u64> },
+    /// Admin only. Sets the number of entries a batch should aim for, which
+    /// `MigrateToDaoDao` uses for the `BatchFeedback` in its data. `None`
+    /// restores the default.
+    UpdateTargetOps { target_ops: Option<u64> },
+    /// Admin only. Sets the address forfeited claims are paid to. `None`
+    /// removes it, which disables `forfeit_claims`.
+    UpdateTreasury { treasury: Option<String> },
+    /// Admin only. For a destination that rejects messages carrying funds:
+    /// while a `recipient` is set, every batch is sent to DAO DAO unfunded
+    /// and its funds paid to `recipient` instead, as with `funds_to`. `None`
+    /// sends the funds along with the messages again.
+    UpdateFundsRecipient { recipient: Option<String> },
+    /// Admin only. Executes every batch on `proxy`, an adapter that forwards
+    /// `MigrateStakes` to DAO DAO, instead of on DAO DAO itself. The
+    /// destination checks run against DAO DAO as before. `None` sends
+    /// batches to DAO DAO directly again.
+    UpdateMigrationProxy { proxy: Option<String> },
+    /// Admin only. Sends `monitor` a `MonitorExecuteMsg::BatchNotification`
+    /// after every batch. A failing monitor doesn't fail the batch. `None`
+    /// stops the notifications.
+    UpdateMonitor { monitor: Option<String> },
+    /// Admin only. Selects the `MigrateStakes` shape batches send, for a
+    /// DAO DAO destination that expects a different one. `V2` is rejected,
+    /// here and by every batch, while the destination reports a
+    /// dao-voting-token-staked release no newer than the bundled one.
+    UpdateDestinationVersion { version: DestinationVersion },
+    /// Admin only. While enabled, a batch first compares the contract's
+    /// balance against its stakes and claims, as `TopUpNeeded` does. On a
+    /// shortfall nothing is sent: migration is paused and the call succeeds
+    /// with an `auto_paused` attribute, so the pause is kept. The check works
+    /// from the backing recorded with the pre-migration snapshot.
+    UpdateAutoPauseOnShortfall { enabled: bool },
+    /// Admin only. Allows batches with `consolidate` set, for a DAO DAO
+    /// destination that accepts `MigrateEntries`.
+    UpdateMigrateEntries { enabled: bool },
+    /// Admin only, and only while migration is paused. Overwrites `TOTAL`
+    /// with `weight`, to repair accounting that drifted from the members'
+    /// summed weights. Nothing is checked against the members map, so only
+    /// use it after reconciling the weights off chain.
+    SetTotal { weight: u64 },
+    /// Admin only, and only while migration is paused. Overwrites the stake of
+    /// `addr` with `new_stake`, recomputing its weight and moving `TOTAL` by
+    /// the difference. No tokens move, so the contract balance has to be
+    /// reconciled separately.
+    AdjustStake { addr: String, new_stake: Uint128 },
+    /// Admin only, and only while migration is paused. Updates the config
+    /// fields that are set, all at once. Weights are not recomputed, so
+    /// `tokens_per_weight` and `min_bond` can only change while no stakes are
+    /// held. `denom` can only change while no stakes or claims are held, as
+    /// they are backed by the current one.
+    UpdateConfig {
+        denom: Option<Denom>,
+        tokens_per_weight: Option<Uint128>,
+        min_bond: Option<Uint128>,
+        unbonding_period: Option<Duration>,
+    },
+    /// Admin only. Sends the contract's whole balance of a native `denom` to
+    /// `to`, to recover tokens sent here by mistake. The staking denom backs
+    /// stakes and claims and is refused.
+    SweepForeignDenom { denom: String, to: String },
+    /// Admin only, once no stakes or claims are left. Sends whatever is left
+    /// of the staking denom to `to`, leaving the contract empty.
+    FinalizeResidual { to: String },
+    /// Admin only. Adds a contract to be listed by the `Hooks` query.
+    AddHook { addr: String },
+    /// Admin only. Removes a hook added by `AddHook`.
+    RemoveHook { addr: String },
+    /// Admin only. Permanently blocks further `MigrateToDaoDao` calls.
+    /// Batches already sent are not reversed.
+    AbortMigration {},
+    /// Admin only. Tops up the contract with the attached funds of the
+    /// staking denom, to cover a shortfall found by `TopUpNeeded`. Nothing
+    /// is staked or credited to anyone. A cw20 staking denom is topped up
+    /// with a plain cw20 transfer instead.
+    Fund {},
+    /// Admin only, once no stakes or claims are left and the contract holds
+    /// none of the staking denom. Rejects every execute message from then
+    /// on, admin ones included, so the emptied contract can't be used again.
+    Seal {},
+    /// Admin only. Refunds and removes up to `num` stakes that give no
+    /// weight, being below `min_bond` or `tokens_per_weight`, along with
+    /// their member entry if they have one. Reads at most
+    /// `MAX_DISTRIBUTION_SCAN` stakes after `start_after`. While more are
+    /// left, the `next_start_after` attribute gives where to resume.
+    PruneZeroWeightMembers {
+        num: u64,
+        start_after: Option<String>,
+    },
+    /// Admin only. Adds or removes addresses from the set skipped by
+    /// migration batches.
+    UpdateExcluded {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Admin only. Pauses or resumes migration. `paused` blocks every batch,
+    /// the granular flags block only forwarding of stakes or of claims.
+    /// Flags left as `None` are unchanged.
+    UpdatePauseState {
+        paused: Option<bool>,
+        stake_migration_paused: Option<bool>,
+        claim_migration_paused: Option<bool>,
+    },
+}
+
+/// Messages sent to DAO DAO other than the default `MigrateStakes`.
+#[cw_serde]
+pub enum DaoDaoExecuteMsg {
+    /// Migrates a batch where each address appears exactly once. Not
+    /// understood by the bundled dao-voting-token-staked, see
+    /// `UpdateMigrateEntries`.
+    MigrateEntries { entries: Vec<MigrateEntry> },
+    /// `MigrateStakes` of a `DestinationVersion::V2` destination. Not
+    /// understood by the bundled dao-voting-token-staked.
+    MigrateStakes {
+        stakes: Vec<StakeEntry>,
+        claims: Vec<ClaimsEntry>,
+    },
+}
+
+#[cw_serde]
+pub struct StakeEntry {
+    pub address: Addr,
+    /// staked tokens
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct ClaimsEntry {
+    pub address: Addr,
+    pub claims: Vec<Claim>,
+}
+
+/// Query the registry set on upgrade must answer.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum RegistryQueryMsg {
+    #[returns(IsRegisteredResponse)]
+    IsRegistered { address: String },
+}
+
+#[cw_serde]
+pub struct IsRegisteredResponse {
+    pub registered: bool,
+}
+
+/// Messages sent to the monitor set by `UpdateMonitor`.
+#[cw_serde]
+pub enum MonitorExecuteMsg {
+    BatchNotification {
+        /// batch number, counting from 1
+        index: u64,
+        stake_count: u64,
+        /// addresses whose claims were migrated
+        claim_count: u64,
+        /// total cw4 weight left after the batch
+        remaining: u64,
+    },
+}
+
+/// Data of a `MigrateToDaoDao` response. The suggestion scales the requested
+/// batch sizes by how far the batch's entry count was from `target_ops`. It is
+/// only advisory, the contract can't observe the gas actually used.
+#[cw_serde]
+pub struct BatchFeedback {
+    /// stakes plus claim entries migrated by this batch
+    pub ops: u64,
+    pub target_ops: u64,
+    pub next_num: u64,
+    pub next_num_claims: u64,
+}
+
+/// Data of the callback sent after a batch with a `callback`.
+#[cw_serde]
+pub struct MigrationCallback {
+    pub batch: u64,
+    pub status: BatchStatus,
+    /// number of the batch's messages DAO DAO rejected
+    pub failed: u64,
+    /// the DAO DAO contract the batch was sent to
+    pub dao_dao: Addr,
+    /// funds migrated over all batches so far, as in `MigratedTotal`
+    pub migrated_total: Uint128,
+}
+
+#[cw_serde]
+pub enum BatchStatus {
+    /// every message was accepted
+    Success,
+    /// some messages were rejected and their entries restored
+    Partial,
+    /// every message was rejected and the whole batch restored
+    Failure,
+}
+
+/// Reply data expected from DAO DAO when a batch is verified.
+#[cw_serde]
+pub struct MigrationAcceptance {
+    /// staked tokens credited by DAO DAO
+    pub weight: Uint128,
+}
+
+#[cw_serde]
+pub struct MigrateEntry {
+    pub addr: Addr,
+    /// staked tokens, zero if the address only has claims
+    pub weight: Uint128,
+    pub claims: Vec<Claim>,
 }
 
 #[cw_serde]
@@ -37,30 +349,343 @@ pub enum QueryMsg {
     /// Claims shows the tokens in process of unbonding for this address
     #[returns(cw_controllers::ClaimsResponse)]
     Claims { address: String },
+    /// The first `max` claims of `Claims`, for a response of bounded size.
+    #[returns(ClaimsBoundedResponse)]
+    ClaimsBounded { address: String, max: u32 },
+    /// Claims whose height based `release_at` is within `[from, to]`. Reads
+    /// at most `MAX_DISTRIBUTION_SCAN` addresses with claims.
+    #[returns(ClaimsInRangeResponse)]
+    ClaimsInHeightRange { from: u64, to: u64, limit: u32 },
+    /// Claims with a height based `release_at`, or with a time based one if
+    /// `height_based` is false. Claims that never release match neither.
+    /// Reads at most `MAX_DISTRIBUTION_SCAN` addresses with claims.
+    #[returns(ClaimsInRangeResponse)]
+    ClaimsByType { height_based: bool, limit: u32 },
+    /// Soonest `release_at` among the address's claims, `None` without claims.
+    #[returns(Option<Expiration>)]
+    NextMaturity { address: String },
+    /// Number of claims held by the address and the earliest and latest
+    /// `release_at` among them. The claims controller does not cap claims per
+    /// address, so a large count here means a costly migration batch.
+    #[returns(ClaimsInfoResponse)]
+    ClaimsInfo { address: String },
+    /// Serialized size of the claims of up to `sample` addresses from the
+    /// start of the claims map, at most 1000, to estimate message sizes
+    /// when picking `num_claims`.
+    #[returns(ClaimsMapStatsResponse)]
+    ClaimsMapStats { sample: u32 },
+    /// Claims for this address with `release_at` split into numeric fields.
+    #[returns(ClaimsNormalizedResponse)]
+    ClaimsNormalized { address: String },
+    /// Total claim amount maturing at each distinct `release_at`, soonest
+    /// first, up to `limit` release points.
+    #[returns(ClaimMaturityScheduleResponse)]
+    ClaimMaturitySchedule { limit: u32 },
+    /// The `limit` addresses holding the most individual claims, most first.
+    /// Ties are ordered by address.
+    #[returns(TopClaimHoldersResponse)]
+    TopClaimHolders { limit: u32 },
+    /// Number of addresses with both a stake and pending claims, the ones
+    /// `consolidate` batches merge into a single entry.
+    #[returns(DualHoldersResponse)]
+    DualHolders {},
+    /// Share of the total weight held by the `k` heaviest members, `k` capped
+    /// at 30.
+    #[returns(TopKConcentrationResponse)]
+    TopKConcentration { k: u32 },
     // Show the number of tokens currently staked by this address.
     #[returns(StakedResponse)]
     Staked { address: String },
 
     #[returns(cw_controllers::AdminResponse)]
     Admin {},
+    /// Whether `address` is the current admin.
+    #[returns(IsAdminResponse)]
+    IsAdmin { address: String },
     #[returns(cw4::TotalWeightResponse)]
     TotalWeight {},
+    /// Totals of stakes, members and claims along with the migration state,
+    /// in one call. See `SummaryResponse`.
+    #[returns(SummaryResponse)]
+    Summary {},
+    /// Config, admin, flags, metrics and cursors in one response, to paste
+    /// into a bug report. Reads single items only, no maps.
+    #[returns(DebugDumpResponse)]
+    DebugDump {},
+    /// Total weight as it was at the start of the given height.
+    #[returns(cw4::TotalWeightResponse)]
+    TotalWeightAt { height: u64 },
+    /// Weights of up to 30 addresses at the start of the given height.
+    #[returns(MembersAtResponse)]
+    MembersAt { addrs: Vec<String>, height: u64 },
+    /// All members with their weight in address order, in pages of up to
+    /// 500 for off-chain snapshots. Pass `next` back as `start_after` until
+    /// it is `None`.
+    #[returns(ExportMembersResponse)]
+    ExportMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(cw4::MemberListResponse)]
     ListMembers {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Members whose stake has not been migrated yet. Migration removes
+    /// members, so these are the current members, regardless of any claims
+    /// receipt they may already have.
+    #[returns(cw4::MemberListResponse)]
+    UnmigratedMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Lists members with a weight strictly above `weight`, among at most
+    /// `MAX_DISTRIBUTION_SCAN` members read after `start_after`.
+    #[returns(MembersAboveWeightResponse)]
+    MembersAboveWeight {
+        weight: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(cw4::MemberResponse)]
     Member {
         addr: String,
         at_height: Option<u64>,
     },
+    /// The address's stored member weight next to the weight its stake
+    /// works out to, to spot per-address drift.
+    #[returns(EffectiveWeightResponse)]
+    EffectiveWeight { address: String },
+    /// Number of heights at which the address's member weight changed,
+    /// counted from the members changelog. Several changes within one block
+    /// count once.
+    #[returns(MemberChangeCountResponse)]
+    MemberChangeCount { address: String },
     /// Shows all registered hooks.
     #[returns(cw_controllers::HooksResponse)]
     Hooks {},
+    /// Whether any hook is registered.
+    #[returns(HasHooksResponse)]
+    HasHooks {},
     /// Returns the config
     #[returns(Config)]
     Config {},
+    /// The config as instantiate stored it, after normalizing `min_bond`,
+    /// along with the admin and the weight `min_bond` works out to.
+    #[returns(InstantiateParamsResponse)]
+    InstantiateParams {},
+    /// Number of stakers per stake size bucket, with the total and average
+    /// stake. See `StakeDistributionResponse`.
+    #[returns(StakeDistributionResponse)]
+    StakeDistribution { buckets: Vec<Uint128> },
+    /// Contract info of the DAO DAO destination, to confirm it runs the
+    /// expected code. Errors if no destination is set.
+    #[returns(cosmwasm_std::ContractInfoResponse)]
+    DaoDaoInfo {},
+    /// Whether the DAO DAO destination is `addr`, for deploy scripts to
+    /// confirm the migration set the expected target. False if none is set.
+    #[returns(ConfirmDaoDaoResponse)]
+    ConfirmDaoDao { addr: String },
+    /// Whether this contract's admin is also the wasm admin of the DAO DAO
+    /// destination. False if either admin or the destination is unset.
+    #[returns(AdminMatchesDaoResponse)]
+    AdminMatchesDao {},
+    /// Storage namespaces of the core maps and items, for tools reading raw
+    /// storage.
+    #[returns(StorageLayoutResponse)]
+    StorageLayout {},
+    /// This contract's address and its cw2 version.
+    #[returns(ContractIdentityResponse)]
+    ContractInfo {},
+    /// Returns the denom string `MigrateToDaoDao` transfers: the native
+    /// denom, or the cw20 contract address.
+    #[returns(TransferDenomResponse)]
+    TransferDenom {},
+    /// The denom top ups are accepted in, for frontends to check a send
+    /// before making it. `Fund` only takes a native denom.
+    #[returns(AcceptedFundingDenomResponse)]
+    AcceptedFundingDenom {},
+    /// Whether the staking denom can be migrated to DAO DAO at all, for
+    /// deploy tooling to fail fast on an unsupported configuration.
+    #[returns(DenomMigratableResponse)]
+    DenomMigratable {},
+    /// Distinct denoms held in claims. Claims carry only an amount, and
+    /// `ImportClaims` rejects foreign denoms, so this is the config denom.
+    #[returns(ClaimDenomsResponse)]
+    ClaimDenoms {},
+    #[returns(PauseStateResponse)]
+    PauseState {},
+    /// Whether a batch could migrate stakes and claims at this height,
+    /// given the pause flags, the seal, an abort and the migration start.
+    /// Empty maps and the batch interval are not considered.
+    #[returns(MigratableNowResponse)]
+    MigratableNow {},
+    /// Whether `Seal` has been executed.
+    #[returns(bool)]
+    Sealed {},
+    /// Whether the address could currently unstake or claim. Always false
+    /// with a reason, as stakes and claims only leave through migration.
+    #[returns(CanExitResponse)]
+    CanExit { address: String },
+    /// Stake, weight and claims of the address, with the claims split into
+    /// those that can be claimed now and those still unbonding.
+    #[returns(AccountStatusResponse)]
+    AccountStatus { address: String },
+    /// Checks the contract's balance of the staking denom against the total
+    /// of all stakes and pending claims. Run before migrating.
+    #[returns(BalanceReconciliationResponse)]
+    BalanceReconciliation {},
+    /// All integrity checks at once, as a gate before migrating. Reads up to
+    /// 1000 entries each of the members, stakes and claims maps, and reports
+    /// `all_ok` only if none of them was cut short.
+    #[returns(InvariantsHoldResponse)]
+    InvariantsHold {},
+    /// Every coin held by the contract, including denoms other than the
+    /// staking denom.
+    #[returns(Vec<cosmwasm_std::Coin>)]
+    AllBalances {},
+    /// Balance left once everything is migrated, which should be zero. A
+    /// residual flags funds the accounting doesn't cover.
+    #[returns(ProjectedFinalBalanceResponse)]
+    ProjectedFinalBalance {},
+    /// What must be sent to the contract for its balance to cover all stakes
+    /// and claims. Zero when the balance already covers them.
+    #[returns(TopUpNeededResponse)]
+    TopUpNeeded {},
+    /// Receipts of the addresses migrated so far.
+    #[returns(ListMigratedResponse)]
+    ListMigrated {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// A `ListMigrated` page along with its receipts as one string, each
+    /// `addr,weight,amount,height` and separated by `;`, to archive as is.
+    #[returns(ExportReceiptsResponse)]
+    ExportReceipts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Hash chained over every stake and claims migrated, and every one
+    /// restored after DAO DAO rejected it, in the order the contract recorded
+    /// them. It starts as 32 zero bytes and each entry replaces it with
+    /// `sha256(root || tag || weight || amount || addr)`: `tag` is one byte, 0
+    /// for a migration and 1 for a restore, `weight` the cw4 weight as a
+    /// big-endian u64 (0 for claims), `amount` the tokens as a big-endian
+    /// u128 and `addr` the address bytes. Each batch reports the root it
+    /// left as `receipt_root`.
+    #[returns(ReceiptRootResponse)]
+    ReceiptRoot {},
+    /// Claims of addresses with neither a stake nor a membership, which
+    /// migrate as claims only.
+    #[returns(OrphanClaimsResponse)]
+    OrphanClaims {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Addresses whose migration DAO DAO rejected, awaiting `RetryFailed`.
+    #[returns(ListFailedResponse)]
+    ListFailed {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Migration batches run so far, with the address that ran each.
+    #[returns(BatchHistoryResponse)]
+    BatchHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Data DAO DAO returned on its latest successful migration reply.
+    #[returns(Option<LastReplyData>)]
+    LastReplyData {},
+    /// Verified batches where DAO DAO accepted a different weight than sent.
+    #[returns(DiscrepanciesResponse)]
+    ListDiscrepancies {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Total weight still to be migrated, excluding members that are skipped
+    /// by migration batches.
+    #[returns(ProjectedVotingPowerResponse)]
+    ProjectedVotingPower {},
+    /// Rough height the migration completes at if one batch of `num` entries
+    /// runs per block from now on. `None` before the first batch.
+    #[returns(Option<EstimatedCompletionResponse>)]
+    EstimatedCompletion { num: u64 },
+    /// `MigrateToDaoDao` transactions of `num` stakes and `num_claims` claim
+    /// addresses still needed to migrate everything not excluded. With
+    /// `UpdateMaxPerBlock` set, a transaction moves at most that many entries,
+    /// as a larger batch is rejected.
+    #[returns(TxsToFinishResponse)]
+    TxsToFinish { num: u64, num_claims: u64 },
+    /// Funds and entries sent to DAO DAO so far, to reconcile against what it
+    /// received.
+    #[returns(MigratedTotalResponse)]
+    MigratedTotal {},
+    /// Stakes and claim addresses migrated per batch on average, zero
+    /// before the first batch.
+    #[returns(AverageBatchSizeResponse)]
+    AverageBatchSize {},
+    /// Funds sent to DAO DAO so far next to the stakes and claims still
+    /// held, as migration progress in tokens rather than weight.
+    #[returns(MigrationBreakdownResponse)]
+    MigrationBreakdown {},
+    /// Total weight before migration against the weight forwarded since and
+    /// the weight left, to detect accounting drift. Admin overrides of the
+    /// total show up as inconsistent.
+    #[returns(ForwardingDeltaResponse)]
+    ForwardingDelta {},
+    /// Cumulative progress of the migration to DAO DAO.
+    #[returns(MigrationMetrics)]
+    MigrationMetrics {},
+    /// The most recent migration batch, `None` before migration starts.
+    #[returns(Option<LastBatch>)]
+    LastBatch {},
+    /// Totals recorded by `PrepareSnapshot` before the first migration batch,
+    /// kept for audits after the live maps have been emptied. `None` until
+    /// the snapshot is complete.
+    #[returns(Option<PreMigrationSnapshot>)]
+    PreMigrationSnapshot {},
+    /// Where the next migration batch picks up and how much is left to
+    /// migrate, for sizing batches. Reads at most `MAX_DISTRIBUTION_SCAN`
+    /// entries from each of the stake and claims maps.
+    #[returns(MigrationStatusResponse)]
+    MigrationStatus {},
+    /// Whether no stake or claim is left, for DAO DAO or integrators to poll.
+    /// Unlike `MigrationStatus` it ignores the completion threshold. Its shape
+    /// is kept stable across versions.
+    #[returns(SourceMigrationCompleteResponse)]
+    SourceMigrationComplete {},
+    /// Addresses whose stakes the next `batches` batches of `num` stakes would
+    /// migrate, one list per batch. At most 10 batches are returned.
+    #[returns(Vec<Vec<String>>)]
+    UpcomingBatches { num: u64, batches: u64 },
+    /// Dry run of a `MigrateToDaoDao` batch with these options, see
+    /// `SimulatedBatch`. `num` and `num_claims` are capped at 1000.
+    #[returns(SimulatedBatch)]
+    SimulateMigration {
+        num: u64,
+        num_claims: u64,
+        protect_admin: Option<bool>,
+        min_weight: Option<u64>,
+    },
+    /// Funds a claims-only `MigrateToDaoDao` batch of `num_claims` would
+    /// forward, to check the contract can cover it first.
+    #[returns(NextClaimBatchSumResponse)]
+    NextClaimBatchSum { num_claims: u64 },
+    /// The messages a `MigrateToDaoDao` batch with default options would send
+    /// right now, including the payment to a funds recipient, to check them
+    /// or submit them another way. Errors if no destination is set.
+    #[returns(NextBatchMessageResponse)]
+    NextBatchMessage { num: u64, num_claims: u64 },
+    /// Serialized size of the largest message a `MigrateToDaoDao` batch with
+    /// default options would send right now, and whether it is over
+    /// `limit_bytes`, to size batches before sending them.
+    #[returns(MessageLimitResponse)]
+    WouldExceedMessageLimit {
+        num: u64,
+        num_claims: u64,
+        limit_bytes: u64,
+    },
 }
 
 #[cw_serde]
@@ -68,3 +693,491 @@ pub struct StakedResponse {
     pub stake: Uint128,
     pub denom: Denom,
 }
+
+#[cw_serde]
+pub struct NextBatchMessageResponse {
+    /// one message per `MAX_ENTRIES_PER_MESSAGE` entries
+    pub messages: Vec<CosmosMsg>,
+}
+
+#[cw_serde]
+pub struct MessageLimitResponse {
+    /// size of the largest `MigrateStakes` payload of the batch
+    pub size_bytes: u64,
+    pub exceeds: bool,
+}
+
+#[cw_serde]
+pub struct SimulatedBatch {
+    /// members that would be migrated, with their current weight
+    pub stakes: Vec<(Addr, u64)>,
+    pub claims: Vec<(Addr, Vec<Claim>)>,
+    /// staked tokens of `stakes`
+    pub stake_sum: Uint128,
+    pub claim_sum: Uint128,
+    /// funds the batch would send to DAO DAO
+    pub total_sum: Uint128,
+    /// last stake read, the following batch continues after it
+    pub next_stake_cursor: Option<Addr>,
+    /// `MigrationStatusResponse::claims_cursor` once the batch ran
+    pub next_claim_cursor: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct MigrationStatusResponse {
+    /// the next claims batch starts after this address
+    pub claims_cursor: Option<Addr>,
+    /// no stakes are left to migrate
+    pub stakes_complete: bool,
+    /// no claims are left to migrate
+    pub claims_complete: bool,
+    /// claims are migrated, and so are stakes, or the remaining weight is
+    /// below the completion threshold
+    pub complete: bool,
+    /// stakers not migrated yet
+    pub remaining_stakes: u64,
+    /// addresses whose claims are not migrated yet
+    pub remaining_claims: u64,
+    pub total_weight: u64,
+    /// staked and claimed tokens still to be sent out
+    pub remaining_amount: Uint128,
+    /// a map held more entries than were read, so the counts and amount are
+    /// lower bounds
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct StorageLayoutResponse {
+    pub stake: String,
+    /// primary namespace of the members snapshot map
+    pub members: String,
+    /// primary namespace of the total weight snapshot item
+    pub total: String,
+    /// `Map<Addr, Vec<Claim>>` of pending claims
+    pub claims: String,
+    pub config: String,
+    pub admin: String,
+    pub hooks: String,
+}
+
+#[cw_serde]
+pub struct EffectiveWeightResponse {
+    /// weight in the members map, `None` if not a member
+    pub stored: Option<u64>,
+    /// `stake / tokens_per_weight`, or 0 for a stake below `min_bond`
+    pub computed: u64,
+    /// a missing member counts as weight 0
+    pub consistent: bool,
+}
+
+#[cw_serde]
+pub struct InstantiateParamsResponse {
+    pub config: Config,
+    pub admin: Option<Addr>,
+    /// weight of a member staking exactly `min_bond`
+    pub min_bond_weight: u64,
+}
+
+#[cw_serde]
+pub struct IsAdminResponse {
+    pub is_admin: bool,
+}
+
+#[cw_serde]
+pub struct HasHooksResponse {
+    pub has_hooks: bool,
+}
+
+#[cw_serde]
+pub struct ConfirmDaoDaoResponse {
+    pub matches: bool,
+}
+
+#[cw_serde]
+pub struct AdminMatchesDaoResponse {
+    pub matches: bool,
+}
+
+#[cw_serde]
+pub struct ContractIdentityResponse {
+    pub address: Addr,
+    pub code_version: ContractVersion,
+}
+
+#[cw_serde]
+pub struct ClaimDenomsResponse {
+    pub denoms: Vec<Denom>,
+}
+
+#[cw_serde]
+pub struct TransferDenomResponse {
+    pub denom: String,
+}
+
+#[cw_serde]
+pub struct AcceptedFundingDenomResponse {
+    /// the native denom, or the cw20 contract address
+    pub denom: String,
+    /// whether `Fund` takes it, otherwise it is sent with a cw20 transfer
+    pub via_fund: bool,
+}
+
+#[cw_serde]
+pub struct PauseStateResponse {
+    pub paused: bool,
+    pub stake_migration_paused: bool,
+    pub claim_migration_paused: bool,
+}
+
+#[cw_serde]
+pub struct MigratableNowResponse {
+    pub stakes: bool,
+    pub claims: bool,
+}
+
+#[cw_serde]
+pub struct ClaimsInfoResponse {
+    pub count: u64,
+    pub oldest: Option<Expiration>,
+    pub newest: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct SourceMigrationCompleteResponse {
+    pub complete: bool,
+}
+
+#[cw_serde]
+pub struct NextClaimBatchSumResponse {
+    pub sum: Uint128,
+}
+
+#[cw_serde]
+pub struct DenomMigratableResponse {
+    pub migratable: bool,
+    /// why the denom can't be migrated, `None` if it can
+    pub reason: Option<String>,
+}
+
+#[cw_serde]
+pub struct ProjectedVotingPowerResponse {
+    pub weight: u64,
+    /// more members are excluded than were read, so `weight` is an upper bound
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct EstimatedCompletionResponse {
+    pub height: u64,
+    /// more entries are left than were read, so the height is a lower bound
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct TxsToFinishResponse {
+    pub txs: u64,
+    /// more entries are left than were read, so `txs` is a lower bound
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct ClaimsBoundedResponse {
+    pub claims: Vec<Claim>,
+    /// the address has more than `max` claims
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct ClaimsNormalizedResponse {
+    pub claims: Vec<NormalizedClaim>,
+}
+
+#[cw_serde]
+pub struct NormalizedClaim {
+    pub amount: Uint128,
+    /// set for height based claims
+    pub release_height: Option<u64>,
+    /// unix seconds, set for time based claims
+    pub release_time: Option<u64>,
+}
+
+#[cw_serde]
+pub struct DebugDumpResponse {
+    pub contract_version: ContractVersion,
+    pub config: Config,
+    pub admin: Option<Addr>,
+    pub dao_dao: Option<Addr>,
+    pub destination_version: DestinationVersion,
+    pub total_weight: u64,
+    pub pause: PauseStateResponse,
+    pub sealed: bool,
+    pub aborted: bool,
+    pub migration_start: Option<u64>,
+    pub min_batch_interval: Option<Duration>,
+    pub max_per_block: Option<u64>,
+    pub completion_threshold: Option<u64>,
+    pub treasury: Option<Addr>,
+    pub funds_recipient: Option<Addr>,
+    pub migration_proxy: Option<Addr>,
+    pub monitor: Option<Addr>,
+    pub auto_pause_on_shortfall: bool,
+    pub migrate_entries_enabled: bool,
+    pub metrics: MigrationMetrics,
+    pub claims_cursor: Option<Addr>,
+    pub last_batch: Option<LastBatch>,
+    pub has_prepared_batch: bool,
+    pub has_pending_batch: bool,
+}
+
+#[cw_serde]
+pub struct SummaryResponse {
+    pub total_weight: u64,
+    pub total_staked: Uint128,
+    pub member_count: u64,
+    /// summed amount of all pending claims
+    pub total_claims: Uint128,
+    /// number of individual pending claims
+    pub claim_count: u64,
+    pub dao_dao: Option<Addr>,
+    /// no stakes or claims are left to migrate
+    /// as in `MigrationStatusResponse::complete`
+    pub migration_complete: bool,
+    /// the stake, member or claim scan stopped early, so the totals only
+    /// cover the first entries
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct MigrationBreakdownResponse {
+    /// as in `MigratedTotal`
+    pub migrated_amount: Uint128,
+    /// total of all stakes and pending claims
+    pub remaining_amount: Uint128,
+    /// `migrated_amount` plus `remaining_amount`
+    pub total_amount: Uint128,
+    /// the total of stakes and claims was read from part of the maps only,
+    /// before the snapshot recorded it
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct ForwardingDeltaResponse {
+    /// total weight of the pre-migration snapshot, the current total before
+    /// the first batch
+    pub initial_total: u64,
+    pub forwarded_weight: u64,
+    pub remaining_total: u64,
+    /// whether `initial_total` equals `forwarded_weight` plus
+    /// `remaining_total`
+    pub consistent: bool,
+}
+
+#[cw_serde]
+pub struct AverageBatchSizeResponse {
+    pub avg_stakes: Decimal,
+    pub avg_claims: Decimal,
+}
+
+#[cw_serde]
+pub struct MigratedTotalResponse {
+    pub amount: Uint128,
+    pub stake_count: u64,
+    pub claim_count: u64,
+}
+
+#[cw_serde]
+pub struct ListFailedResponse {
+    pub failed: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct ExportMembersResponse {
+    pub members: Vec<(Addr, u64)>,
+    /// last address of this page if more members follow
+    pub next: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct MembersAboveWeightResponse {
+    pub members: Vec<Member>,
+    /// last address read, to continue from as `start_after`, `None` once the
+    /// end of the members map was reached
+    pub next_start_after: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct OrphanClaimsResponse {
+    pub claims: Vec<(Addr, Vec<Claim>)>,
+    /// last address read, to continue from as `start_after`, `None` once the
+    /// end of the claims map was reached
+    pub next_start_after: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct ListMigratedResponse {
+    pub migrated: Vec<MigrationReceipt>,
+}
+
+#[cw_serde]
+pub struct ReceiptRootResponse {
+    pub root: Binary,
+}
+
+#[cw_serde]
+pub struct ExportReceiptsResponse {
+    pub receipts: Vec<MigrationReceipt>,
+    /// `receipts` as `addr,weight,amount,height` entries joined by `;`
+    pub csv: String,
+}
+
+#[cw_serde]
+pub struct BatchHistoryResponse {
+    pub batches: Vec<BatchRecord>,
+}
+
+#[cw_serde]
+pub struct DiscrepanciesResponse {
+    pub discrepancies: Vec<(u64, Discrepancy)>,
+}
+
+#[cw_serde]
+pub struct TopUpNeededResponse {
+    pub shortfall: Uint128,
+    pub denom: Denom,
+    /// the total of stakes and claims was read from part of the maps only,
+    /// before the snapshot recorded it
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct ProjectedFinalBalanceResponse {
+    pub projected: Uint128,
+    /// the total of stakes and claims was read from part of the maps only,
+    /// before the snapshot recorded it
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct BalanceReconciliationResponse {
+    /// total staked plus total pending claims
+    pub expected: Uint128,
+    /// balance held by the contract
+    pub actual: Uint128,
+    pub reconciled: bool,
+    /// the total of stakes and claims was read from part of the maps only,
+    /// before the snapshot recorded it
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct ClaimsMapStatsResponse {
+    /// addresses read
+    pub sampled: u64,
+    /// average serialized size of an address's claims, in bytes
+    pub average_size: u64,
+    pub max_size: u64,
+}
+
+#[cw_serde]
+pub struct MemberChangeCountResponse {
+    pub count: u64,
+    /// the changelog scan stopped early, so `count` is a lower bound
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct InvariantsHoldResponse {
+    /// `TOTAL` equals the summed member weights
+    pub total_matches_weights: bool,
+    /// the contract balance covers all stakes and pending claims
+    pub balance_backs_liabilities: bool,
+    /// every member has a stake. Claims without a stake are expected, they
+    /// belong to addresses that fully unbonded.
+    pub no_orphans: bool,
+    pub all_ok: bool,
+    /// one of the scans stopped early, so the checks only cover the first
+    /// entries
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct ClaimMaturityScheduleResponse {
+    /// height based release points come before time based ones
+    pub schedule: Vec<(Expiration, Uint128)>,
+    /// there were more addresses with claims than the query reads
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct DualHoldersResponse {
+    pub count: u64,
+    /// there were more addresses with claims than the query reads
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct TopClaimHoldersResponse {
+    /// addresses with their number of claims
+    pub holders: Vec<(Addr, u64)>,
+    /// there were more addresses with claims than the query reads
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct TopKConcentrationResponse {
+    pub top_k_weight: u64,
+    pub total_weight: u64,
+    /// `top_k_weight` over `total_weight`, zero when there is no weight
+    pub fraction: Decimal,
+    /// there were more members than the query reads
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct StakeDistributionResponse {
+    /// bucket boundaries, sorted
+    pub buckets: Vec<Uint128>,
+    /// `counts[0]` stakers are below `buckets[0]`, `counts[i]` are from
+    /// `buckets[i - 1]` up to `buckets[i]`, and the last count is from the
+    /// last boundary up
+    pub counts: Vec<u64>,
+    pub stakers: u64,
+    pub total: Uint128,
+    pub average: Uint128,
+    /// there were more stakes than the query reads
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct ClaimsInRangeResponse {
+    pub claims: Vec<(Addr, Claim)>,
+    /// the scan stopped before the end of the claims map, so more matching
+    /// claims may exist
+    pub truncated: bool,
+}
+
+#[cw_serde]
+pub struct CanExitResponse {
+    pub can_unstake: bool,
+    pub can_claim: bool,
+    /// why an action is unavailable, if one is
+    pub reason: Option<String>,
+}
+
+#[cw_serde]
+pub struct AccountStatusResponse {
+    pub staked: Uint128,
+    pub weight: u64,
+    /// claims still unbonding
+    pub pending_claims: Uint128,
+    /// matured claims
+    pub claimable_now: Uint128,
+    /// whether the address has a migration receipt
+    pub migrated: bool,
+}
+
+#[cw_serde]
+pub struct MembersAtResponse {
+    /// each address with its weight, `None` if it wasn't a member
+    pub members: Vec<(String, Option<u64>)>,
+}
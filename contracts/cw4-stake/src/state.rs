@@ -1,12 +1,22 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Binary, BlockInfo, Empty, Uint128};
 use cw20::Denom;
 use cw4::TOTAL_KEY;
-use cw_controllers::{Admin, Claims, Hooks};
-use cw_storage_plus::{Item, Map, SnapshotMap, Strategy};
+use cw_controllers::{Admin, Claim, Claims, Hooks};
+use cw_storage_plus::{Item, Map, SnapshotItem, SnapshotMap, Strategy};
 use cw_utils::Duration;
+use kujira::CallbackData;
 
-pub const CLAIMS: Claims = Claims::new("claims");
+// Claims offers no way to iterate all claims, so migration reads and removes
+// them through a raw `Map<Addr, Vec<Claim>>` on this same namespace. The
+// `claims_storage_compatibility` test guards the layout across upgrades.
+pub const CLAIMS_KEY: &str = "claims";
+pub const CLAIMS: Claims = Claims::new(CLAIMS_KEY);
+
+/// The claims of `CLAIMS` as a raw map, to iterate or remove them.
+pub fn claims_storage() -> Map<'static, Addr, Vec<Claim>> {
+    Map::new(CLAIMS_KEY)
+}
 
 #[cw_serde]
 pub struct Config {
@@ -17,10 +27,18 @@ pub struct Config {
     pub unbonding_period: Duration,
 }
 
-pub const ADMIN: Admin = Admin::new("admin");
-pub const HOOKS: Hooks = Hooks::new("cw4-hooks");
-pub const CONFIG: Item<Config> = Item::new("config");
-pub const TOTAL: Item<u64> = Item::new(TOTAL_KEY);
+pub const ADMIN_KEY: &str = "admin";
+pub const HOOKS_KEY: &str = "cw4-hooks";
+pub const CONFIG_KEY: &str = "config";
+pub const ADMIN: Admin = Admin::new(ADMIN_KEY);
+pub const HOOKS: Hooks = Hooks::new(HOOKS_KEY);
+pub const CONFIG: Item<Config> = Item::new(CONFIG_KEY);
+pub const TOTAL: SnapshotItem<u64> = SnapshotItem::new(
+    TOTAL_KEY,
+    cw4::TOTAL_KEY_CHECKPOINTS,
+    cw4::TOTAL_KEY_CHANGELOG,
+    Strategy::EveryBlock,
+);
 
 pub const MEMBERS: SnapshotMap<&Addr, u64> = SnapshotMap::new(
     cw4::MEMBERS_KEY,
@@ -29,7 +47,284 @@ pub const MEMBERS: SnapshotMap<&Addr, u64> = SnapshotMap::new(
     Strategy::EveryBlock,
 );
 
-pub const STAKE: Map<&Addr, Uint128> = Map::new("stake");
+pub const STAKE_KEY: &str = "stake";
+pub const STAKE: Map<&Addr, Uint128> = Map::new(STAKE_KEY);
 
 /// DAO DAO Migration
 pub const DAO_DAO: Item<Addr> = Item::new("dao_dao");
+/// Registry `DAO_DAO` is checked against on upgrade.
+pub const REGISTRY: Item<Addr> = Item::new("registry");
+
+/// Shape of the `MigrateStakes` message the DAO DAO destination expects.
+#[cw_serde]
+#[derive(Default)]
+pub enum DestinationVersion {
+    /// `MigrateStakes { weights, claims }` with tuples, as the bundled
+    /// `dao-voting-token-staked` takes it
+    #[default]
+    V1,
+    /// `MigrateStakes { stakes, claims }` with named fields, see
+    /// `DaoDaoExecuteMsg::MigrateStakes`. Only for a destination newer than
+    /// the bundled `dao-voting-token-staked`, which can't parse it
+    V2,
+}
+
+/// Defaults to `DestinationVersion::V1` when unset.
+pub const DESTINATION_VERSION: Item<DestinationVersion> = Item::new("destination_version");
+/// Code id the DAO DAO contract is expected to run, checked before each batch.
+pub const DAO_DAO_CODE_ID: Item<u64> = Item::new("dao_dao_code_id");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct MigrationMetrics {
+    /// number of `MigrateToDaoDao` calls executed
+    pub batches_run: u64,
+    /// number of stakers forwarded to DAO DAO
+    pub stakes_migrated: u64,
+    /// number of addresses whose claims were forwarded to DAO DAO
+    pub claims_migrated: u64,
+    /// total funds (stakes and claims) sent to DAO DAO
+    pub total_moved: Uint128,
+    /// cw4 weight of the stakes forwarded to DAO DAO
+    #[serde(default)]
+    pub forwarded_weight: u64,
+}
+
+/// Cumulative migration progress, written on the first batch.
+pub const MIGRATION_METRICS: Item<MigrationMetrics> = Item::new("migration_metrics");
+
+/// Last address whose claims were migrated. Claim batches resume after it, and
+/// it is reset once the end of the claims map is reached, or when a batch has
+/// claims restored, so the next pass picks up claims added behind it.
+pub const CLAIMS_CURSOR: Item<Option<Addr>> = Item::new("claims_cursor");
+/// Last stake read by a batch, skipped ones included. Stake batches resume
+/// after it, and it is reset the same way as `CLAIMS_CURSOR`.
+pub const STAKE_CURSOR: Item<Option<Addr>> = Item::new("stake_cursor");
+
+#[cw_serde]
+pub struct PreMigrationSnapshot {
+    pub total_weight: u64,
+    pub total_staked: Uint128,
+    pub member_count: u64,
+    /// number of individual pending claims
+    pub claim_count: u64,
+}
+
+/// State of the contract as it was before the first migration batch ran.
+pub const PRE_MIGRATION_SNAPSHOT: Item<PreMigrationSnapshot> = Item::new("pre_migration_snapshot");
+
+/// Map `PrepareSnapshot` is reading, in the order they are read.
+#[cw_serde]
+pub enum SnapshotStage {
+    Stakes,
+    Members,
+    Claims,
+}
+
+#[cw_serde]
+pub struct SnapshotProgress {
+    pub stage: SnapshotStage,
+    /// last key read from the map of `stage`
+    pub start_after: Option<Addr>,
+    pub total_staked: Uint128,
+    pub member_count: u64,
+    pub claim_count: u64,
+    /// tokens of the claims read
+    pub total_claimed: Uint128,
+}
+
+/// Totals of a `PrepareSnapshot` still in progress.
+pub const SNAPSHOT_PROGRESS: Item<SnapshotProgress> = Item::new("snapshot_progress");
+
+/// Stakes and claims held when the snapshot was taken, adjusted by later
+/// imports, overrides, forfeits and refunds. Funds sent to DAO DAO over all
+/// batches may never exceed it.
+pub const MIGRATION_BACKING: Item<Uint128> = Item::new("migration_backing");
+
+/// Set by `AbortMigration`, blocks migration for good.
+pub const ABORTED: Item<bool> = Item::new("aborted");
+
+/// Set by `Seal`, rejects every execute message for good.
+pub const SEALED: Item<bool> = Item::new("sealed");
+
+/// Receives the funds of claims forfeited by a `forfeit_claims` batch.
+pub const TREASURY: Item<Addr> = Item::new("treasury");
+
+/// Set for a destination that rejects funds: batches are sent to it unfunded
+/// and their funds paid here, as with `funds_to`.
+pub const FUNDS_RECIPIENT: Item<Addr> = Item::new("funds_recipient");
+
+/// Adapter batches are executed on, which forwards them to DAO DAO. Checks
+/// and queries still go to `DAO_DAO`.
+pub const MIGRATION_PROXY: Item<Addr> = Item::new("migration_proxy");
+
+/// Contract notified with a `MonitorExecuteMsg` after each batch.
+pub const MONITOR: Item<Addr> = Item::new("monitor");
+
+/// Blocks all migration batches.
+pub const PAUSED: Item<bool> = Item::new("paused");
+/// Pauses migration instead of running a batch while the contract holds less
+/// than its stakes and claims.
+pub const AUTO_PAUSE_ON_SHORTFALL: Item<bool> = Item::new("auto_pause_on_shortfall");
+/// Allows batches sent as `MigrateEntries`, which the bundled
+/// `dao-voting-token-staked` cannot parse.
+pub const MIGRATE_ENTRIES_ENABLED: Item<bool> = Item::new("migrate_entries_enabled");
+/// Blocks forwarding of active stakes only.
+pub const PAUSE_STAKE_MIGRATION: Item<bool> = Item::new("pause_stake_migration");
+/// Blocks forwarding of pending claims only.
+pub const PAUSE_CLAIM_MIGRATION: Item<bool> = Item::new("pause_claim_migration");
+
+/// Addresses left out of migration batches entirely.
+pub const EXCLUDE: Map<&Addr, Empty> = Map::new("exclude");
+
+/// Maximum number of stakes and claim entries migrated within one block.
+pub const MAX_PER_BLOCK: Item<u64> = Item::new("max_per_block");
+/// Entries a migration batch should aim for, used to suggest the next batch
+/// size. Defaults to `DEFAULT_TARGET_OPS` in the contract.
+pub const TARGET_OPS: Item<u64> = Item::new("target_ops");
+/// Height before which no migration batch may run, for an announced start.
+pub const MIGRATION_START: Item<u64> = Item::new("migration_start");
+/// Minimum time or blocks between two migration batches.
+pub const MIN_BATCH_INTERVAL: Item<Duration> = Item::new("min_batch_interval");
+/// Migration counts as complete once the remaining total weight is below this,
+/// with all claims migrated, even if dust stakes remain.
+pub const COMPLETION_THRESHOLD: Item<u64> = Item::new("completion_threshold");
+/// Block the last migration batch ran at.
+pub const LAST_BATCH_BLOCK: Item<BlockInfo> = Item::new("last_batch_block");
+/// Height of the last batch and the number of entries migrated at it.
+pub const BLOCK_MIGRATIONS: Item<(u64, u64)> = Item::new("block_migrations");
+
+#[cw_serde]
+pub struct MigrationReceipt {
+    pub addr: Addr,
+    /// cw4 weight the address held
+    pub weight: u64,
+    /// staked and claimed tokens sent to DAO DAO
+    pub amount: Uint128,
+    /// height of the latest batch that included the address
+    pub height: u64,
+    /// whether DAO DAO reported voting power for the stake sent, `None` unless
+    /// that batch ran with `confirm`
+    pub confirmed: Option<bool>,
+}
+
+/// Receipts of every address migrated to DAO DAO.
+pub const MIGRATED: Map<&Addr, MigrationReceipt> = Map::new("migrated");
+
+/// Hash chained over every receipt change, see `QueryMsg::ReceiptRoot`.
+pub const RECEIPT_ROOT: Item<Binary> = Item::new("receipt_root");
+
+#[cw_serde]
+pub struct BatchRecord {
+    pub batch_index: u64,
+    /// address that executed the batch
+    pub sender: Addr,
+    pub height: u64,
+    pub stake_count: u64,
+    /// addresses whose claims were migrated
+    pub claim_count: u64,
+}
+
+/// Audit log of migration batches, by batch number (counting from 1).
+pub const BATCH_LOG: Map<u64, BatchRecord> = Map::new("batch_log");
+
+#[cw_serde]
+pub struct LastBatch {
+    pub height: u64,
+    pub sender: Addr,
+    pub stake_count: u64,
+    /// addresses whose claims were migrated
+    pub claim_count: u64,
+    /// funds (stakes and claims) the batch sent
+    pub sum: Uint128,
+    /// total cw4 weight left after the batch
+    pub remaining: u64,
+}
+
+/// The most recent migration batch.
+pub const LAST_BATCH: Item<LastBatch> = Item::new("last_batch");
+
+#[cw_serde]
+pub struct LastReplyData {
+    pub batch: u64,
+    /// data DAO DAO set in its response, such as minted receipt tokens
+    pub data: Option<Binary>,
+}
+
+/// Data of the latest successful DAO DAO reply, kept for inspection.
+pub const LAST_REPLY_DATA: Item<LastReplyData> = Item::new("last_reply_data");
+
+/// A batch sent with a callback, awaiting the DAO DAO replies to its
+/// messages.
+#[cw_serde]
+pub struct PendingBatch {
+    pub batch: u64,
+    /// address the callback is sent to, the caller of the batch
+    pub callback_to: Addr,
+    pub callback: CallbackData,
+    /// messages not yet replied to, in the order they were sent
+    pub messages: Vec<PendingMessage>,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+/// What a batch message removed from storage, to be restored if it fails.
+#[cw_serde]
+pub struct PendingMessage {
+    /// address, staked tokens and cw4 weight of each stake
+    pub stakes: Vec<(Addr, Uint128, u64)>,
+    /// claims as they were stored, before any collapsing
+    pub claims: Vec<(Addr, Vec<Claim>)>,
+}
+
+pub const PENDING_BATCH: Item<PendingBatch> = Item::new("pending_batch");
+
+/// Stakes of each message of a `confirm` batch not yet replied to, to be
+/// checked against the voting power DAO DAO reports.
+pub const PENDING_CONFIRMATION: Item<Vec<Vec<(Addr, Uint128)>>> = Item::new("pending_confirmation");
+
+/// Addresses whose stakes or claims were restored after DAO DAO rejected
+/// their message, to be migrated again by `RetryFailed`.
+pub const FAILED: Map<&Addr, Empty> = Map::new("failed");
+
+/// Batch selected by `PrepareBatch`, sent by a matching `CommitBatch`.
+#[cw_serde]
+pub struct PreparedBatch {
+    pub sender: Addr,
+    pub num: u64,
+    pub num_claims: u64,
+    /// members selected, with their weight
+    pub stakes: Vec<(Addr, u64)>,
+    pub claims: Vec<(Addr, Vec<Claim>)>,
+    /// funds the batch sends
+    pub sum: Uint128,
+}
+
+pub const PREPARED_BATCH: Item<PreparedBatch> = Item::new("prepared_batch");
+
+/// A verified batch awaiting the DAO DAO replies to its messages.
+#[cw_serde]
+pub struct PendingVerification {
+    pub batch: u64,
+    /// messages of the batch not yet replied to
+    pub remaining: u64,
+    /// staked tokens sent over all messages
+    pub sent: Uint128,
+    /// weight accepted so far, `None` once a reply is missing it
+    pub accepted: Option<Uint128>,
+}
+
+pub const PENDING_VERIFICATION: Item<PendingVerification> = Item::new("pending_verification");
+
+#[cw_serde]
+pub struct Discrepancy {
+    /// staked tokens sent in the batch
+    pub sent: Uint128,
+    /// weight DAO DAO reported as accepted, `None` only in records kept from
+    /// before batches without acceptance data were skipped
+    pub accepted: Option<Uint128>,
+}
+
+/// Verified batches where DAO DAO did not accept exactly what was sent, by
+/// batch number (counting from 1, as in `MigrationMetrics::batches_run`).
+pub const MIGRATION_DISCREPANCIES: Map<u64, Discrepancy> = Map::new("migration_discrepancies");
@@ -0,0 +1,6841 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    attr, coin, coins, from_json, to_json_binary, Addr, Attribute, BankMsg, Binary, Coin,
+    ContractInfoResponse, ContractResult, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, Event,
+    MessageInfo, OwnedDeps, Reply, ReplyOn, Response, StdError, StdResult, Storage, SubMsg,
+    SubMsgResponse, SubMsgResult, SystemResult, Timestamp, Uint128, WasmMsg, WasmQuery,
+};
+use cw20::{Cw20ExecuteMsg, Denom};
+use cw4::{Member, MemberListResponse, MemberResponse, TotalWeightResponse};
+use cw_controllers::{AdminError, Claim, HookError, HooksResponse};
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use cw_storage_plus::{Item, Map};
+use cw_utils::{Duration, Expiration};
+use kujira::{CallbackData, CallbackMsg};
+use sha2::{Digest, Sha256};
+
+use crate::contract::{build_transfer, execute, instantiate, migrate, query, reply};
+use crate::msg::{
+    AcceptedFundingDenomResponse, AccountStatusResponse, AdminMatchesDaoResponse,
+    AverageBatchSizeResponse, BalanceReconciliationResponse, BatchFeedback, BatchHistoryResponse,
+    BatchStatus, CanExitResponse, ClaimDenomsResponse, ClaimMaturityScheduleResponse,
+    ClaimsBoundedResponse, ClaimsEntry, ClaimsInRangeResponse, ClaimsInfoResponse,
+    ClaimsMapStatsResponse, ClaimsNormalizedResponse, ConfirmDaoDaoResponse,
+    ContractIdentityResponse, DaoDaoExecuteMsg, DebugDumpResponse, DenomMigratableResponse,
+    DiscrepanciesResponse, DualHoldersResponse, EffectiveWeightResponse,
+    EstimatedCompletionResponse, ExecuteMsg, ExportMembersResponse, ExportReceiptsResponse,
+    ForwardingDeltaResponse, HasHooksResponse, InstantiateMsg, InstantiateParamsResponse,
+    InvariantsHoldResponse, IsAdminResponse, IsRegisteredResponse, ListFailedResponse,
+    ListMigratedResponse, MemberChangeCountResponse, MembersAboveWeightResponse, MembersAtResponse,
+    MessageLimitResponse, MigratableNowResponse, MigrateEntry, MigrateMsg, MigratedTotalResponse,
+    MigrationAcceptance, MigrationBreakdownResponse, MigrationCallback, MigrationStatusResponse,
+    MonitorExecuteMsg, NextBatchMessageResponse, NextClaimBatchSumResponse, NormalizedClaim,
+    OrphanClaimsResponse, PauseStateResponse, ProjectedFinalBalanceResponse,
+    ProjectedVotingPowerResponse, QueryMsg, ReceiptRootResponse, RegistryQueryMsg, SimulatedBatch,
+    SourceMigrationCompleteResponse, StakeDistributionResponse, StakeEntry, StakedResponse,
+    StorageLayoutResponse, SummaryResponse, TopClaimHoldersResponse, TopKConcentrationResponse,
+    TopUpNeededResponse, TransferDenomResponse, TxsToFinishResponse,
+};
+use crate::state::{
+    claims_storage, BatchRecord, Config, DestinationVersion, Discrepancy, LastBatch, LastReplyData,
+    MigrationMetrics, MigrationReceipt, PreMigrationSnapshot, CLAIMS, CONFIG, HOOKS, MEMBERS,
+    MIGRATION_BACKING, PENDING_BATCH, PENDING_CONFIRMATION, PENDING_VERIFICATION, STAKE,
+    STAKE_CURSOR, TOTAL,
+};
+use crate::ContractError;
+
+const ADMIN: &str = "admin";
+const DAO_DAO: &str = "dao_dao";
+const DENOM: &str = "ukuji";
+const USER1: &str = "user1";
+const USER2: &str = "user2";
+const USER3: &str = "user3";
+
+const TOKENS_PER_WEIGHT: Uint128 = Uint128::new(1_000);
+const MIN_BOND: Uint128 = Uint128::new(5_000);
+
+type MockDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+fn instantiate_msg(denom: Denom) -> InstantiateMsg {
+    InstantiateMsg {
+        denom,
+        tokens_per_weight: TOKENS_PER_WEIGHT,
+        min_bond: MIN_BOND,
+        unbonding_period: Duration::Height(100),
+        admin: Some(ADMIN.to_string()),
+    }
+}
+
+/// Instantiates and upgrades the contract, leaving the pre-migration snapshot
+/// to the test.
+fn setup_unprepared(denom: Denom) -> MockDeps {
+    let mut deps = mock_dependencies();
+    let msg = instantiate_msg(denom);
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            dao_dao_addr: Some(Addr::unchecked(DAO_DAO)),
+            migration_start: None,
+            registry: None,
+        },
+    )
+    .unwrap();
+    deps
+}
+
+/// Like `setup_unprepared`, with the snapshot taken before anything is
+/// seeded. Seeded stakes and claims stand in for ones bonded before it.
+fn setup_with_denom(denom: Denom) -> MockDeps {
+    let mut deps = setup_unprepared(denom);
+    prepare_snapshot(deps.as_mut(), 100).unwrap();
+    deps
+}
+
+fn prepare_snapshot(deps: DepsMut, num: u64) -> Result<Response, ContractError> {
+    execute(
+        deps,
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::PrepareSnapshot { num },
+    )
+}
+
+fn setup() -> MockDeps {
+    setup_with_denom(Denom::Native(DENOM.to_string()))
+}
+
+/// Makes every contract report the given code id.
+fn mock_code_id(deps: &mut MockDeps, code_id: u64) {
+    deps.querier.update_wasm(move |query| match query {
+        WasmQuery::ContractInfo { .. } => {
+            let mut info = ContractInfoResponse::default();
+            info.code_id = code_id;
+            info.creator = "creator".to_string();
+            SystemResult::Ok(ContractResult::Ok(to_json_binary(&info).unwrap()))
+        }
+        // no cw2 info
+        WasmQuery::Raw { .. } => SystemResult::Ok(ContractResult::Ok(Binary::default())),
+        _ => unimplemented!(),
+    });
+}
+
+/// The tokens of stakes and claims seeded after the snapshot back them, as
+/// bonded tokens would have.
+fn add_backing(storage: &mut dyn Storage, amount: Uint128) {
+    if let Some(backing) = MIGRATION_BACKING.may_load(storage).unwrap() {
+        MIGRATION_BACKING
+            .save(storage, &(backing + amount))
+            .unwrap();
+    }
+}
+
+/// Seeds a stake the way the original bond handler recorded it.
+fn stake(deps: DepsMut, env: &Env, addr: &str, amount: u128) {
+    let addr = Addr::unchecked(addr);
+    let amount = Uint128::new(amount);
+    let config = CONFIG.load(deps.storage).unwrap();
+    STAKE.save(deps.storage, &addr, &amount).unwrap();
+    add_backing(deps.storage, amount);
+    // below min_bond there is no membership at all
+    if amount < config.min_bond {
+        MEMBERS
+            .remove(deps.storage, &addr, env.block.height)
+            .unwrap();
+        return;
+    }
+    let weight = (amount / config.tokens_per_weight).u128() as u64;
+    MEMBERS
+        .save(deps.storage, &addr, &weight, env.block.height)
+        .unwrap();
+    TOTAL
+        .update(deps.storage, env.block.height, |total| -> StdResult<_> {
+            Ok(total.unwrap_or_default() + weight)
+        })
+        .unwrap();
+}
+
+fn add_claim(deps: DepsMut, addr: &str, amount: u128, release_at: Expiration) {
+    add_backing(deps.storage, Uint128::new(amount));
+    CLAIMS
+        .create_claim(
+            deps.storage,
+            &Addr::unchecked(addr),
+            Uint128::new(amount),
+            release_at,
+        )
+        .unwrap();
+}
+
+/// Fields of `ExecuteMsg::MigrateToDaoDao`, so a test spells out only the
+/// options it sets.
+#[derive(Default)]
+struct MigrateBatch {
+    num: u64,
+    num_claims: u64,
+    protect_admin: Option<bool>,
+    consolidate: Option<bool>,
+    verify: Option<bool>,
+    debug: Option<bool>,
+    min_weight: Option<u64>,
+    funds_to: Option<String>,
+    collapse_claims: Option<bool>,
+    callback: Option<CallbackData>,
+    until_gas: Option<u64>,
+    forfeit_claims: Option<bool>,
+    max_claims_per_addr: Option<u32>,
+    max_release: Option<Expiration>,
+    confirm: Option<bool>,
+    forward_denom: Option<String>,
+    interleave: Option<bool>,
+    soonest_first: Option<bool>,
+}
+
+impl MigrateBatch {
+    fn msg(self) -> ExecuteMsg {
+        ExecuteMsg::MigrateToDaoDao {
+            num: self.num,
+            num_claims: self.num_claims,
+            protect_admin: self.protect_admin,
+            consolidate: self.consolidate,
+            verify: self.verify,
+            debug: self.debug,
+            min_weight: self.min_weight,
+            funds_to: self.funds_to,
+            collapse_claims: self.collapse_claims,
+            callback: self.callback,
+            until_gas: self.until_gas,
+            forfeit_claims: self.forfeit_claims,
+            max_claims_per_addr: self.max_claims_per_addr,
+            max_release: self.max_release,
+            confirm: self.confirm,
+            forward_denom: self.forward_denom,
+            interleave: self.interleave,
+            soonest_first: self.soonest_first,
+        }
+    }
+}
+
+fn migrate_msg(num: u64, num_claims: u64) -> ExecuteMsg {
+    MigrateBatch {
+        num,
+        num_claims,
+        ..Default::default()
+    }
+    .msg()
+}
+
+fn migrate_batch(deps: DepsMut, num: u64, num_claims: u64) -> Result<Response, ContractError> {
+    execute(
+        deps,
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(num, num_claims),
+    )
+}
+
+/// Decodes the `MigrateStakes` message sent to DAO DAO along with its funds.
+fn sent_migration(
+    res: &Response,
+) -> (
+    dao_voting_token_staked::msg::ExecuteMsg,
+    Vec<cosmwasm_std::Coin>,
+) {
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            msg,
+            funds,
+        }) => {
+            assert_eq!(contract_addr, DAO_DAO);
+            (from_json(msg).unwrap(), funds.clone())
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+}
+
+#[test]
+fn import_claims() {
+    let mut deps = setup();
+    let claims1 = vec![Claim::new(100, Expiration::AtHeight(1_000))];
+    let claims2 = vec![
+        Claim::new(200, Expiration::AtHeight(2_000)),
+        Claim::new(300, Expiration::AtHeight(3_000)),
+    ];
+    let msg = ExecuteMsg::ImportClaims {
+        entries: vec![
+            (USER1.to_string(), claims1.clone()),
+            (USER2.to_string(), claims2.clone()),
+        ],
+        denom: Denom::Native(DENOM.to_string()),
+        overwrite: false,
+    };
+
+    // only the admin may import
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    let claims = CLAIMS
+        .query_claims(deps.as_ref(), &Addr::unchecked(USER2))
+        .unwrap();
+    assert_eq!(claims.claims, claims2);
+
+    // a replay is rejected unless overwriting
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ClaimsExist {
+            addr: USER1.to_string()
+        }
+    );
+    let replacement = vec![Claim::new(150, Expiration::AtHeight(1_500))];
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::ImportClaims {
+            entries: vec![(USER1.to_string(), replacement.clone())],
+            denom: Denom::Native(DENOM.to_string()),
+            overwrite: true,
+        },
+    )
+    .unwrap();
+
+    // imported claims are then migratable
+    let res = migrate_batch(deps.as_mut(), 0, 10).unwrap();
+    let (msg, funds) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![],
+            claims: vec![
+                (Addr::unchecked(USER1), replacement),
+                (Addr::unchecked(USER2), claims2),
+            ],
+        }
+    );
+    assert_eq!(funds, coins(650, DENOM));
+}
+
+#[test]
+fn import_claims_batch_cap() {
+    let mut deps = setup();
+    let entries = (0..31)
+        .map(|i| {
+            (
+                format!("user{i}"),
+                vec![Claim::new(1, Expiration::Never {})],
+            )
+        })
+        .collect();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::ImportClaims {
+            entries,
+            denom: Denom::Native(DENOM.to_string()),
+            overwrite: false,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ImportTooLarge { max: 30 });
+}
+
+#[test]
+fn import_claims_per_addr_cap() {
+    let mut deps = setup();
+    let import = |count: u64| ExecuteMsg::ImportClaims {
+        entries: vec![(
+            USER1.to_string(),
+            (0..count)
+                .map(|i| Claim::new(1, Expiration::AtHeight(i + 1)))
+                .collect(),
+        )],
+        denom: Denom::Native(DENOM.to_string()),
+        overwrite: true,
+    };
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), import(51)).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::TooManyClaims {
+            addr: USER1.to_string()
+        }
+    );
+
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), import(50)).unwrap();
+    let claims = CLAIMS
+        .query_claims(deps.as_ref(), &Addr::unchecked(USER1))
+        .unwrap();
+    assert_eq!(claims.claims.len(), 50);
+}
+
+#[test]
+fn transfer_denom() {
+    let deps = setup();
+    let res: TransferDenomResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::TransferDenom {}).unwrap()).unwrap();
+    assert_eq!(res.denom, DENOM);
+
+    let deps = setup_with_denom(Denom::Cw20(Addr::unchecked("cw20_token")));
+    let res: TransferDenomResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::TransferDenom {}).unwrap()).unwrap();
+    assert_eq!(res.denom, "cw20_token");
+}
+
+#[test]
+fn denom_migratable() {
+    let migratable = |deps: Deps| -> DenomMigratableResponse {
+        from_json(query(deps, mock_env(), QueryMsg::DenomMigratable {}).unwrap()).unwrap()
+    };
+
+    let deps = setup();
+    assert_eq!(
+        migratable(deps.as_ref()),
+        DenomMigratableResponse {
+            migratable: true,
+            reason: None,
+        }
+    );
+    let deps = setup_with_denom(Denom::Cw20(Addr::unchecked("cw20_token")));
+    let res = migratable(deps.as_ref());
+    assert!(!res.migratable);
+    assert!(res.reason.unwrap().contains("native funds"));
+}
+
+#[test]
+fn accepted_funding_denom() {
+    let accepted = |deps: Deps| -> AcceptedFundingDenomResponse {
+        from_json(query(deps, mock_env(), QueryMsg::AcceptedFundingDenom {}).unwrap()).unwrap()
+    };
+    let deps = setup();
+    assert_eq!(
+        accepted(deps.as_ref()),
+        AcceptedFundingDenomResponse {
+            denom: DENOM.to_string(),
+            via_fund: true,
+        }
+    );
+
+    let deps = setup_with_denom(Denom::Cw20(Addr::unchecked("cw20_token")));
+    assert_eq!(
+        accepted(deps.as_ref()),
+        AcceptedFundingDenomResponse {
+            denom: "cw20_token".to_string(),
+            via_fund: false,
+        }
+    );
+}
+
+#[test]
+fn native_denom_type_attribute() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("denom_type", "native")));
+}
+
+#[test]
+fn last_batch() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    let last_batch = |deps: Deps| -> Option<LastBatch> {
+        from_json(query(deps, mock_env(), QueryMsg::LastBatch {}).unwrap()).unwrap()
+    };
+    assert_eq!(last_batch(deps.as_ref()), None);
+
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    assert_eq!(
+        last_batch(deps.as_ref()),
+        Some(LastBatch {
+            height: env.block.height,
+            sender: Addr::unchecked(ADMIN),
+            stake_count: 1,
+            claim_count: 1,
+            sum: Uint128::new(10_500),
+            remaining: 20,
+        })
+    );
+}
+
+#[test]
+fn migration_metrics() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+
+    let metrics: MigrationMetrics =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::MigrationMetrics {}).unwrap())
+            .unwrap();
+    assert_eq!(metrics, MigrationMetrics::default());
+
+    migrate_batch(deps.as_mut(), 2, 1).unwrap();
+    migrate_batch(deps.as_mut(), 2, 2).unwrap();
+
+    let metrics: MigrationMetrics =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::MigrationMetrics {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        metrics,
+        MigrationMetrics {
+            batches_run: 2,
+            stakes_migrated: 3,
+            claims_migrated: 2,
+            total_moved: Uint128::new(61_200),
+            forwarded_weight: 60,
+        }
+    );
+}
+
+#[test]
+fn protect_admin() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, ADMIN, 10_000);
+    stake(deps.as_mut(), &env, USER1, 20_000);
+    stake(deps.as_mut(), &env, USER2, 30_000);
+    add_claim(deps.as_mut(), ADMIN, 500, Expiration::AtHeight(1_000));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        MigrateBatch {
+            num: 10,
+            num_claims: 10,
+            protect_admin: Some(true),
+            ..Default::default()
+        }
+        .msg(),
+    )
+    .unwrap();
+    let (msg, funds) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![
+                (Addr::unchecked(USER1), Uint128::new(20_000)),
+                (Addr::unchecked(USER2), Uint128::new(30_000)),
+            ],
+            claims: vec![],
+        }
+    );
+    assert_eq!(funds, coins(50_000, DENOM));
+
+    // the admin keeps its stake, weight and claims
+    let admin = Addr::unchecked(ADMIN);
+    assert_eq!(
+        STAKE.load(&deps.storage, &admin).unwrap(),
+        Uint128::new(10_000)
+    );
+    assert_eq!(MEMBERS.load(&deps.storage, &admin).unwrap(), 10);
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 10);
+    assert_eq!(
+        CLAIMS
+            .query_claims(deps.as_ref(), &admin)
+            .unwrap()
+            .claims
+            .len(),
+        1
+    );
+
+    // an explicit final batch migrates the admin
+    let res = migrate_batch(deps.as_mut(), 10, 10).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(10_500, DENOM));
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 0);
+}
+
+#[test]
+fn remaining_total_attribute() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+
+    let res = migrate_batch(deps.as_mut(), 2, 0).unwrap();
+    let total = TOTAL.load(&deps.storage).unwrap();
+    assert_eq!(total, 30);
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("remaining_total", total.to_string())));
+}
+
+#[test]
+fn pre_migration_snapshot() {
+    let mut deps = setup_unprepared(Denom::Native(DENOM.to_string()));
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 1_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER1, 600, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+    let snapshot = |deps: Deps| -> Option<PreMigrationSnapshot> {
+        from_json(query(deps, mock_env(), QueryMsg::PreMigrationSnapshot {}).unwrap()).unwrap()
+    };
+    let complete = |res: &Response| {
+        res.attributes
+            .iter()
+            .any(|attr| attr.key == "complete" && attr.value == "true")
+    };
+
+    let err = migrate_batch(deps.as_mut(), 1, 1).unwrap_err();
+    assert_eq!(err, ContractError::SnapshotNotTaken {});
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        ExecuteMsg::PrepareSnapshot { num: 10 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    // 3 stakes, 2 members and 2 claim holders, read 2 at a time
+    for _ in 0..3 {
+        let res = prepare_snapshot(deps.as_mut(), 2).unwrap();
+        assert!(!complete(&res));
+        assert_eq!(snapshot(deps.as_ref()), None);
+    }
+    let res = prepare_snapshot(deps.as_mut(), 2).unwrap();
+    assert!(complete(&res));
+    assert!(res.attributes.contains(&attr("read", "1")));
+    let err = prepare_snapshot(deps.as_mut(), 2).unwrap_err();
+    assert_eq!(err, ContractError::SnapshotTaken {});
+
+    let expected = PreMigrationSnapshot {
+        total_weight: 30,
+        total_staked: Uint128::new(31_000),
+        member_count: 2,
+        claim_count: 3,
+    };
+    assert_eq!(snapshot(deps.as_ref()), Some(expected.clone()));
+    // stakes and claims read
+    assert_eq!(
+        MIGRATION_BACKING.load(&deps.storage).unwrap(),
+        Uint128::new(32_800)
+    );
+    for _ in 0..3 {
+        migrate_batch(deps.as_mut(), 1, 1).unwrap();
+        let snapshot: Option<PreMigrationSnapshot> =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::PreMigrationSnapshot {}).unwrap())
+                .unwrap();
+        assert_eq!(snapshot, Some(expected.clone()));
+    }
+}
+
+#[test]
+fn snapshot_restarts_on_writes() {
+    let mut deps = setup_unprepared(Denom::Native(DENOM.to_string()));
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 1_000);
+
+    // all stakes are read, then one of them is pruned
+    prepare_snapshot(deps.as_mut(), 3).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::PruneZeroWeightMembers {
+            num: 10,
+            start_after: None,
+        },
+    )
+    .unwrap();
+
+    prepare_snapshot(deps.as_mut(), 100).unwrap();
+    let snapshot: Option<PreMigrationSnapshot> =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::PreMigrationSnapshot {}).unwrap())
+            .unwrap();
+    assert_eq!(snapshot.unwrap().total_staked, Uint128::new(30_000));
+}
+
+#[test]
+fn empty_batch() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+
+    let err = migrate_batch(deps.as_mut(), 0, 0).unwrap_err();
+    assert_eq!(err, ContractError::EmptyBatch {});
+    assert_eq!(
+        query(deps.as_ref(), mock_env(), QueryMsg::MigrationMetrics {}).unwrap(),
+        to_json_binary(&MigrationMetrics::default()).unwrap()
+    );
+
+    // claims only
+    let res = migrate_batch(deps.as_mut(), 0, 1).unwrap();
+    let (msg, funds) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![],
+            claims: vec![(
+                Addr::unchecked(USER2),
+                vec![Claim::new(700, Expiration::AtHeight(1_000))]
+            )],
+        }
+    );
+    assert_eq!(funds, coins(700, DENOM));
+
+    // stakes only
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    let (msg, funds) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER1), Uint128::new(10_000))],
+            claims: vec![],
+        }
+    );
+    assert_eq!(funds, coins(10_000, DENOM));
+}
+
+#[test]
+fn dao_dao_code_id_check() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    mock_code_id(&mut deps, 7);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        ExecuteMsg::UpdateDaoDaoCodeId { code_id: Some(8) },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateDaoDaoCodeId { code_id: Some(8) },
+    )
+    .unwrap();
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnexpectedCodeId {
+            expected: 8,
+            found: 7
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateDaoDaoCodeId { code_id: Some(7) },
+    )
+    .unwrap();
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+
+    // clearing the expectation skips the check entirely
+    mock_code_id(&mut deps, 9);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateDaoDaoCodeId { code_id: None },
+    )
+    .unwrap();
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+}
+
+#[test]
+fn members_above_weight() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 50_000);
+    stake(deps.as_mut(), &env, USER3, 20_000);
+    stake(deps.as_mut(), &env, "user4", 90_000);
+
+    let res: MembersAboveWeightResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::MembersAboveWeight {
+                weight: 20,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.members,
+        vec![
+            Member {
+                addr: USER2.to_string(),
+                weight: 50
+            },
+            Member {
+                addr: "user4".to_string(),
+                weight: 90
+            },
+        ]
+    );
+
+    assert_eq!(res.next_start_after, None);
+
+    let res: MembersAboveWeightResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::MembersAboveWeight {
+                weight: 20,
+                start_after: Some(USER2.to_string()),
+                limit: Some(1),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.members,
+        vec![Member {
+            addr: "user4".to_string(),
+            weight: 90
+        }]
+    );
+}
+
+#[test]
+fn members_above_weight_read_cap() {
+    let mut deps = setup();
+    let env = mock_env();
+    // more light members than a query reads, sorted before the heavy one
+    for i in 0..1_000 {
+        stake(deps.as_mut(), &env, &format!("staker{i:04}"), 5_000);
+    }
+    stake(deps.as_mut(), &env, USER1, 50_000);
+    let above = |deps: Deps, start_after: Option<String>| -> MembersAboveWeightResponse {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::MembersAboveWeight {
+                    weight: 20,
+                    start_after,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let res = above(deps.as_ref(), None);
+    assert_eq!(res.members, vec![]);
+    assert_eq!(res.next_start_after, Some(Addr::unchecked("staker0999")));
+    let res = above(deps.as_ref(), res.next_start_after.map(String::from));
+    assert_eq!(
+        res.members,
+        vec![Member {
+            addr: USER1.to_string(),
+            weight: 50
+        }]
+    );
+    assert_eq!(res.next_start_after, None);
+}
+
+fn update_pause_state(
+    deps: DepsMut,
+    paused: Option<bool>,
+    stake_migration_paused: Option<bool>,
+    claim_migration_paused: Option<bool>,
+) {
+    execute(
+        deps,
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdatePauseState {
+            paused,
+            stake_migration_paused,
+            claim_migration_paused,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn migratable_now() {
+    let mut deps = setup();
+    let migratable = |deps: Deps, env: Env| -> MigratableNowResponse {
+        from_json(query(deps, env, QueryMsg::MigratableNow {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        migratable(deps.as_ref(), mock_env()),
+        MigratableNowResponse {
+            stakes: true,
+            claims: true,
+        }
+    );
+
+    update_pause_state(deps.as_mut(), None, Some(true), None);
+    assert_eq!(
+        migratable(deps.as_ref(), mock_env()),
+        MigratableNowResponse {
+            stakes: false,
+            claims: true,
+        }
+    );
+    update_pause_state(deps.as_mut(), None, Some(false), None);
+    assert!(migratable(deps.as_ref(), mock_env()).stakes);
+
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    assert_eq!(
+        migratable(deps.as_ref(), mock_env()),
+        MigratableNowResponse {
+            stakes: false,
+            claims: false,
+        }
+    );
+    update_pause_state(deps.as_mut(), Some(false), None, None);
+
+    // not before the announced start
+    let start = mock_env().block.height + 5;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateMigrationStart { start: Some(start) },
+    )
+    .unwrap();
+    assert!(!migratable(deps.as_ref(), mock_env()).claims);
+    let mut env = mock_env();
+    env.block.height = start;
+    assert!(migratable(deps.as_ref(), env).claims);
+}
+
+#[test]
+fn pause_stake_migration() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+
+    update_pause_state(deps.as_mut(), None, Some(true), None);
+    let res: PauseStateResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::PauseState {}).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        PauseStateResponse {
+            paused: false,
+            stake_migration_paused: true,
+            claim_migration_paused: false,
+        }
+    );
+
+    let err = migrate_batch(deps.as_mut(), 1, 1).unwrap_err();
+    assert_eq!(err, ContractError::StakeMigrationPaused {});
+    // claims still go through
+    migrate_batch(deps.as_mut(), 0, 1).unwrap();
+
+    update_pause_state(deps.as_mut(), None, Some(false), None);
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+}
+
+#[test]
+fn pause_claim_migration() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+
+    update_pause_state(deps.as_mut(), None, None, Some(true));
+    let err = migrate_batch(deps.as_mut(), 1, 1).unwrap_err();
+    assert_eq!(err, ContractError::ClaimMigrationPaused {});
+    // stakes still go through
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+}
+
+#[test]
+fn pause_all_migration() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        ExecuteMsg::UpdatePauseState {
+            paused: Some(true),
+            stake_migration_paused: None,
+            claim_migration_paused: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    assert_eq!(
+        migrate_batch(deps.as_mut(), 1, 0).unwrap_err(),
+        ContractError::Paused {}
+    );
+    assert_eq!(
+        migrate_batch(deps.as_mut(), 0, 1).unwrap_err(),
+        ContractError::Paused {}
+    );
+}
+
+#[test]
+fn projected_voting_power_omits_excluded() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateExcluded {
+            add: vec![USER2.to_string()],
+            remove: vec![],
+        },
+    )
+    .unwrap();
+
+    let projected = |deps: Deps| -> ProjectedVotingPowerResponse {
+        from_json(query(deps, mock_env(), QueryMsg::ProjectedVotingPower {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        projected(deps.as_ref()),
+        ProjectedVotingPowerResponse {
+            weight: 40,
+            truncated: false,
+        }
+    );
+    let total: TotalWeightResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::TotalWeight {}).unwrap()).unwrap();
+    assert_eq!(total.weight, 60);
+
+    // excluded members are skipped by batches
+    let res = migrate_batch(deps.as_mut(), 10, 10).unwrap();
+    let (msg, _) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![
+                (Addr::unchecked(USER1), Uint128::new(10_000)),
+                (Addr::unchecked(USER3), Uint128::new(30_000)),
+            ],
+            claims: vec![],
+        }
+    );
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 20);
+
+    // the scan stops before reaching the excluded member
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateExcluded {
+            add: (0..1_000).map(|i| format!("excluded{i:04}")).collect(),
+            remove: vec![],
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        projected(deps.as_ref()),
+        ProjectedVotingPowerResponse {
+            weight: 20,
+            truncated: true,
+        }
+    );
+}
+
+#[test]
+fn consolidate_batch_entries() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER3, 700, Expiration::AtHeight(2_000));
+
+    let batch = MigrateBatch {
+        num: 10,
+        num_claims: 10,
+        consolidate: Some(true),
+        ..Default::default()
+    }
+    .msg();
+    // rejected until the destination is declared to accept MigrateEntries
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        batch.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MigrateEntriesDisabled {});
+
+    let enable = ExecuteMsg::UpdateMigrateEntries { enabled: true };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        enable.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), enable).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), batch).unwrap();
+    let CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) = &res.messages[0].msg else {
+        panic!("unexpected message");
+    };
+    assert_eq!(funds, &coins(31_200, DENOM));
+    let msg: DaoDaoExecuteMsg = from_json(msg).unwrap();
+    assert_eq!(
+        msg,
+        DaoDaoExecuteMsg::MigrateEntries {
+            entries: vec![
+                MigrateEntry {
+                    addr: Addr::unchecked(USER1),
+                    weight: Uint128::new(10_000),
+                    claims: vec![Claim::new(500, Expiration::AtHeight(1_000))],
+                },
+                MigrateEntry {
+                    addr: Addr::unchecked(USER2),
+                    weight: Uint128::new(20_000),
+                    claims: vec![],
+                },
+                MigrateEntry {
+                    addr: Addr::unchecked(USER3),
+                    weight: Uint128::zero(),
+                    claims: vec![Claim::new(700, Expiration::AtHeight(2_000))],
+                },
+            ]
+        }
+    );
+}
+
+#[test]
+fn destination_versions() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER3, 700, Expiration::AtHeight(2_000));
+
+    // V1 by default
+    let res = migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    let (msg, _) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER1), Uint128::new(10_000))],
+            claims: vec![(
+                Addr::unchecked(USER1),
+                vec![Claim::new(500, Expiration::AtHeight(1_000))]
+            )],
+        }
+    );
+
+    let update = ExecuteMsg::UpdateDestinationVersion {
+        version: DestinationVersion::V2,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        update.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+
+    let res = migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    let CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) = &res.messages[0].msg else {
+        panic!("unexpected message");
+    };
+    assert_eq!(funds, &coins(20_700, DENOM));
+    let msg: DaoDaoExecuteMsg = from_json(msg).unwrap();
+    assert_eq!(
+        msg,
+        DaoDaoExecuteMsg::MigrateStakes {
+            stakes: vec![StakeEntry {
+                address: Addr::unchecked(USER2),
+                amount: Uint128::new(20_000),
+            }],
+            claims: vec![ClaimsEntry {
+                address: Addr::unchecked(USER3),
+                claims: vec![Claim::new(700, Expiration::AtHeight(2_000))],
+            }],
+        }
+    );
+}
+
+#[test]
+fn max_per_block() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..5 {
+        stake(deps.as_mut(), &env, &format!("user{i}"), 10_000);
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateMaxPerBlock {
+            max_per_block: Some(3),
+        },
+    )
+    .unwrap();
+
+    migrate_batch(deps.as_mut(), 2, 0).unwrap();
+    let err = migrate_batch(deps.as_mut(), 2, 0).unwrap_err();
+    assert_eq!(err, ContractError::BlockLimitExceeded { max: 3 });
+
+    // the tally resets at the next height
+    let mut env = mock_env();
+    env.block.height += 1;
+    execute(deps.as_mut(), env, mock_info(ADMIN, &[]), migrate_msg(2, 0)).unwrap();
+}
+
+#[test]
+fn min_batch_interval() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..3 {
+        stake(deps.as_mut(), &env, &format!("user{i}"), 10_000);
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateMinBatchInterval {
+            interval: Some(Duration::Height(10)),
+        },
+    )
+    .unwrap();
+
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    let mut env = mock_env();
+    env.block.height += 9;
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 0),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::BatchTooSoon {
+            earliest: Expiration::AtHeight(mock_env().block.height + 10),
+        }
+    );
+
+    env.block.height += 1;
+    execute(deps.as_mut(), env, mock_info(ADMIN, &[]), migrate_msg(1, 0)).unwrap();
+}
+
+#[test]
+fn migration_start() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    let start = mock_env().block.height + 5;
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            dao_dao_addr: Some(Addr::unchecked(DAO_DAO)),
+            migration_start: Some(start),
+            registry: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.height = start - 1;
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 0),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MigrationNotStarted { start });
+
+    env.block.height = start;
+    execute(deps.as_mut(), env, mock_info(ADMIN, &[]), migrate_msg(1, 0)).unwrap();
+
+    // the admin can clear it
+    stake(deps.as_mut(), &mock_env(), USER2, 10_000);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateMigrationStart { start: None },
+    )
+    .unwrap();
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+}
+
+#[test]
+fn upgrade_mid_migration() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER3, 700, Expiration::AtHeight(1_000));
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+
+    let progress = |deps: Deps| -> Vec<Binary> {
+        [
+            QueryMsg::MigrationMetrics {},
+            QueryMsg::MigrationStatus {},
+            QueryMsg::ListMigrated {
+                start_after: None,
+                limit: None,
+            },
+            QueryMsg::PreMigrationSnapshot {},
+        ]
+        .into_iter()
+        .map(|msg| query(deps, mock_env(), msg).unwrap())
+        .collect()
+    };
+    let before = progress(deps.as_ref());
+
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            dao_dao_addr: None,
+            migration_start: None,
+            registry: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(progress(deps.as_ref()), before);
+
+    // the next batch picks up where the first one stopped
+    let res = migrate_batch(deps.as_mut(), 10, 10).unwrap();
+    let (msg, funds) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER2), Uint128::new(20_000))],
+            claims: vec![(
+                Addr::unchecked(USER3),
+                vec![Claim::new(700, Expiration::AtHeight(1_000))]
+            )],
+        }
+    );
+    assert_eq!(funds, coins(20_700, DENOM));
+}
+
+#[test]
+fn migrate_requires_dao_dao() {
+    let mut deps = mock_dependencies();
+    let msg = instantiate_msg(Denom::Native(DENOM.to_string()));
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    let err = migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            dao_dao_addr: None,
+            migration_start: None,
+            registry: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::DaoDaoNotSet {});
+}
+
+#[test]
+fn migrate_checks_registry() {
+    let mut deps = setup();
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, msg } if contract_addr == "registry" => {
+            let RegistryQueryMsg::IsRegistered { address } = from_json(msg).unwrap();
+            let res = IsRegisteredResponse {
+                registered: address == DAO_DAO,
+            };
+            SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+        }
+        _ => unimplemented!(),
+    });
+    let upgrade = |dao_dao: &str| MigrateMsg {
+        dao_dao_addr: Some(Addr::unchecked(dao_dao)),
+        migration_start: None,
+        registry: Some(Addr::unchecked("registry")),
+    };
+
+    let err = migrate(deps.as_mut(), mock_env(), upgrade("impostor")).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::NotRegisteredDao {
+            addr: "impostor".to_string(),
+        }
+    );
+    migrate(deps.as_mut(), mock_env(), upgrade(DAO_DAO)).unwrap();
+    assert_eq!(
+        crate::state::DAO_DAO.load(&deps.storage).unwrap(),
+        Addr::unchecked(DAO_DAO)
+    );
+
+    // the saved registry still checks a later upgrade
+    let err = migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            registry: None,
+            ..upgrade("impostor")
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::NotRegisteredDao { .. }));
+}
+
+#[test]
+fn excluded_staker_with_claims_is_skipped() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 200, Expiration::AtHeight(1_000));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateExcluded {
+            add: vec![USER1.to_string()],
+            remove: vec![],
+        },
+    )
+    .unwrap();
+
+    let res = migrate_batch(deps.as_mut(), 10, 10).unwrap();
+    let (msg, _) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER2), Uint128::new(20_000))],
+            claims: vec![(
+                Addr::unchecked(USER2),
+                vec![Claim::new(200, Expiration::AtHeight(1_000))]
+            )],
+        }
+    );
+    let user1 = Addr::unchecked(USER1);
+    assert_eq!(
+        STAKE.load(&deps.storage, &user1).unwrap(),
+        Uint128::new(10_000)
+    );
+    assert_eq!(
+        CLAIMS
+            .query_claims(deps.as_ref(), &user1)
+            .unwrap()
+            .claims
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn migrate_addresses() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    add_claim(deps.as_mut(), USER3, 300, Expiration::AtHeight(1_000));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateExcluded {
+            add: vec![USER2.to_string()],
+            remove: vec![],
+        },
+    )
+    .unwrap();
+    let migrate_addresses = |addrs: &[&str]| ExecuteMsg::MigrateAddresses {
+        addrs: addrs.iter().map(|addr| addr.to_string()).collect(),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_addresses(&[USER1, USER2]),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::AddressExcluded {
+            addr: USER2.to_string()
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_addresses(&[USER3]),
+    )
+    .unwrap();
+    let (msg, funds) = sent_migration(&res);
+    assert_eq!(
+        msg,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER3), Uint128::new(30_000))],
+            claims: vec![(
+                Addr::unchecked(USER3),
+                vec![Claim::new(300, Expiration::AtHeight(1_000))]
+            )],
+        }
+    );
+    assert_eq!(funds, coins(30_300, DENOM));
+    assert!(STAKE.has(&deps.storage, &Addr::unchecked(USER1)));
+
+    // a retry with an overlapping set only sends the new address
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_addresses(&[USER3, USER1]),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(10_000, DENOM));
+    let skipped = res
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "already_migrated")
+        .unwrap();
+    assert_eq!(skipped.value, USER3);
+
+    // and a full retry sends nothing
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_addresses(&[USER1, USER3]),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![]);
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("already_migrated", format!("{USER1},{USER3}"))
+        ]
+    );
+}
+
+#[test]
+fn claims_bounded() {
+    let mut deps = setup();
+    for i in 0..5u64 {
+        add_claim(
+            deps.as_mut(),
+            USER1,
+            100 + i as u128,
+            Expiration::AtHeight(1_000 + i),
+        );
+    }
+    let bounded = |deps: Deps, max: u32| -> ClaimsBoundedResponse {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::ClaimsBounded {
+                    address: USER1.to_string(),
+                    max,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let res = bounded(deps.as_ref(), 3);
+    assert!(res.truncated);
+    assert_eq!(
+        res.claims
+            .iter()
+            .map(|claim| claim.amount.u128())
+            .collect::<Vec<_>>(),
+        vec![100, 101, 102]
+    );
+    let res = bounded(deps.as_ref(), 5);
+    assert!(!res.truncated);
+    assert_eq!(res.claims.len(), 5);
+}
+
+#[test]
+fn claims_normalized() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(
+        deps.as_mut(),
+        USER1,
+        200,
+        Expiration::AtTime(Timestamp::from_seconds(1_700_000_000)),
+    );
+    add_claim(deps.as_mut(), USER1, 300, Expiration::Never {});
+
+    let res: ClaimsNormalizedResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ClaimsNormalized {
+                address: USER1.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.claims,
+        vec![
+            NormalizedClaim {
+                amount: Uint128::new(100),
+                release_height: Some(1_000),
+                release_time: None,
+            },
+            NormalizedClaim {
+                amount: Uint128::new(200),
+                release_height: None,
+                release_time: Some(1_700_000_000),
+            },
+            NormalizedClaim {
+                amount: Uint128::new(300),
+                release_height: None,
+                release_time: None,
+            },
+        ]
+    );
+}
+
+/// Wraps `data` the way wasmd wraps the data of an executed contract.
+fn execute_response_data(data: &[u8]) -> Binary {
+    let mut encoded = vec![0x0a, data.len() as u8];
+    encoded.extend_from_slice(data);
+    Binary::from(encoded)
+}
+
+fn verify_reply(deps: DepsMut, weight: Option<u128>) -> Response {
+    let data = weight.map(|weight| {
+        let acceptance = MigrationAcceptance {
+            weight: Uint128::new(weight),
+        };
+        execute_response_data(&to_json_binary(&acceptance).unwrap())
+    });
+    let msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data,
+        }),
+    };
+    reply(deps, mock_env(), msg).unwrap()
+}
+
+fn discrepancies(deps: Deps) -> Vec<(u64, Discrepancy)> {
+    let res: DiscrepanciesResponse = from_json(
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::ListDiscrepancies {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    res.discrepancies
+}
+
+#[test]
+fn verify_migration_reply() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    let verified = |num| {
+        MigrateBatch {
+            num,
+            num_claims: 0,
+            verify: Some(true),
+            ..Default::default()
+        }
+        .msg()
+    };
+
+    // accepted in full, nothing recorded
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        verified(1),
+    )
+    .unwrap();
+    assert_eq!(res.messages[0].reply_on, ReplyOn::Success);
+    verify_reply(deps.as_mut(), Some(10_000));
+    assert_eq!(discrepancies(deps.as_ref()), vec![]);
+
+    // DAO DAO credits less than it was sent
+    execute(deps.as_mut(), env, mock_info(ADMIN, &[]), verified(1)).unwrap();
+    let res = verify_reply(deps.as_mut(), Some(15_000));
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("discrepancy", "true")));
+    assert_eq!(
+        discrepancies(deps.as_ref()),
+        vec![(
+            2,
+            Discrepancy {
+                sent: Uint128::new(20_000),
+                accepted: Some(Uint128::new(15_000)),
+            }
+        )]
+    );
+
+    // unverified batches record no discrepancy
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(res.messages[0].reply_on, ReplyOn::Success);
+    verify_reply(deps.as_mut(), None);
+    assert_eq!(discrepancies(deps.as_ref()).len(), 1);
+}
+
+#[test]
+fn verify_migration_without_data() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    let msg = MigrateBatch {
+        num: 1,
+        num_claims: 0,
+        verify: Some(true),
+        ..Default::default()
+    }
+    .msg();
+    execute(deps.as_mut(), env, mock_info(ADMIN, &[]), msg.clone()).unwrap();
+
+    // as with dao-voting-token-staked, which returns no data
+    let res = verify_reply(deps.as_mut(), None);
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("verify_skipped", "no acceptance data")));
+    assert_eq!(discrepancies(deps.as_ref()), vec![]);
+
+    // data of the destination's own doesn't revert the batch
+    stake(deps.as_mut(), &mock_env(), USER2, 20_000);
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(execute_response_data(b"\x01\x02 not json")),
+        }),
+    };
+    let res = reply(deps.as_mut(), mock_env(), msg).unwrap();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("verify_skipped", "no acceptance data")));
+    assert_eq!(discrepancies(deps.as_ref()), vec![]);
+}
+
+#[test]
+fn balance_reconciliation() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    let reconciliation = |deps: Deps| -> BalanceReconciliationResponse {
+        from_json(query(deps, mock_env(), QueryMsg::BalanceReconciliation {}).unwrap()).unwrap()
+    };
+
+    deps.querier
+        .update_balance(env.contract.address.clone(), coins(30_500, DENOM));
+    assert_eq!(
+        reconciliation(deps.as_ref()),
+        BalanceReconciliationResponse {
+            expected: Uint128::new(30_500),
+            actual: Uint128::new(30_500),
+            reconciled: true,
+            truncated: false,
+        }
+    );
+
+    deps.querier
+        .update_balance(env.contract.address, coins(30_000, DENOM));
+    assert_eq!(
+        reconciliation(deps.as_ref()),
+        BalanceReconciliationResponse {
+            expected: Uint128::new(30_500),
+            actual: Uint128::new(30_000),
+            reconciled: false,
+            truncated: false,
+        }
+    );
+}
+
+#[test]
+fn balance_reconciliation_before_snapshot() {
+    let mut deps = setup_unprepared(Denom::Native(DENOM.to_string()));
+    let env = mock_env();
+    for i in 0..1_001 {
+        stake(deps.as_mut(), &env, &format!("staker{i:04}"), 5_000);
+    }
+    let reconciliation = |deps: Deps| -> BalanceReconciliationResponse {
+        from_json(query(deps, mock_env(), QueryMsg::BalanceReconciliation {}).unwrap()).unwrap()
+    };
+
+    // only part of the stakes is read until the snapshot records them
+    let res = reconciliation(deps.as_ref());
+    assert_eq!(res.expected, Uint128::new(5_000_000));
+    assert!(res.truncated);
+
+    prepare_snapshot(deps.as_mut(), 10_000).unwrap();
+    let res = reconciliation(deps.as_ref());
+    assert_eq!(res.expected, Uint128::new(5_005_000));
+    assert!(!res.truncated);
+}
+
+#[test]
+fn prune_zero_weight_members() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 3_000);
+    stake(deps.as_mut(), &env, USER2, 10_000);
+    stake(deps.as_mut(), &env, USER3, 0);
+    let total = TOTAL.load(&deps.storage).unwrap();
+    // stakes below min_bond are not members, pruning finds them by stake
+    for addr in [USER1, USER3] {
+        let addr = Addr::unchecked(addr);
+        assert_eq!(MEMBERS.may_load(&deps.storage, &addr).unwrap(), None);
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(USER1, &[]),
+        ExecuteMsg::PruneZeroWeightMembers {
+            num: 10,
+            start_after: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::PruneZeroWeightMembers {
+            num: 10,
+            start_after: None,
+        },
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&Attribute::new("pruned", "2")));
+    // only the dust stake is refunded
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|sub| sub.msg.clone())
+            .collect::<Vec<_>>(),
+        vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: USER1.to_string(),
+            amount: coins(3_000, DENOM),
+        })]
+    );
+
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), total);
+    for addr in [USER1, USER3] {
+        let addr = Addr::unchecked(addr);
+        assert_eq!(MEMBERS.may_load(&deps.storage, &addr).unwrap(), None);
+        assert_eq!(STAKE.may_load(&deps.storage, &addr).unwrap(), None);
+    }
+    assert_eq!(
+        MEMBERS
+            .load(&deps.storage, &Addr::unchecked(USER2))
+            .unwrap(),
+        10
+    );
+}
+
+#[test]
+fn prune_zero_weight_members_paged() {
+    let mut deps = setup();
+    let env = mock_env();
+    // more full stakes than a call reads, sorted before the dust one
+    for i in 0..1_000 {
+        stake(deps.as_mut(), &env, &format!("staker{i:04}"), 10_000);
+    }
+    stake(deps.as_mut(), &env, USER1, 1_000);
+    stake(deps.as_mut(), &env, USER2, 2_000);
+    let prune = |deps: DepsMut, num: u64, start_after: Option<String>| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::PruneZeroWeightMembers { num, start_after },
+        )
+        .unwrap()
+    };
+    let next = |res: &Response| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == "next_start_after")
+            .map(|attr| attr.value.clone())
+    };
+
+    let res = prune(deps.as_mut(), 10, None);
+    assert!(res.attributes.contains(&Attribute::new("pruned", "0")));
+    assert_eq!(next(&res), Some("staker0999".to_string()));
+
+    // stopped by `num`, the next call resumes after the pruned stake
+    let res = prune(deps.as_mut(), 1, next(&res));
+    assert!(res.attributes.contains(&Attribute::new("pruned", "1")));
+    assert_eq!(next(&res), Some(USER1.to_string()));
+    let res = prune(deps.as_mut(), 10, next(&res));
+    assert!(res.attributes.contains(&Attribute::new("pruned", "1")));
+    assert_eq!(next(&res), None);
+    for addr in [USER1, USER2] {
+        assert!(!STAKE.has(&deps.storage, &Addr::unchecked(addr)));
+    }
+}
+
+#[test]
+fn batch_history() {
+    let mut deps = setup();
+    let mut env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 1),
+    )
+    .unwrap();
+    env.block.height += 1;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 0),
+    )
+    .unwrap();
+
+    let res: BatchHistoryResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BatchHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.batches,
+        vec![
+            BatchRecord {
+                batch_index: 1,
+                sender: Addr::unchecked(ADMIN),
+                height: env.block.height - 1,
+                stake_count: 1,
+                claim_count: 1,
+            },
+            BatchRecord {
+                batch_index: 2,
+                sender: Addr::unchecked(ADMIN),
+                height: env.block.height,
+                stake_count: 1,
+                claim_count: 0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn batches_admin_only() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+
+    for msg in [
+        migrate_msg(1, 1),
+        ExecuteMsg::MigrateAndFinalize {
+            num: 1,
+            num_claims: 1,
+        },
+        ExecuteMsg::RetryFailed { num: 1 },
+        ExecuteMsg::MigrateAddresses {
+            addrs: vec![USER1.to_string()],
+        },
+        ExecuteMsg::PrepareBatch {
+            num: 1,
+            num_claims: 1,
+        },
+        ExecuteMsg::CommitBatch {
+            expected_sum: Uint128::new(10_500),
+        },
+    ] {
+        let err = execute(deps.as_mut(), mock_env(), mock_info(USER1, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+    assert!(STAKE.has(&deps.storage, &Addr::unchecked(USER1)));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 1),
+    )
+    .unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(10_500, DENOM));
+
+    // with the admin cleared, nobody may run a batch
+    let mut deps = setup();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    crate::state::ADMIN.set(deps.as_mut(), None).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 0),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoAdmin {});
+}
+
+#[test]
+fn import_claims_denom() {
+    let mut deps = setup();
+    let claims = vec![Claim::new(100, Expiration::AtHeight(1_000))];
+    let import = |denom: &str| ExecuteMsg::ImportClaims {
+        entries: vec![(USER1.to_string(), claims.clone())],
+        denom: Denom::Native(denom.to_string()),
+        overwrite: false,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        import("uatom"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ClaimDenomMismatch {
+            expected: DENOM.to_string(),
+            found: "uatom".to_string(),
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        import(DENOM),
+    )
+    .unwrap();
+    let res = migrate_batch(deps.as_mut(), 0, 1).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(100, DENOM));
+}
+
+#[test]
+fn next_maturity() {
+    let mut deps = setup();
+    let next_maturity = |deps: Deps, addr: &str| -> Option<Expiration> {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::NextMaturity {
+                    address: addr.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(next_maturity(deps.as_ref(), USER1), None);
+
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(3_000));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER1, 300, Expiration::AtHeight(2_000));
+    assert_eq!(
+        next_maturity(deps.as_ref(), USER1),
+        Some(Expiration::AtHeight(1_000))
+    );
+}
+
+#[test]
+fn claims_info() {
+    let mut deps = setup();
+    let claims_info = |deps: Deps| -> ClaimsInfoResponse {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::ClaimsInfo {
+                    address: USER1.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        claims_info(deps.as_ref()),
+        ClaimsInfoResponse {
+            count: 0,
+            oldest: None,
+            newest: None,
+        }
+    );
+
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(3_000));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER1, 300, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER2, 400, Expiration::AtHeight(500));
+    assert_eq!(
+        claims_info(deps.as_ref()),
+        ClaimsInfoResponse {
+            count: 3,
+            oldest: Some(Expiration::AtHeight(1_000)),
+            newest: Some(Expiration::AtHeight(3_000)),
+        }
+    );
+}
+
+#[test]
+fn debug_outgoing_payload() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert!(!res.attributes.iter().any(|a| a.key == "outgoing_payload"));
+
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    let msg = MigrateBatch {
+        num: 1,
+        num_claims: 0,
+        debug: Some(true),
+        ..Default::default()
+    }
+    .msg();
+    let res = execute(deps.as_mut(), env, mock_info(ADMIN, &[]), msg).unwrap();
+    let payload = res
+        .attributes
+        .iter()
+        .find(|a| a.key == "outgoing_payload")
+        .unwrap();
+    let (sent, _) = sent_migration(&res);
+    assert_eq!(
+        from_json::<dao_voting_token_staked::msg::ExecuteMsg>(payload.value.as_bytes()).unwrap(),
+        sent
+    );
+    assert_eq!(
+        sent,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER2), Uint128::new(20_000))],
+            claims: vec![],
+        }
+    );
+}
+
+#[test]
+fn min_weight_filter() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 6_000);
+    stake(deps.as_mut(), &env, USER2, 50_000);
+    stake(deps.as_mut(), &env, USER3, 80_000);
+
+    let msg = MigrateBatch {
+        num: 1,
+        num_claims: 0,
+        min_weight: Some(10),
+        ..Default::default()
+    }
+    .msg();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    // user1 is skipped without using up the batch
+    let (sent, _) = sent_migration(&res);
+    assert_eq!(
+        sent,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER2), Uint128::new(50_000))],
+            claims: vec![],
+        }
+    );
+
+    let res = execute(deps.as_mut(), env, mock_info(ADMIN, &[]), msg).unwrap();
+    let (sent, _) = sent_migration(&res);
+    assert_eq!(
+        sent,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER3), Uint128::new(80_000))],
+            claims: vec![],
+        }
+    );
+    assert_eq!(
+        STAKE.load(&deps.storage, &Addr::unchecked(USER1)).unwrap(),
+        Uint128::new(6_000)
+    );
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 6);
+}
+
+#[test]
+fn dao_dao_info() {
+    let mut deps = mock_dependencies();
+    let msg = instantiate_msg(Denom::Native(DENOM.to_string()));
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    let err = query(deps.as_ref(), mock_env(), QueryMsg::DaoDaoInfo {}).unwrap_err();
+    assert_eq!(err, StdError::not_found("DAO DAO address"));
+
+    let mut deps = setup();
+    mock_code_id(&mut deps, 42);
+    let info: ContractInfoResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::DaoDaoInfo {}).unwrap()).unwrap();
+    assert_eq!(info.code_id, 42);
+    assert_eq!(info.creator, "creator");
+}
+
+#[test]
+fn split_large_batch() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..101 {
+        stake(deps.as_mut(), &env, &format!("staker{i:03}"), 10_000 + i);
+    }
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 700, Expiration::AtHeight(1_000));
+
+    let res = migrate_batch(deps.as_mut(), 101, 2).unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    let mut stakes_sent = 0;
+    let mut claims_sent = 0;
+    let mut total = Uint128::zero();
+    for sub in &res.messages {
+        let CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) = &sub.msg else {
+            panic!("unexpected message {:?}", sub.msg);
+        };
+        let dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { weights, claims } =
+            from_json(msg).unwrap()
+        else {
+            panic!("unexpected payload");
+        };
+        assert!(weights.len() + claims.len() <= 100);
+        let expected: Uint128 = weights.iter().map(|(_, w)| *w).sum::<Uint128>()
+            + claims
+                .iter()
+                .flat_map(|(_, claims)| claims.iter().map(|c| c.amount))
+                .sum::<Uint128>();
+        assert_eq!(funds, &coins(expected.u128(), DENOM));
+        stakes_sent += weights.len();
+        claims_sent += claims.len();
+        total += expected;
+    }
+    assert_eq!(stakes_sent, 101);
+    assert_eq!(claims_sent, 2);
+    let staked: u128 = (0..101).map(|i| 10_000 + i).sum();
+    assert_eq!(total, Uint128::new(staked + 1_200));
+    assert!(res.attributes.contains(&Attribute::new(
+        "amount",
+        format!("{} {DENOM}", staked + 1_200)
+    )));
+}
+
+#[test]
+fn stake_distribution() {
+    let mut deps = setup();
+    let env = mock_env();
+    for (i, amount) in [1_000, 5_000, 9_999, 10_000, 50_000, 200_000]
+        .into_iter()
+        .enumerate()
+    {
+        stake(deps.as_mut(), &env, &format!("staker{i}"), amount);
+    }
+
+    let res: StakeDistributionResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::StakeDistribution {
+                buckets: vec![Uint128::new(100_000), Uint128::new(10_000)],
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        StakeDistributionResponse {
+            buckets: vec![Uint128::new(10_000), Uint128::new(100_000)],
+            counts: vec![3, 2, 1],
+            stakers: 6,
+            total: Uint128::new(275_999),
+            average: Uint128::new(45_999),
+            truncated: false,
+        }
+    );
+}
+
+#[test]
+fn abort_migration() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(USER1, &[]),
+        ExecuteMsg::AbortMigration {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::AbortMigration {},
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&Attribute::new("batches_run", "1")));
+
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(err, ContractError::MigrationAborted {});
+    assert_eq!(
+        STAKE.load(&deps.storage, &Addr::unchecked(USER2)).unwrap(),
+        Uint128::new(20_000)
+    );
+}
+
+#[test]
+fn claims_in_height_range() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(500));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 300, Expiration::AtHeight(1_500));
+    add_claim(deps.as_mut(), USER2, 400, Expiration::AtHeight(2_001));
+    add_claim(
+        deps.as_mut(),
+        USER3,
+        500,
+        Expiration::AtTime(Timestamp::from_seconds(1_200)),
+    );
+    let in_range = |deps: Deps, limit| -> ClaimsInRangeResponse {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::ClaimsInHeightRange {
+                    from: 1_000,
+                    to: 2_000,
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let res = in_range(deps.as_ref(), 10);
+    assert!(!res.truncated);
+    assert_eq!(
+        res.claims,
+        vec![
+            (
+                Addr::unchecked(USER1),
+                Claim::new(200, Expiration::AtHeight(1_000))
+            ),
+            (
+                Addr::unchecked(USER2),
+                Claim::new(300, Expiration::AtHeight(1_500))
+            ),
+        ]
+    );
+    // stopped at the limit, with more addresses unread
+    let res = in_range(deps.as_ref(), 1);
+    assert_eq!(res.claims.len(), 1);
+    assert!(res.truncated);
+}
+
+#[test]
+fn claims_by_type() {
+    let mut deps = setup();
+    let at_time = Expiration::AtTime(Timestamp::from_seconds(1_200));
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(500));
+    add_claim(deps.as_mut(), USER1, 200, at_time);
+    add_claim(deps.as_mut(), USER2, 300, Expiration::Never {});
+    add_claim(deps.as_mut(), USER3, 400, Expiration::AtHeight(1_500));
+    let by_type = |height_based| -> Vec<(Addr, Claim)> {
+        let res: ClaimsInRangeResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ClaimsByType {
+                    height_based,
+                    limit: 10,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        // every address was read
+        assert!(!res.truncated);
+        res.claims
+    };
+
+    assert_eq!(
+        by_type(true),
+        vec![
+            (
+                Addr::unchecked(USER1),
+                Claim::new(100, Expiration::AtHeight(500))
+            ),
+            (
+                Addr::unchecked(USER3),
+                Claim::new(400, Expiration::AtHeight(1_500))
+            ),
+        ]
+    );
+    assert_eq!(
+        by_type(false),
+        vec![(Addr::unchecked(USER1), Claim::new(200, at_time))]
+    );
+}
+
+#[test]
+fn funds_to_override() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    let msg = MigrateBatch {
+        num: 1,
+        num_claims: 0,
+        funds_to: Some("sub_staking".to_string()),
+        ..Default::default()
+    }
+    .msg();
+
+    let res = execute(deps.as_mut(), env, mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "sub_staking".to_string(),
+            amount: coins(10_000, DENOM),
+        })
+    );
+    match &res.messages[1].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            funds,
+            ..
+        }) => {
+            assert_eq!(contract_addr, DAO_DAO);
+            assert_eq!(funds, &vec![]);
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+
+    // later batches pay DAO DAO again
+    stake(deps.as_mut(), &mock_env(), USER2, 20_000);
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(20_000, DENOM));
+}
+
+#[test]
+fn can_exit() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(
+        deps.as_mut(),
+        USER2,
+        200,
+        Expiration::AtHeight(env.block.height + 10),
+    );
+    let can_exit = |deps: Deps, addr: &str| -> CanExitResponse {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::CanExit {
+                    address: addr.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let reason = |deps: Deps, addr: &str| {
+        let res = can_exit(deps, addr);
+        assert!(!res.can_unstake && !res.can_claim);
+        res.reason.unwrap()
+    };
+    assert_eq!(reason(deps.as_ref(), USER1), "exit only via migration");
+    assert_eq!(reason(deps.as_ref(), USER2), "exit only via migration");
+    assert_eq!(
+        reason(deps.as_ref(), USER3),
+        "nothing staked or claimed, or already migrated"
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::AbortMigration {},
+    )
+    .unwrap();
+    assert_eq!(reason(deps.as_ref(), USER1), "migration is aborted");
+}
+
+#[test]
+fn account_status() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 12_000);
+    add_claim(
+        deps.as_mut(),
+        USER1,
+        500,
+        Expiration::AtHeight(env.block.height),
+    );
+    add_claim(
+        deps.as_mut(),
+        USER1,
+        700,
+        Expiration::AtHeight(env.block.height + 10),
+    );
+    let status = |deps: Deps, addr: &str| -> StdResult<AccountStatusResponse> {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::AccountStatus {
+                address: addr.to_string(),
+            },
+        )
+        .and_then(from_json)
+    };
+
+    assert_eq!(
+        status(deps.as_ref(), USER1).unwrap(),
+        AccountStatusResponse {
+            staked: Uint128::new(12_000),
+            weight: 12,
+            pending_claims: Uint128::new(700),
+            claimable_now: Uint128::new(500),
+            migrated: false,
+        }
+    );
+
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    assert_eq!(
+        status(deps.as_ref(), USER1).unwrap(),
+        AccountStatusResponse {
+            staked: Uint128::zero(),
+            weight: 0,
+            pending_claims: Uint128::zero(),
+            claimable_now: Uint128::zero(),
+            migrated: true,
+        }
+    );
+
+    status(deps.as_ref(), "").unwrap_err();
+}
+
+#[test]
+fn payload_carries_token_amounts() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 12_345);
+    stake(deps.as_mut(), &env, USER2, 7_999);
+    stake(deps.as_mut(), &env, USER3, 3_000);
+
+    let res = migrate_batch(deps.as_mut(), 3, 0).unwrap();
+    let (sent, _) = sent_migration(&res);
+    // cw4 weights would have been 12, 7 and 0
+    assert_eq!(
+        sent,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![
+                (Addr::unchecked(USER1), Uint128::new(12_345)),
+                (Addr::unchecked(USER2), Uint128::new(7_999)),
+                (Addr::unchecked(USER3), Uint128::new(3_000)),
+            ],
+            claims: vec![],
+        }
+    );
+}
+
+#[test]
+fn typed_migration_errors() {
+    let mut deps = mock_dependencies();
+    crate::state::ADMIN
+        .set(deps.as_mut(), Some(Addr::unchecked(ADMIN)))
+        .unwrap();
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(err, ContractError::ConfigNotFound {});
+
+    let msg = instantiate_msg(Denom::Native(DENOM.to_string()));
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(err, ContractError::DaoDaoNotSet {});
+
+    // a total overridden below the weight migrated fails instead of panicking
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::SetTotal { weight: 3 },
+    )
+    .unwrap();
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+    update_pause_state(deps.as_mut(), Some(false), None, None);
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::TotalWeightUnderflow {
+            total: 3,
+            removed: 10
+        }
+    );
+}
+
+#[test]
+fn list_migrated() {
+    let mut deps = setup();
+    let mut env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(2, 1),
+    )
+    .unwrap();
+    env.block.height += 1;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 0),
+    )
+    .unwrap();
+
+    let page = |deps: Deps, start_after: Option<&str>| -> Vec<MigrationReceipt> {
+        let res: ListMigratedResponse = from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::ListMigrated {
+                    start_after: start_after.map(str::to_string),
+                    limit: Some(2),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.migrated
+    };
+
+    let first = page(deps.as_ref(), None);
+    assert_eq!(
+        first,
+        vec![
+            MigrationReceipt {
+                addr: Addr::unchecked(USER1),
+                weight: 10,
+                amount: Uint128::new(10_500),
+                height: env.block.height - 1,
+                confirmed: None,
+            },
+            MigrationReceipt {
+                addr: Addr::unchecked(USER2),
+                weight: 20,
+                amount: Uint128::new(20_000),
+                height: env.block.height - 1,
+                confirmed: None,
+            },
+        ]
+    );
+    let second = page(deps.as_ref(), Some(USER2));
+    assert_eq!(
+        second,
+        vec![MigrationReceipt {
+            addr: Addr::unchecked(USER3),
+            weight: 30,
+            amount: Uint128::new(30_000),
+            height: env.block.height,
+            confirmed: None,
+        }]
+    );
+    assert_eq!(page(deps.as_ref(), Some(USER3)), vec![]);
+}
+
+#[test]
+fn receipt_root() {
+    let mut deps = setup();
+    let env = mock_env();
+    let root = |deps: Deps| -> Binary {
+        from_json::<ReceiptRootResponse>(query(deps, mock_env(), QueryMsg::ReceiptRoot {}).unwrap())
+            .unwrap()
+            .root
+    };
+    assert_eq!(root(deps.as_ref()), Binary::from([0u8; 32]));
+
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    let res = migrate_batch(deps.as_mut(), 2, 1).unwrap();
+
+    // replayed as documented on `QueryMsg::ReceiptRoot`
+    let expected = [
+        (USER1, 10u64, 10_000u128),
+        (USER2, 20, 20_000),
+        (USER3, 0, 500),
+    ]
+    .into_iter()
+    .fold([0u8; 32].to_vec(), |root, (addr, weight, amount)| {
+        Sha256::new()
+            .chain_update(root)
+            .chain_update([0u8])
+            .chain_update(weight.to_be_bytes())
+            .chain_update(amount.to_be_bytes())
+            .chain_update(addr.as_bytes())
+            .finalize()
+            .to_vec()
+    });
+    assert_eq!(root(deps.as_ref()), Binary::from(expected));
+    // pinned, so any change to the encoding is caught
+    assert_eq!(
+        root(deps.as_ref()).to_base64(),
+        "oHjSWnz3iLOIO2QJTjAn2wAdy/q/jRq7JbmFBTUO5dU="
+    );
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "receipt_root" && attr.value == root(deps.as_ref()).to_base64()));
+}
+
+#[test]
+fn export_receipts() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    migrate_batch(deps.as_mut(), 2, 1).unwrap();
+
+    let res: ExportReceiptsResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ExportReceipts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.receipts.len(), 3);
+    let parsed: Vec<_> = res
+        .csv
+        .split(';')
+        .map(|entry| {
+            let fields: Vec<_> = entry.split(',').collect();
+            (
+                Addr::unchecked(fields[0]),
+                fields[1].parse::<u64>().unwrap(),
+                fields[2].parse::<Uint128>().unwrap(),
+                fields[3].parse::<u64>().unwrap(),
+            )
+        })
+        .collect();
+    let expected: Vec<_> = res
+        .receipts
+        .into_iter()
+        .map(|r| (r.addr, r.weight, r.amount, r.height))
+        .collect();
+    assert_eq!(parsed, expected);
+    assert_eq!(
+        parsed[2],
+        (
+            Addr::unchecked(USER3),
+            0,
+            Uint128::new(500),
+            env.block.height
+        )
+    );
+}
+
+#[test]
+fn collapse_claims() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(3_000));
+    add_claim(deps.as_mut(), USER1, 300, Expiration::AtHeight(1_000));
+    let msg = MigrateBatch {
+        num: 0,
+        num_claims: 1,
+        collapse_claims: Some(true),
+        ..Default::default()
+    }
+    .msg();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let (sent, funds) = sent_migration(&res);
+    assert_eq!(
+        sent,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![],
+            claims: vec![(
+                Addr::unchecked(USER1),
+                vec![Claim::new(600, Expiration::AtHeight(3_000))]
+            )],
+        }
+    );
+    assert_eq!(funds, coins(600, DENOM));
+}
+
+#[test]
+fn total_weight_at() {
+    let mut deps = setup();
+    let mut env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    let start = env.block.height;
+
+    env.block.height += 10;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 0),
+    )
+    .unwrap();
+
+    let total_at = |deps: Deps, height| -> u64 {
+        let res: TotalWeightResponse =
+            from_json(query(deps, mock_env(), QueryMsg::TotalWeightAt { height }).unwrap())
+                .unwrap();
+        res.weight
+    };
+    assert_eq!(total_at(deps.as_ref(), start + 5), 30);
+    assert_eq!(total_at(deps.as_ref(), start + 10), 30);
+    assert_eq!(total_at(deps.as_ref(), start + 11), 20);
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 20);
+}
+
+/// Instantiates with DAO DAO already set and the given stakes seeded, as
+/// multi-test cannot run the contract migration on a cw4-stake contract.
+#[cw_serde]
+struct SeededInstantiateMsg {
+    admin: String,
+    dao_dao: String,
+    stakes: Vec<(String, u128)>,
+}
+
+fn seeded_instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: SeededInstantiateMsg,
+) -> Result<Response, ContractError> {
+    let res = instantiate(
+        deps.branch(),
+        env.clone(),
+        info,
+        InstantiateMsg {
+            admin: Some(msg.admin.clone()),
+            ..instantiate_msg(Denom::Native(DENOM.to_string()))
+        },
+    )?;
+    crate::state::DAO_DAO.save(deps.storage, &Addr::unchecked(msg.dao_dao))?;
+    for (addr, amount) in msg.stakes {
+        stake(deps.branch(), &env, &addr, amount);
+    }
+    execute(
+        deps,
+        env,
+        mock_info(&msg.admin, &[]),
+        ExecuteMsg::PrepareSnapshot { num: 100 },
+    )?;
+    Ok(res)
+}
+
+fn stake_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, seeded_instantiate, query).with_reply(reply))
+}
+
+/// A DAO DAO contract rejecting every migration.
+fn rejecting_dao_dao() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        |_: DepsMut,
+         _: Env,
+         _: MessageInfo,
+         _: dao_voting_token_staked::msg::ExecuteMsg|
+         -> StdResult<Response> { Err(StdError::generic_err("rejected")) },
+        |_: DepsMut, _: Env, _: MessageInfo, _: Empty| -> StdResult<Response> {
+            Ok(Response::new())
+        },
+        |_: Deps, _: Env, _: Empty| -> StdResult<Binary> { unimplemented!() },
+    ))
+}
+
+#[cw_serde]
+enum AutomationMsg {
+    Run { contract: String, msg: Binary },
+    Callback(CallbackMsg),
+}
+
+/// Runs migration batches and reports the callbacks it receives.
+fn automation() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        |_: DepsMut, _: Env, _: MessageInfo, msg: AutomationMsg| -> StdResult<Response> {
+            match msg {
+                AutomationMsg::Run { contract, msg } => {
+                    Ok(Response::new().add_message(WasmMsg::Execute {
+                        contract_addr: contract,
+                        msg,
+                        funds: vec![],
+                    }))
+                }
+                AutomationMsg::Callback(callback) => {
+                    let data: MigrationCallback = callback.deserialize_data()?;
+                    Ok(Response::new()
+                        .add_attribute("callback_status", format!("{:?}", data.status))
+                        .add_attribute("callback_failed", data.failed.to_string()))
+                }
+            }
+        },
+        |_: DepsMut, _: Env, _: MessageInfo, _: Empty| -> StdResult<Response> {
+            Ok(Response::new())
+        },
+        |_: Deps, _: Env, _: Empty| -> StdResult<Binary> { unimplemented!() },
+    ))
+}
+
+/// Deploys a seeded and funded cw4-stake contract migrating to `dao_dao`.
+fn setup_app(dao_dao: Box<dyn Contract<Empty>>, stakes: &[(&str, u128)]) -> (App, Addr) {
+    let mut app = App::default();
+    let stake = deploy_stake(&mut app, dao_dao, stakes, ADMIN);
+    (app, stake)
+}
+
+/// Like `setup_app`, with the automation contract deployed first and made the
+/// admin, as only the admin may run a batch.
+fn setup_automated_app(
+    dao_dao: Box<dyn Contract<Empty>>,
+    stakes: &[(&str, u128)],
+) -> (App, Addr, Addr) {
+    let mut app = App::default();
+    let automation_id = app.store_code(automation());
+    let automation = app
+        .instantiate_contract(
+            automation_id,
+            Addr::unchecked("creator"),
+            &Empty {},
+            &[],
+            "bot",
+            None,
+        )
+        .unwrap();
+    let stake = deploy_stake(&mut app, dao_dao, stakes, automation.as_str());
+    (app, stake, automation)
+}
+
+fn deploy_stake(
+    app: &mut App,
+    dao_dao: Box<dyn Contract<Empty>>,
+    stakes: &[(&str, u128)],
+    admin: &str,
+) -> Addr {
+    let dao_dao_id = app.store_code(dao_dao);
+    let stake_id = app.store_code(stake_contract());
+    let creator = Addr::unchecked("creator");
+
+    let dao_dao = app
+        .instantiate_contract(dao_dao_id, creator.clone(), &Empty {}, &[], "dao", None)
+        .unwrap();
+    let stake = app
+        .instantiate_contract(
+            stake_id,
+            creator,
+            &SeededInstantiateMsg {
+                admin: admin.to_string(),
+                dao_dao: dao_dao.to_string(),
+                stakes: stakes
+                    .iter()
+                    .map(|(addr, amount)| (addr.to_string(), *amount))
+                    .collect(),
+            },
+            &[],
+            "stake",
+            None,
+        )
+        .unwrap();
+    let funds: u128 = stakes.iter().map(|(_, amount)| amount).sum();
+    app.init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &stake, coins(funds, DENOM))
+            .unwrap()
+    });
+    stake
+}
+
+#[test]
+fn failure_callback() {
+    let (mut app, stake, automation) =
+        setup_automated_app(rejecting_dao_dao(), &[(USER1, 10_000), (USER2, 20_000)]);
+
+    let msg = MigrateBatch {
+        num: 2,
+        num_claims: 0,
+        callback: Some(CallbackData(Binary::from(b"batch".as_slice()))),
+        ..Default::default()
+    }
+    .msg();
+    let res = app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            automation.clone(),
+            &AutomationMsg::Run {
+                contract: stake.to_string(),
+                msg: to_json_binary(&msg).unwrap(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    let callback = res
+        .events
+        .iter()
+        .find(|event| {
+            event.ty == "wasm" && event.attributes.iter().any(|a| a.key == "callback_status")
+        })
+        .unwrap();
+    assert!(callback
+        .attributes
+        .iter()
+        .any(|a| a.key == "_contract_address" && a.value == automation.as_str()));
+    assert!(callback
+        .attributes
+        .contains(&Attribute::new("callback_status", "Failure")));
+    assert!(callback
+        .attributes
+        .contains(&Attribute::new("callback_failed", "1")));
+
+    // the rejected batch is back in place, funds included
+    let staked: StakedResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &stake,
+            &QueryMsg::Staked {
+                address: USER2.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(staked.stake, Uint128::new(20_000));
+    let total: TotalWeightResponse = app
+        .wrap()
+        .query_wasm_smart(&stake, &QueryMsg::TotalWeight {})
+        .unwrap();
+    assert_eq!(total.weight, 30);
+    let metrics: MigrationMetrics = app
+        .wrap()
+        .query_wasm_smart(&stake, &QueryMsg::MigrationMetrics {})
+        .unwrap();
+    assert_eq!(metrics.stakes_migrated, 0);
+    assert_eq!(metrics.total_moved, Uint128::zero());
+    assert_eq!(
+        app.wrap().query_balance(&stake, DENOM).unwrap(),
+        coin(30_000, DENOM)
+    );
+}
+
+#[test]
+fn callback_carries_destination_and_total() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+    let msg = MigrateBatch {
+        num: 1,
+        num_claims: 1,
+        callback: Some(CallbackData(Binary::from(b"batch".as_slice()))),
+        ..Default::default()
+    }
+    .msg();
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    let CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr, msg, ..
+    }) = &res.messages[0].msg
+    else {
+        panic!("unexpected message {:?}", res.messages[0].msg);
+    };
+    assert_eq!(contract_addr, ADMIN);
+    let AutomationMsg::Callback(callback) = from_json(msg).unwrap() else {
+        panic!("expected a callback");
+    };
+    assert_eq!(
+        callback.deserialize_data::<MigrationCallback>().unwrap(),
+        MigrationCallback {
+            batch: 1,
+            status: BatchStatus::Success,
+            failed: 0,
+            dao_dao: Addr::unchecked(DAO_DAO),
+            migrated_total: Uint128::new(10_500),
+        }
+    );
+}
+
+#[test]
+fn top_up_needed() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+    let top_up = |deps: Deps| -> TopUpNeededResponse {
+        from_json(query(deps, mock_env(), QueryMsg::TopUpNeeded {}).unwrap()).unwrap()
+    };
+
+    deps.querier
+        .update_balance(env.contract.address.clone(), coins(10_200, DENOM));
+    assert_eq!(
+        top_up(deps.as_ref()),
+        TopUpNeededResponse {
+            shortfall: Uint128::new(300),
+            denom: Denom::Native(DENOM.to_string()),
+            truncated: false,
+        }
+    );
+
+    // over-funded
+    deps.querier
+        .update_balance(env.contract.address, coins(11_000, DENOM));
+    assert_eq!(top_up(deps.as_ref()).shortfall, Uint128::zero());
+}
+
+#[test]
+fn auto_pause_on_shortfall() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+    deps.querier
+        .update_balance(env.contract.address.clone(), coins(10_200, DENOM));
+
+    let enable = ExecuteMsg::UpdateAutoPauseOnShortfall { enabled: true };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        enable.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), enable).unwrap();
+
+    // a sender not allowed to run the batch cannot trigger the pause
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER3, &[]),
+        migrate_msg(1, 1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // the shortfall pauses migration, and the pause outlives the call
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 1),
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "auto_paused" && attr.value == "true"));
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "shortfall" && attr.value == "300"));
+    let pause: PauseStateResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::PauseState {}).unwrap()).unwrap();
+    assert!(pause.paused);
+    assert_eq!(
+        STAKE.load(&deps.storage, &Addr::unchecked(USER1)).unwrap(),
+        Uint128::new(10_000)
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+
+    // topped up, the batch runs once migration is resumed
+    deps.querier
+        .update_balance(env.contract.address.clone(), coins(10_500, DENOM));
+    update_pause_state(deps.as_mut(), Some(false), None, None);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 1),
+    )
+    .unwrap();
+    assert_eq!(sent_migration(&res).1, coins(10_500, DENOM));
+
+    // the moved funds no longer need backing
+    deps.querier
+        .update_balance(env.contract.address, coins(0, DENOM));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 1),
+    )
+    .unwrap();
+    assert!(!res.attributes.iter().any(|attr| attr.key == "auto_paused"));
+}
+
+#[test]
+fn projected_final_balance() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+    let projected = |deps: Deps| -> Uint128 {
+        let res: ProjectedFinalBalanceResponse =
+            from_json(query(deps, mock_env(), QueryMsg::ProjectedFinalBalance {}).unwrap())
+                .unwrap();
+        res.projected
+    };
+
+    deps.querier
+        .update_balance(env.contract.address.clone(), coins(10_500, DENOM));
+    assert_eq!(projected(deps.as_ref()), Uint128::zero());
+
+    // stray funds sent straight to the contract
+    deps.querier
+        .update_balance(env.contract.address, coins(10_742, DENOM));
+    assert_eq!(projected(deps.as_ref()), Uint128::new(242));
+}
+
+#[test]
+fn claims_storage_compatibility() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER2, 300, Expiration::AtHeight(3_000));
+
+    // claims written by the controller are found by the migration
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    let (sent, funds) = sent_migration(&res);
+    assert_eq!(
+        sent,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![],
+            claims: vec![
+                (
+                    Addr::unchecked(USER1),
+                    vec![
+                        Claim::new(100, Expiration::AtHeight(1_000)),
+                        Claim::new(200, Expiration::AtHeight(2_000)),
+                    ]
+                ),
+                (
+                    Addr::unchecked(USER2),
+                    vec![Claim::new(300, Expiration::AtHeight(3_000))]
+                ),
+            ],
+        }
+    );
+    assert_eq!(funds, coins(600, DENOM));
+
+    // and removing them through the migration clears them for the controller
+    for addr in [USER1, USER2] {
+        let claims = CLAIMS
+            .query_claims(deps.as_ref(), &Addr::unchecked(addr))
+            .unwrap();
+        assert_eq!(claims.claims, vec![]);
+    }
+
+    // claims written through the raw map are read by the controller
+    let written = vec![Claim::new(400, Expiration::AtHeight(4_000))];
+    claims_storage()
+        .save(deps.as_mut().storage, Addr::unchecked(USER3), &written)
+        .unwrap();
+    let claims = CLAIMS
+        .query_claims(deps.as_ref(), &Addr::unchecked(USER3))
+        .unwrap();
+    assert_eq!(claims.claims, written);
+}
+
+#[test]
+fn contract_balance_after_attribute() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    deps.querier
+        .update_balance(env.contract.address, coins(30_500, DENOM));
+
+    let res = migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("contract_balance_after", "20000")));
+}
+
+#[test]
+fn migrated_at_attribute() {
+    let mut deps = setup();
+    let release_at = Expiration::AtTime(Timestamp::from_seconds(1_700_000_000));
+    add_claim(deps.as_mut(), USER1, 500, release_at);
+
+    let res = migrate_batch(deps.as_mut(), 0, 1).unwrap();
+    assert!(res.attributes.contains(&Attribute::new(
+        "migrated_at",
+        mock_env().block.height.to_string()
+    )));
+    // release_at goes through untouched
+    match sent_migration(&res).0 {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+            assert_eq!(claims[0].1, vec![Claim::new(500, release_at)]);
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+}
+
+#[test]
+fn claims_forwarded_per_claimant() {
+    let mut deps = setup();
+    let user1_claims = vec![
+        Claim::new(100, Expiration::AtHeight(1_000)),
+        Claim::new(
+            200,
+            Expiration::AtTime(Timestamp::from_seconds(1_700_000_000)),
+        ),
+    ];
+    for claim in &user1_claims {
+        add_claim(deps.as_mut(), USER1, claim.amount.u128(), claim.release_at);
+    }
+    add_claim(deps.as_mut(), USER2, 300, Expiration::AtHeight(2_000));
+
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    match sent_migration(&res).0 {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+            assert_eq!(
+                claims,
+                vec![
+                    (Addr::unchecked(USER1), user1_claims),
+                    (
+                        Addr::unchecked(USER2),
+                        vec![Claim::new(300, Expiration::AtHeight(2_000))]
+                    ),
+                ]
+            );
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+}
+
+/// A DAO DAO contract accepting migrations and returning a receipt token.
+fn minting_dao_dao() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        |_: DepsMut,
+         _: Env,
+         _: MessageInfo,
+         _: dao_voting_token_staked::msg::ExecuteMsg|
+         -> StdResult<Response> {
+            Ok(Response::new().set_data(Binary::from(b"factory/dao/receipt".as_slice())))
+        },
+        |_: DepsMut, _: Env, _: MessageInfo, _: Empty| -> StdResult<Response> {
+            Ok(Response::new())
+        },
+        |_: Deps, _: Env, _: Empty| -> StdResult<Binary> { unimplemented!() },
+    ))
+}
+
+#[test]
+fn last_reply_data() {
+    let (mut app, stake) = setup_app(minting_dao_dao(), &[(USER1, 10_000)]);
+    let last: Option<LastReplyData> = app
+        .wrap()
+        .query_wasm_smart(&stake, &QueryMsg::LastReplyData {})
+        .unwrap();
+    assert_eq!(last, None);
+
+    app.execute_contract(
+        Addr::unchecked(ADMIN),
+        stake.clone(),
+        &migrate_msg(1, 0),
+        &[],
+    )
+    .unwrap();
+
+    let last: Option<LastReplyData> = app
+        .wrap()
+        .query_wasm_smart(&stake, &QueryMsg::LastReplyData {})
+        .unwrap();
+    assert_eq!(
+        last,
+        Some(LastReplyData {
+            batch: 1,
+            data: Some(Binary::from(b"factory/dao/receipt".as_slice())),
+        })
+    );
+}
+
+#[test]
+fn members_at() {
+    let mut deps = setup();
+    let mut env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    let before = env.block.height + 1;
+
+    env.block.height += 10;
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        migrate_msg(1, 0),
+    )
+    .unwrap();
+
+    let members_at = |deps: Deps, addrs: Vec<String>, height| {
+        query(deps, mock_env(), QueryMsg::MembersAt { addrs, height })
+            .map(|bin| from_json::<MembersAtResponse>(bin).unwrap().members)
+    };
+    let addrs = vec![USER1.to_string(), USER2.to_string(), USER3.to_string()];
+    assert_eq!(
+        members_at(deps.as_ref(), addrs.clone(), before).unwrap(),
+        vec![
+            (USER1.to_string(), Some(10)),
+            (USER2.to_string(), Some(20)),
+            (USER3.to_string(), None),
+        ]
+    );
+    assert_eq!(
+        members_at(deps.as_ref(), addrs, env.block.height + 1).unwrap(),
+        vec![
+            (USER1.to_string(), None),
+            (USER2.to_string(), Some(20)),
+            (USER3.to_string(), Some(30)),
+        ]
+    );
+
+    let too_many = (0..31).map(|i| format!("addr{i}")).collect();
+    members_at(deps.as_ref(), too_many, before).unwrap_err();
+}
+
+#[test]
+fn build_transfer_per_denom() {
+    let to = Addr::unchecked(DAO_DAO);
+    let msg = to_json_binary(&"payload").unwrap();
+
+    let native = build_transfer(
+        &Denom::Native(DENOM.to_string()),
+        Uint128::new(1_000),
+        &to,
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        native,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: DAO_DAO.to_string(),
+            msg: msg.clone(),
+            funds: coins(1_000, DENOM),
+        })
+    );
+
+    let cw20 = build_transfer(
+        &Denom::Cw20(Addr::unchecked("cw20_token")),
+        Uint128::new(1_000),
+        &to,
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        cw20,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "cw20_token".to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: DAO_DAO.to_string(),
+                amount: Uint128::new(1_000),
+                msg,
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn migrate_cw20_stakes() {
+    let mut deps = setup_with_denom(Denom::Cw20(Addr::unchecked("cw20_token")));
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == "cw20_token" => {
+            let balance = cw20::BalanceResponse {
+                balance: Uint128::new(10_000),
+            };
+            SystemResult::Ok(ContractResult::Ok(to_json_binary(&balance).unwrap()))
+        }
+        WasmQuery::Raw { .. } => SystemResult::Ok(ContractResult::Ok(Binary::default())),
+        _ => unimplemented!(),
+    });
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("denom_type", "cw20")));
+    let CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr,
+        msg,
+        funds,
+    }) = &res.messages[0].msg
+    else {
+        panic!("unexpected message {:?}", res.messages[0].msg);
+    };
+    assert_eq!(contract_addr, "cw20_token");
+    assert_eq!(funds, &vec![]);
+    let Cw20ExecuteMsg::Send {
+        contract,
+        amount,
+        msg,
+    } = from_json(msg).unwrap()
+    else {
+        panic!("expected a cw20 send");
+    };
+    assert_eq!(contract, DAO_DAO);
+    assert_eq!(amount, Uint128::new(10_000));
+    assert_eq!(
+        from_json::<dao_voting_token_staked::msg::ExecuteMsg>(&msg).unwrap(),
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER1), Uint128::new(10_000))],
+            claims: vec![],
+        }
+    );
+
+    // nothing left to move, so no zero cw20 send
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(res.messages, vec![]);
+}
+
+#[test]
+fn zero_sum_batch_sends_nothing() {
+    let mut deps = setup();
+    let batch = |callback: Option<CallbackData>| {
+        MigrateBatch {
+            num: 1,
+            num_claims: 1,
+            verify: Some(true),
+            callback,
+            confirm: Some(true),
+            ..Default::default()
+        }
+        .msg()
+    };
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        batch(None),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![]);
+    assert!(!PENDING_VERIFICATION.exists(&deps.storage));
+    assert!(!PENDING_CONFIRMATION.exists(&deps.storage));
+
+    // with no reply coming, the callback goes out with the batch
+    let callback = CallbackData(Binary::from(b"batch".as_slice()));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        batch(Some(callback.clone())),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(
+            callback
+                .to_message(
+                    &Addr::unchecked(ADMIN),
+                    MigrationCallback {
+                        batch: 2,
+                        status: BatchStatus::Success,
+                        failed: 0,
+                        dao_dao: Addr::unchecked(DAO_DAO),
+                        migrated_total: Uint128::zero(),
+                    },
+                    vec![],
+                )
+                .unwrap()
+        )]
+    );
+    assert!(!PENDING_BATCH.exists(&deps.storage));
+}
+
+#[test]
+fn instantiate_reports_min_bond_normalization() {
+    let mut deps = mock_dependencies();
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[]),
+        instantiate_msg(Denom::Native(DENOM.to_string())),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![Attribute::new("min_bond_normalized", "false")]
+    );
+
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        min_bond: Uint128::zero(),
+        ..instantiate_msg(Denom::Native(DENOM.to_string()))
+    };
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![Attribute::new("min_bond_normalized", "true")]
+    );
+    let config = CONFIG.load(&deps.storage).unwrap();
+    assert_eq!(config.min_bond, Uint128::new(1));
+}
+
+#[test]
+fn instantiate_params() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        min_bond: Uint128::zero(),
+        ..instantiate_msg(Denom::Native(DENOM.to_string()))
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+    let res: InstantiateParamsResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::InstantiateParams {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        InstantiateParamsResponse {
+            config: Config {
+                denom: Denom::Native(DENOM.to_string()),
+                tokens_per_weight: TOKENS_PER_WEIGHT,
+                min_bond: Uint128::new(1),
+                unbonding_period: Duration::Height(100),
+            },
+            admin: Some(Addr::unchecked(ADMIN)),
+            min_bond_weight: 0,
+        }
+    );
+}
+
+#[test]
+fn claim_denoms() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+
+    let res: ClaimDenomsResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::ClaimDenoms {}).unwrap()).unwrap();
+    assert_eq!(res.denoms, vec![Denom::Native(DENOM.to_string())]);
+}
+
+#[test]
+fn migrate_until_gas() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    let mut migrate_with_gas = |until_gas| {
+        let msg = MigrateBatch {
+            num: 3,
+            num_claims: 0,
+            until_gas: Some(until_gas),
+            ..Default::default()
+        }
+        .msg();
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg)
+    };
+    let migrated = |res: Response| match sent_migration(&res).0 {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { weights, .. } => weights.len(),
+        msg => panic!("unexpected message {msg:?}"),
+    };
+
+    // too little for a single stake
+    let err = migrate_with_gas(1_000).unwrap_err();
+    assert_eq!(err, ContractError::EmptyBatch {});
+
+    // enough for one stake only
+    assert_eq!(migrated(migrate_with_gas(30_000).unwrap()), 1);
+
+    // a larger budget takes the other two
+    assert_eq!(migrated(migrate_with_gas(1_000_000).unwrap()), 2);
+}
+
+#[test]
+fn contract_info() {
+    let deps = setup();
+    let env = mock_env();
+    let res: ContractIdentityResponse =
+        from_json(query(deps.as_ref(), env.clone(), QueryMsg::ContractInfo {}).unwrap()).unwrap();
+    assert_eq!(res.address, env.contract.address);
+    assert_eq!(res.code_version.contract, "crates.io:cw4-stake");
+    assert_eq!(res.code_version.version, env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn migration_status_remaining() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    let status = |deps: Deps| -> MigrationStatusResponse {
+        from_json(query(deps, mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap()
+    };
+    let remaining = |status: MigrationStatusResponse| {
+        (
+            status.remaining_stakes,
+            status.remaining_claims,
+            status.total_weight,
+            status.remaining_amount.u128(),
+            status.truncated,
+        )
+    };
+    assert_eq!(remaining(status(deps.as_ref())), (3, 1, 60, 60_500, false));
+
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    let res = status(deps.as_ref());
+    assert!(res.claims_complete);
+    assert!(!res.stakes_complete);
+    assert_eq!(remaining(res), (2, 0, 50, 50_000, false));
+
+    migrate_batch(deps.as_mut(), 2, 0).unwrap();
+    let res = status(deps.as_ref());
+    assert!(res.complete);
+    assert_eq!(remaining(res), (0, 0, 0, 0, false));
+}
+
+#[test]
+fn claims_cursor() {
+    let mut deps = setup();
+    for user in [USER1, USER2, USER3] {
+        add_claim(deps.as_mut(), user, 100, Expiration::AtHeight(1_000));
+    }
+    let status = |deps: Deps| -> MigrationStatusResponse {
+        from_json(query(deps, mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap()
+    };
+    let claim_addrs = |res: Response| -> Vec<Addr> {
+        match sent_migration(&res).0 {
+            dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+                claims.into_iter().map(|(addr, _)| addr).collect()
+            }
+            msg => panic!("unexpected message {msg:?}"),
+        }
+    };
+    assert_eq!(status(deps.as_ref()).claims_cursor, None);
+
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    assert_eq!(
+        claim_addrs(res),
+        vec![Addr::unchecked(USER1), Addr::unchecked(USER2)]
+    );
+    assert_eq!(
+        status(deps.as_ref()).claims_cursor,
+        Some(Addr::unchecked(USER2))
+    );
+
+    // a claim behind the cursor waits for the next pass
+    add_claim(deps.as_mut(), USER1, 50, Expiration::AtHeight(2_000));
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    assert_eq!(claim_addrs(res), vec![Addr::unchecked(USER3)]);
+    assert_eq!(status(deps.as_ref()).claims_cursor, None);
+
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    assert_eq!(claim_addrs(res), vec![Addr::unchecked(USER1)]);
+}
+
+#[test]
+fn stake_cursor() {
+    let mut deps = setup();
+    let env = mock_env();
+    // more light stakes than a batch reads, sorted before the heavy one
+    for i in 0..1_000 {
+        stake(deps.as_mut(), &env, &format!("staker{i:04}"), 5_000);
+    }
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    let heavy = || {
+        MigrateBatch {
+            num: 5,
+            min_weight: Some(10),
+            ..Default::default()
+        }
+        .msg()
+    };
+
+    // the batch stops at the read cap, short of the heavy stake
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), heavy()).unwrap();
+    assert_eq!(res.messages, vec![]);
+    assert!(STAKE.has(&deps.storage, &Addr::unchecked(USER1)));
+    assert_eq!(
+        STAKE_CURSOR.load(&deps.storage).unwrap(),
+        Some(Addr::unchecked("staker0999"))
+    );
+
+    // and the next one resumes after it
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), heavy()).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(10_000, DENOM));
+    assert!(!STAKE.has(&deps.storage, &Addr::unchecked(USER1)));
+    assert_eq!(STAKE_CURSOR.load(&deps.storage).unwrap(), None);
+}
+
+#[test]
+fn migration_proxy() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    stake(deps.as_mut(), &mock_env(), USER2, 20_000);
+    let update = |proxy: Option<&str>| ExecuteMsg::UpdateMigrationProxy {
+        proxy: proxy.map(str::to_string),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        update(Some("adapter")),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update(Some("adapter")),
+    )
+    .unwrap();
+
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "adapter".to_string(),
+            msg: to_json_binary(&dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+                weights: vec![(Addr::unchecked(USER1), Uint128::new(10_000))],
+                claims: vec![],
+            })
+            .unwrap(),
+            funds: coins(10_000, DENOM),
+        })
+    );
+    assert!(res.attributes.contains(&attr("proxy", "adapter")));
+    assert!(res.attributes.contains(&attr("destination", DAO_DAO)));
+
+    // cleared, batches go to DAO DAO again
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update(None),
+    )
+    .unwrap();
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    sent_migration(&res);
+}
+
+#[test]
+fn would_exceed_message_limit() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    for i in 0..50 {
+        add_claim(deps.as_mut(), USER2, 100, Expiration::AtHeight(1_000 + i));
+    }
+    let check = |deps: Deps, num: u64, num_claims: u64| -> MessageLimitResponse {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::WouldExceedMessageLimit {
+                    num,
+                    num_claims,
+                    limit_bytes: 1_000,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let payload = to_json_binary(&dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+        weights: vec![(Addr::unchecked(USER1), Uint128::new(10_000))],
+        claims: vec![],
+    })
+    .unwrap();
+    assert_eq!(
+        check(deps.as_ref(), 1, 0),
+        MessageLimitResponse {
+            size_bytes: payload.len() as u64,
+            exceeds: false,
+        }
+    );
+
+    let res = check(deps.as_ref(), 0, 1);
+    assert!(res.exceeds);
+    assert!(res.size_bytes > 1_000);
+}
+
+#[test]
+fn batch_monitor() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    let update = ExecuteMsg::UpdateMonitor {
+        monitor: Some("monitor".to_string()),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        update.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+
+    let res = migrate_batch(deps.as_mut(), 2, 1).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_on_error(
+            WasmMsg::Execute {
+                contract_addr: "monitor".to_string(),
+                msg: to_json_binary(&MonitorExecuteMsg::BatchNotification {
+                    index: 1,
+                    stake_count: 2,
+                    claim_count: 1,
+                    remaining: 30,
+                })
+                .unwrap(),
+                funds: vec![],
+            },
+            2,
+        )
+    );
+
+    // a failing monitor leaves the batch in place
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 2,
+            result: SubMsgResult::Err("out of gas".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.attributes, vec![attr("monitor_failed", "out of gas")]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateMonitor { monitor: None },
+    )
+    .unwrap();
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(res.messages.len(), 1);
+}
+
+#[test]
+fn funds_recipient_for_strict_destination() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+    let update = ExecuteMsg::UpdateFundsRecipient {
+        recipient: Some("vault".to_string()),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        update.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+
+    let next: NextBatchMessageResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::NextBatchMessage {
+                num: 1,
+                num_claims: 1,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let res = migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    let sent: Vec<CosmosMsg> = res.messages.into_iter().map(|sub| sub.msg).collect();
+    assert_eq!(next.messages, sent);
+    assert_eq!(
+        sent[0],
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "vault".to_string(),
+            amount: coins(10_500, DENOM),
+        })
+    );
+    match &sent[1] {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            funds,
+            ..
+        }) => {
+            assert_eq!(contract_addr, DAO_DAO);
+            assert_eq!(funds, &vec![]);
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+
+    // unset, funds travel with the messages again
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateFundsRecipient { recipient: None },
+    )
+    .unwrap();
+    stake(deps.as_mut(), &mock_env(), USER3, 20_000);
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(20_000, DENOM));
+}
+
+#[test]
+fn next_batch_message_matches_batch() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER3, 200, Expiration::AtHeight(2_000));
+
+    let next: NextBatchMessageResponse = from_json(
+        query(
+            deps.as_ref(),
+            env,
+            QueryMsg::NextBatchMessage {
+                num: 1,
+                num_claims: 2,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let res = migrate_batch(deps.as_mut(), 1, 2).unwrap();
+    let sent: Vec<CosmosMsg> = res.messages.into_iter().map(|sub| sub.msg).collect();
+    assert_eq!(next.messages, sent);
+    assert_eq!(sent.len(), 1);
+}
+
+#[test]
+fn next_claim_batch_sum() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER1, 150, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER2, 200, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER3, 400, Expiration::AtHeight(1_000));
+    let sum = |deps: Deps| -> Uint128 {
+        from_json::<NextClaimBatchSumResponse>(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::NextClaimBatchSum { num_claims: 2 },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .sum
+    };
+
+    // stakes are left out
+    assert_eq!(sum(deps.as_ref()), Uint128::new(450));
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    assert_eq!(sent_migration(&res).1, coins(450, DENOM));
+    // the next batch resumes after the cursor
+    assert_eq!(sum(deps.as_ref()), Uint128::new(400));
+}
+
+#[test]
+fn simulate_migration_matches_batch() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER3, 200, Expiration::AtHeight(2_000));
+
+    let simulated: SimulatedBatch = from_json(
+        query(
+            deps.as_ref(),
+            env,
+            QueryMsg::SimulateMigration {
+                num: 2,
+                num_claims: 1,
+                protect_admin: None,
+                min_weight: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        simulated,
+        SimulatedBatch {
+            stakes: vec![(Addr::unchecked(USER1), 10), (Addr::unchecked(USER2), 20),],
+            claims: vec![(
+                Addr::unchecked(USER1),
+                vec![Claim::new(100, Expiration::AtHeight(1_000))]
+            )],
+            stake_sum: Uint128::new(30_000),
+            claim_sum: Uint128::new(100),
+            total_sum: Uint128::new(30_100),
+            next_stake_cursor: Some(Addr::unchecked(USER2)),
+            next_claim_cursor: Some(Addr::unchecked(USER1)),
+        }
+    );
+
+    let res = migrate_batch(deps.as_mut(), 2, 1).unwrap();
+    let amount = res.attributes.iter().find(|a| a.key == "amount").unwrap();
+    assert_eq!(amount.value, format!("{} {DENOM}", simulated.total_sum));
+    let status: MigrationStatusResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap();
+    assert_eq!(status.claims_cursor, simulated.next_claim_cursor);
+}
+
+#[test]
+fn simulate_migration_capped() {
+    let mut deps = setup();
+    for i in 0..1_001 {
+        add_claim(
+            deps.as_mut(),
+            &format!("claimer{i:04}"),
+            100,
+            Expiration::AtHeight(1_000),
+        );
+    }
+
+    let simulated: SimulatedBatch = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SimulateMigration {
+                num: u64::MAX,
+                num_claims: u64::MAX,
+                protect_admin: None,
+                min_weight: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(simulated.claims.len(), 1_000);
+    assert_eq!(
+        simulated.next_claim_cursor,
+        Some(Addr::unchecked("claimer0999"))
+    );
+    let sum: NextClaimBatchSumResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::NextClaimBatchSum {
+                num_claims: u64::MAX,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(sum.sum, Uint128::new(100_000));
+}
+
+#[test]
+fn source_migration_complete() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    let complete = |deps: Deps| -> bool {
+        from_json::<SourceMigrationCompleteResponse>(
+            query(deps, mock_env(), QueryMsg::SourceMigrationComplete {}).unwrap(),
+        )
+        .unwrap()
+        .complete
+    };
+
+    assert!(!complete(deps.as_ref()));
+    migrate_batch(deps.as_mut(), 2, 0).unwrap();
+    assert!(!complete(deps.as_ref()));
+    migrate_batch(deps.as_mut(), 0, 1).unwrap();
+    assert!(complete(deps.as_ref()));
+    // the shape integrators rely on
+    assert_eq!(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SourceMigrationComplete {}
+        )
+        .unwrap(),
+        Binary::from(br#"{"complete":true}"#)
+    );
+
+    // a dust stake under the completion threshold is still left
+    let mut deps = setup();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 2_000);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateCompletionThreshold { threshold: Some(5) },
+    )
+    .unwrap();
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    let status: MigrationStatusResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap();
+    assert!(status.complete);
+    assert!(!complete(deps.as_ref()));
+}
+
+#[test]
+fn migration_status_by_phase() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 100, Expiration::AtHeight(1_000));
+    let status = |deps: Deps| -> MigrationStatusResponse {
+        from_json(query(deps, mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap()
+    };
+
+    let res = status(deps.as_ref());
+    assert!(!res.stakes_complete && !res.claims_complete && !res.complete);
+
+    migrate_batch(deps.as_mut(), 0, 1).unwrap();
+    let res = status(deps.as_ref());
+    assert!(res.claims_complete);
+    assert!(!res.stakes_complete);
+    assert!(!res.complete);
+
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert!(status(deps.as_ref()).complete);
+}
+
+#[test]
+fn claims_exhausted_attribute() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    for user in [USER2, USER3] {
+        add_claim(deps.as_mut(), user, 100, Expiration::AtHeight(1_000));
+    }
+    let exhausted = |res: &Response| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == "claims_exhausted")
+            .map(|attr| attr.value.clone())
+    };
+
+    let res = migrate_batch(deps.as_mut(), 0, 1).unwrap();
+    assert_eq!(exhausted(&res), Some("false".to_string()));
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    assert_eq!(exhausted(&res), Some("true".to_string()));
+    // stakes only batches don't scan claims
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(exhausted(&res), None);
+}
+
+#[test]
+fn invariants_hold() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+    deps.querier
+        .update_balance(env.contract.address.clone(), coins(10_500, DENOM));
+    let invariants = |deps: &MockDeps| -> InvariantsHoldResponse {
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::InvariantsHold {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        invariants(&deps),
+        InvariantsHoldResponse {
+            total_matches_weights: true,
+            balance_backs_liabilities: true,
+            no_orphans: true,
+            all_ok: true,
+            truncated: false,
+        }
+    );
+
+    // a member without a stake
+    MEMBERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked(USER3),
+            &0,
+            env.block.height,
+        )
+        .unwrap();
+    let res = invariants(&deps);
+    assert!(!res.no_orphans && !res.all_ok);
+    assert!(res.total_matches_weights && res.balance_backs_liabilities);
+
+    // drifted total and a shortfall
+    let mut deps = setup();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    TOTAL
+        .save(deps.as_mut().storage, &11, env.block.height)
+        .unwrap();
+    deps.querier
+        .update_balance(env.contract.address, coins(9_999, DENOM));
+    let res = invariants(&deps);
+    assert!(!res.total_matches_weights && !res.balance_backs_liabilities && !res.all_ok);
+    assert!(res.no_orphans);
+}
+
+#[test]
+fn average_batch_size() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..6 {
+        stake(deps.as_mut(), &env, &format!("staker{i}"), 10_000);
+    }
+    for user in [USER1, USER2] {
+        add_claim(deps.as_mut(), user, 100, Expiration::AtHeight(1_000));
+    }
+    let average = |deps: &MockDeps| -> AverageBatchSizeResponse {
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::AverageBatchSize {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        average(&deps),
+        AverageBatchSizeResponse {
+            avg_stakes: Decimal::zero(),
+            avg_claims: Decimal::zero(),
+        }
+    );
+
+    migrate_batch(deps.as_mut(), 3, 0).unwrap();
+    migrate_batch(deps.as_mut(), 2, 1).unwrap();
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    assert_eq!(
+        average(&deps),
+        AverageBatchSizeResponse {
+            avg_stakes: Decimal::from_ratio(6u64, 3u64),
+            avg_claims: Decimal::from_ratio(2u64, 3u64),
+        }
+    );
+}
+
+#[test]
+fn migration_breakdown() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+
+    let res: MigrationBreakdownResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::MigrationBreakdown {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res,
+        MigrationBreakdownResponse {
+            migrated_amount: Uint128::new(10_500),
+            remaining_amount: Uint128::new(20_000),
+            total_amount: Uint128::new(30_500),
+            truncated: false,
+        }
+    );
+}
+
+#[test]
+fn debug_dump() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER2, 500, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER3, 700, Expiration::AtHeight(1_000));
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateCompletionThreshold { threshold: Some(5) },
+    )
+    .unwrap();
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+
+    let dump: DebugDumpResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::DebugDump {}).unwrap()).unwrap();
+    assert_eq!(dump.config.denom, Denom::Native(DENOM.to_string()));
+    assert_eq!(dump.admin, Some(Addr::unchecked(ADMIN)));
+    assert_eq!(dump.dao_dao, Some(Addr::unchecked(DAO_DAO)));
+    assert_eq!(dump.total_weight, 20);
+    assert!(dump.pause.paused);
+    assert!(!dump.sealed);
+    assert!(!dump.aborted);
+    assert_eq!(dump.completion_threshold, Some(5));
+    assert_eq!(dump.treasury, None);
+    assert_eq!(dump.metrics.batches_run, 1);
+    assert_eq!(dump.metrics.total_moved, Uint128::new(10_500));
+    assert_eq!(dump.claims_cursor, Some(Addr::unchecked(USER2)));
+    assert_eq!(dump.last_batch.unwrap().stake_count, 1);
+    assert!(!dump.has_prepared_batch);
+    assert!(!dump.has_pending_batch);
+}
+
+#[test]
+fn forward_exceeds_backing() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+
+    // a stake appearing without any tokens backing it
+    STAKE
+        .update(deps.as_mut().storage, &Addr::unchecked(USER2), |stake| {
+            StdResult::Ok(stake.unwrap() + Uint128::new(5_000))
+        })
+        .unwrap();
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ForwardExceedsBacking {
+            forwarded: Uint128::new(35_000),
+            backing: Uint128::new(30_000),
+        }
+    );
+
+    // claims imported during migration are backed
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::ImportClaims {
+            entries: vec![(
+                USER3.to_string(),
+                vec![Claim::new(5_000, Expiration::AtHeight(1_000))],
+            )],
+            denom: Denom::Native(DENOM.to_string()),
+            overwrite: false,
+        },
+    )
+    .unwrap();
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+}
+
+#[test]
+fn forwarding_delta() {
+    let mut deps = setup_unprepared(Denom::Native(DENOM.to_string()));
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    prepare_snapshot(deps.as_mut(), 100).unwrap();
+    let delta = |deps: Deps| -> ForwardingDeltaResponse {
+        from_json(query(deps, mock_env(), QueryMsg::ForwardingDelta {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        delta(deps.as_ref()),
+        ForwardingDeltaResponse {
+            initial_total: 60,
+            forwarded_weight: 0,
+            remaining_total: 60,
+            consistent: true,
+        }
+    );
+
+    migrate_batch(deps.as_mut(), 2, 0).unwrap();
+    assert_eq!(
+        delta(deps.as_ref()),
+        ForwardingDeltaResponse {
+            initial_total: 60,
+            forwarded_weight: 30,
+            remaining_total: 30,
+            consistent: true,
+        }
+    );
+
+    // an override of the total is reported as drift
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::SetTotal { weight: 25 },
+    )
+    .unwrap();
+    assert!(!delta(deps.as_ref()).consistent);
+}
+
+#[test]
+fn completion_threshold() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    // dust, weight 5
+    stake(deps.as_mut(), &env, USER3, 5_000);
+    let status = |deps: Deps| -> MigrationStatusResponse {
+        from_json(query(deps, mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap()
+    };
+
+    let update = ExecuteMsg::UpdateCompletionThreshold { threshold: Some(6) };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        update.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert!(!res.events.iter().any(|e| e.ty == "migration_complete"));
+    assert!(!status(deps.as_ref()).complete);
+
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(
+        res.events
+            .iter()
+            .find(|e| e.ty == "migration_complete")
+            .unwrap(),
+        &Event::new("migration_complete").add_attribute("remaining_total", "5")
+    );
+    let res = status(deps.as_ref());
+    assert!(res.complete);
+    assert!(!res.stakes_complete);
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 5);
+}
+
+#[test]
+fn set_total() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    let msg = ExecuteMsg::SetTotal { weight: 7 };
+
+    // only while paused
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NotPaused {});
+
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("total_overridden")
+            .add_attribute("previous", "10")
+            .add_attribute("total", "7")
+            .add_attribute("sender", ADMIN)]
+    );
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 7);
+}
+
+#[test]
+fn update_config() {
+    let mut deps = setup();
+    let update = |tokens_per_weight: u128, min_bond: u128| ExecuteMsg::UpdateConfig {
+        denom: None,
+        tokens_per_weight: Some(Uint128::new(tokens_per_weight)),
+        min_bond: Some(Uint128::new(min_bond)),
+        unbonding_period: None,
+    };
+    let before = CONFIG.load(&deps.storage).unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update(500, 1_000),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NotPaused {});
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        update(500, 1_000),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    for (msg, expected) in [
+        (update(0, 1_000), ContractError::ZeroTokensPerWeight {}),
+        (update(500, 0), ContractError::ZeroMinBond {}),
+    ] {
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert_eq!(err, expected);
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update(500, 1_000),
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("tokens_per_weight", "500")));
+    assert!(res.attributes.contains(&Attribute::new("min_bond", "1000")));
+    assert!(!res.attributes.iter().any(|attr| attr.key == "denom"));
+    assert_eq!(
+        CONFIG.load(&deps.storage).unwrap(),
+        Config {
+            tokens_per_weight: Uint128::new(500),
+            min_bond: Uint128::new(1_000),
+            ..before
+        }
+    );
+
+    // weights are not re-indexed, so they can't change under held stakes
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update(1_000, 1_000),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::StakesHeld {});
+}
+
+#[test]
+fn update_config_denom() {
+    let mut deps = setup();
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    let update = |denom: Denom| ExecuteMsg::UpdateConfig {
+        denom: Some(denom),
+        tokens_per_weight: None,
+        min_bond: None,
+        unbonding_period: None,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update(Denom::Cw20(Addr::unchecked("Invalid"))),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Std(_)));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update(Denom::Native("uother".to_string())),
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&Attribute::new("denom", "uother")));
+
+    // held stakes or claims keep the denom that backs them
+    for seeded in [true, false] {
+        let mut deps = setup();
+        update_pause_state(deps.as_mut(), Some(true), None, None);
+        if seeded {
+            stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+        } else {
+            add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+        }
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            update(Denom::Native("uother".to_string())),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::StakingFundsHeld {});
+        assert_eq!(
+            CONFIG.load(&deps.storage).unwrap().denom,
+            Denom::Native(DENOM.to_string())
+        );
+    }
+}
+
+#[test]
+fn adjust_stake() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    stake(deps.as_mut(), &mock_env(), USER2, 20_000);
+    let adjust = |new_stake: u128| ExecuteMsg::AdjustStake {
+        addr: USER1.to_string(),
+        new_stake: Uint128::new(new_stake),
+    };
+
+    // only while paused
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        adjust(15_000),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NotPaused {});
+
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        adjust(15_000),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    let user1 = Addr::unchecked(USER1);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        adjust(15_000),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("stake_overridden")
+            .add_attribute("addr", USER1)
+            .add_attribute("previous_stake", "10000")
+            .add_attribute("stake", "15000")
+            .add_attribute("previous_weight", "10")
+            .add_attribute("weight", "15")
+            .add_attribute("sender", ADMIN)]
+    );
+    assert_eq!(
+        STAKE.load(&deps.storage, &user1).unwrap(),
+        Uint128::new(15_000)
+    );
+    assert_eq!(MEMBERS.load(&deps.storage, &user1).unwrap(), 15);
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 35);
+
+    // down below min_bond drops the membership
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        adjust(4_000),
+    )
+    .unwrap();
+    assert_eq!(
+        STAKE.load(&deps.storage, &user1).unwrap(),
+        Uint128::new(4_000)
+    );
+    assert_eq!(MEMBERS.may_load(&deps.storage, &user1).unwrap(), None);
+    assert_eq!(TOTAL.load(&deps.storage).unwrap(), 20);
+}
+
+#[test]
+fn same_block_adjust_then_migrate() {
+    let mut deps = setup();
+    let mut env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    env.block.height += 10;
+    let height = env.block.height;
+
+    // adjusted and migrated within the same block
+    update_pause_state(deps.as_mut(), Some(true), None, None);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::AdjustStake {
+            addr: USER1.to_string(),
+            new_stake: Uint128::new(15_000),
+        },
+    )
+    .unwrap();
+    update_pause_state(deps.as_mut(), Some(false), None, None);
+    let res = execute(deps.as_mut(), env, mock_info(ADMIN, &[]), migrate_msg(1, 0)).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(15_000, DENOM));
+
+    // the height reads as of the start of the block, before both writes,
+    // and the removal is what the next block sees
+    let member = |deps: Deps, at_height: Option<u64>| -> Option<u64> {
+        let res: MemberResponse = from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::Member {
+                    addr: USER1.to_string(),
+                    at_height,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.weight
+    };
+    assert_eq!(member(deps.as_ref(), Some(height)), Some(10));
+    assert_eq!(member(deps.as_ref(), Some(height + 1)), None);
+    assert_eq!(member(deps.as_ref(), None), None);
+    let total_at = |deps: Deps, height: u64| -> u64 {
+        let res: TotalWeightResponse =
+            from_json(query(deps, mock_env(), QueryMsg::TotalWeightAt { height }).unwrap())
+                .unwrap();
+        res.weight
+    };
+    assert_eq!(total_at(deps.as_ref(), height), 30);
+    assert_eq!(total_at(deps.as_ref(), height + 1), 20);
+
+    // one changelog entry per height, however many writes it had
+    let res: MemberChangeCountResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::MemberChangeCount {
+                address: USER1.to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.count, 2);
+}
+
+#[test]
+fn all_balances() {
+    let mut deps = setup();
+    let held = vec![coin(1_000, DENOM), coin(5, "uairdrop"), coin(42, "ufee")];
+    deps.querier
+        .update_balance(mock_env().contract.address, held.clone());
+
+    let res: Vec<Coin> =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::AllBalances {}).unwrap()).unwrap();
+    assert_eq!(res, held);
+}
+
+#[test]
+fn finalize_residual() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(10_005, DENOM));
+    let finalize = ExecuteMsg::FinalizeResidual {
+        to: "treasury".to_string(),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        finalize.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MigrationNotComplete {});
+
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    // the batch has settled, leaving the residual behind
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(5, DENOM));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        finalize.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), finalize).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(5, DENOM),
+        })]
+    );
+}
+
+#[test]
+fn fund() {
+    let mut deps = setup();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &coins(100, DENOM)),
+        ExecuteMsg::Fund {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &coins(100, DENOM)),
+        ExecuteMsg::Fund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "fund"),
+            attr("funded", format!("100 {DENOM}")),
+            attr("sender", ADMIN),
+        ]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &coins(100, "uother")),
+        ExecuteMsg::Fund {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MissingDenom(DENOM.to_string()));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Fund {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoFunds {});
+}
+
+#[test]
+fn seal() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    deps.querier
+        .update_balance(mock_env().contract.address, coins(10_000, DENOM));
+    let sealed = |deps: &MockDeps| -> bool {
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Sealed {}).unwrap()).unwrap()
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Seal {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MigrationNotComplete {});
+
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Seal {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::BalanceNotEmpty {
+            balance: Uint128::new(10_000)
+        }
+    );
+
+    // the batch paid out the balance
+    deps.querier
+        .update_balance(mock_env().contract.address, vec![]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        ExecuteMsg::Seal {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+    assert!(!sealed(&deps));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::Seal {},
+    )
+    .unwrap();
+    assert!(sealed(&deps));
+
+    for msg in [
+        ExecuteMsg::Seal {},
+        ExecuteMsg::UpdateTreasury { treasury: None },
+        ExecuteMsg::AbortMigration {},
+    ] {
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Sealed {});
+    }
+}
+
+#[test]
+fn sweep_foreign_denom() {
+    let mut deps = setup();
+    deps.querier.update_balance(
+        mock_env().contract.address,
+        vec![coin(1_000, DENOM), coin(5, "uairdrop")],
+    );
+    let sweep = |denom: &str| ExecuteMsg::SweepForeignDenom {
+        denom: denom.to_string(),
+        to: "treasury".to_string(),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        sweep("uairdrop"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Admin(AdminError::NotAdmin {}));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        sweep(DENOM),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::CannotSweepStakingDenom {
+            denom: DENOM.to_string()
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        sweep("uairdrop"),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(5, "uairdrop"),
+        })]
+    );
+}
+
+#[test]
+fn batch_feedback() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..10 {
+        stake(deps.as_mut(), &env, &format!("user{i}"), 10_000);
+        add_claim(
+            deps.as_mut(),
+            &format!("user{i}"),
+            100,
+            Expiration::AtHeight(1_000),
+        );
+    }
+    let feedback = |res: Response| -> BatchFeedback { from_json(res.data.unwrap()).unwrap() };
+
+    // far below the default target of 100 entries
+    let res = migrate_batch(deps.as_mut(), 2, 2).unwrap();
+    assert_eq!(
+        feedback(res),
+        BatchFeedback {
+            ops: 4,
+            target_ops: 100,
+            next_num: 50,
+            next_num_claims: 50,
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateTargetOps {
+            target_ops: Some(3),
+        },
+    )
+    .unwrap();
+    let res = migrate_batch(deps.as_mut(), 4, 2).unwrap();
+    assert_eq!(
+        feedback(res),
+        BatchFeedback {
+            ops: 6,
+            target_ops: 3,
+            next_num: 2,
+            next_num_claims: 1,
+        }
+    );
+}
+
+#[test]
+fn claim_maturity_schedule() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 300, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER3, 400, Expiration::AtHeight(1_000));
+    let schedule = |deps: Deps, limit| -> ClaimMaturityScheduleResponse {
+        from_json(query(deps, mock_env(), QueryMsg::ClaimMaturitySchedule { limit }).unwrap())
+            .unwrap()
+    };
+
+    assert_eq!(
+        schedule(deps.as_ref(), 10),
+        ClaimMaturityScheduleResponse {
+            schedule: vec![
+                (Expiration::AtHeight(1_000), Uint128::new(600)),
+                (Expiration::AtHeight(2_000), Uint128::new(400)),
+            ],
+            truncated: false,
+        }
+    );
+    assert_eq!(
+        schedule(deps.as_ref(), 1).schedule,
+        vec![(Expiration::AtHeight(1_000), Uint128::new(600))]
+    );
+}
+
+#[test]
+fn prepare_and_commit_batch() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+
+    let prepare = ExecuteMsg::PrepareBatch {
+        num: 1,
+        num_claims: 1,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        prepare.clone(),
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::CommitBatch {
+            expected_sum: Uint128::new(10_000),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::PreparedSumMismatch {
+            expected: Uint128::new(10_000),
+            prepared: Uint128::new(10_100),
+        }
+    );
+
+    let commit = ExecuteMsg::CommitBatch {
+        expected_sum: Uint128::new(10_100),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        commit.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        commit.clone(),
+    )
+    .unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(10_100, DENOM));
+    assert_eq!(
+        STAKE
+            .may_load(&deps.storage, &Addr::unchecked(USER1))
+            .unwrap(),
+        None
+    );
+
+    // the prepared batch is used up
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), commit).unwrap_err();
+    assert_eq!(err, ContractError::NoPreparedBatch {});
+}
+
+#[test]
+fn commit_batch_rejects_changed_state() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::PrepareBatch {
+            num: 1,
+            num_claims: 0,
+        },
+    )
+    .unwrap();
+
+    STAKE
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked(USER1),
+            &Uint128::new(5_000),
+        )
+        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::CommitBatch {
+            expected_sum: Uint128::new(10_000),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PreparedBatchChanged {});
+}
+
+#[test]
+fn upcoming_batches() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..6 {
+        stake(deps.as_mut(), &env, &format!("user{i}"), 10_000);
+    }
+
+    let res: Vec<Vec<String>> = from_json(
+        query(
+            deps.as_ref(),
+            env,
+            QueryMsg::UpcomingBatches { num: 2, batches: 2 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            vec!["user0".to_string(), "user1".to_string()],
+            vec!["user2".to_string(), "user3".to_string()],
+        ]
+    );
+
+    // and they are what the batches take
+    let res = migrate_batch(deps.as_mut(), 2, 0).unwrap();
+    match sent_migration(&res).0 {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { weights, .. } => assert_eq!(
+            weights
+                .into_iter()
+                .map(|(addr, _)| addr)
+                .collect::<Vec<_>>(),
+            vec![Addr::unchecked("user0"), Addr::unchecked("user1")]
+        ),
+        msg => panic!("unexpected message {msg:?}"),
+    }
+}
+
+#[test]
+fn claims_over_per_address_cap_are_deferred() {
+    let mut deps = setup();
+    for i in 0..5 {
+        add_claim(deps.as_mut(), USER1, 10, Expiration::AtHeight(1_000 + i));
+    }
+    add_claim(deps.as_mut(), USER2, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 200, Expiration::AtHeight(2_000));
+    let capped = |max| {
+        MigrateBatch {
+            num: 0,
+            num_claims: 3,
+            max_claims_per_addr: Some(max),
+            ..Default::default()
+        }
+        .msg()
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), capped(3)).unwrap();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("capped_claims", USER1)));
+    let (migration, funds) = sent_migration(&res);
+    match migration {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+            assert_eq!(claims.len(), 1);
+            assert_eq!(claims[0].0, Addr::unchecked(USER2));
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+    assert_eq!(funds, coins(300, DENOM));
+    let left = CLAIMS
+        .query_claims(deps.as_ref(), &Addr::unchecked(USER1))
+        .unwrap();
+    assert_eq!(left.claims.len(), 5);
+
+    // a high enough cap lets the address through
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), capped(5)).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(50, DENOM));
+}
+
+#[test]
+fn oversized_claims_are_deferred() {
+    let mut deps = setup();
+    for i in 0..1_000 {
+        add_claim(deps.as_mut(), USER1, 1, Expiration::AtHeight(1_000 + i));
+    }
+    add_claim(deps.as_mut(), USER2, 100, Expiration::AtHeight(1_000));
+
+    // the oversized address doesn't fail the batch and keeps its claims
+    let res = migrate_batch(deps.as_mut(), 0, 3).unwrap();
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("oversized_claims", USER1)));
+    let (migration, funds) = sent_migration(&res);
+    match migration {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+            assert_eq!(claims.len(), 1);
+            assert_eq!(claims[0].0, Addr::unchecked(USER2));
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+    assert_eq!(funds, coins(100, DENOM));
+    let left = CLAIMS
+        .query_claims(deps.as_ref(), &Addr::unchecked(USER1))
+        .unwrap();
+    assert_eq!(left.claims.len(), 1_000);
+
+    // collapsing migrates it as a single claim
+    let msg = MigrateBatch {
+        num: 0,
+        num_claims: 1,
+        collapse_claims: Some(true),
+        ..Default::default()
+    }
+    .msg();
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(1_000, DENOM));
+}
+
+#[test]
+fn is_admin() {
+    let deps = setup();
+    let is_admin = |address: &str| -> bool {
+        let res: IsAdminResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::IsAdmin {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.is_admin
+    };
+    assert!(is_admin(ADMIN));
+    assert!(!is_admin(USER1));
+}
+
+#[test]
+fn confirm_dao_dao() {
+    let deps = setup();
+    let confirm = |addr: &str| -> bool {
+        let res: ConfirmDaoDaoResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ConfirmDaoDao {
+                    addr: addr.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.matches
+    };
+    assert!(confirm(DAO_DAO));
+    assert!(!confirm(USER1));
+}
+
+#[test]
+fn admin_matches_dao() {
+    let mut deps = setup();
+    let dao_admin = |deps: &mut MockDeps, admin: &'static str| {
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::ContractInfo { .. } => {
+                let mut info = ContractInfoResponse::default();
+                info.admin = Some(admin.to_string());
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&info).unwrap()))
+            }
+            _ => unimplemented!(),
+        });
+    };
+    let matches = |deps: &MockDeps| -> bool {
+        let res: AdminMatchesDaoResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::AdminMatchesDao {}).unwrap())
+                .unwrap();
+        res.matches
+    };
+
+    dao_admin(&mut deps, ADMIN);
+    assert!(matches(&deps));
+    dao_admin(&mut deps, USER1);
+    assert!(!matches(&deps));
+
+    // an unset admin never matches
+    dao_admin(&mut deps, ADMIN);
+    crate::state::ADMIN.set(deps.as_mut(), None).unwrap();
+    assert!(!matches(&deps));
+
+    // neither does an unset destination
+    let mut deps = mock_dependencies();
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[]),
+        instantiate_msg(Denom::Native(DENOM.to_string())),
+    )
+    .unwrap();
+    assert!(!matches(&deps));
+}
+
+#[test]
+fn member_change_count() {
+    let mut deps = setup();
+    let mut env = mock_env();
+    let user1 = Addr::unchecked(USER1);
+    let count = |deps: &MockDeps| -> u64 {
+        let res: MemberChangeCountResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::MemberChangeCount {
+                    address: USER1.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(!res.truncated);
+        res.count
+    };
+    assert_eq!(count(&deps), 0);
+
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    env.block.height += 1;
+    MEMBERS
+        .save(deps.as_mut().storage, &user1, &20, env.block.height)
+        .unwrap();
+    // a second change in the same block is not a new height
+    MEMBERS
+        .save(deps.as_mut().storage, &user1, &25, env.block.height)
+        .unwrap();
+    env.block.height += 1;
+    MEMBERS
+        .save(deps.as_mut().storage, &user1, &30, env.block.height)
+        .unwrap();
+    assert_eq!(count(&deps), 3);
+}
+
+#[test]
+fn effective_weight() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    MEMBERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked(USER2),
+            &25,
+            env.block.height,
+        )
+        .unwrap();
+    let effective = |address: &str| -> EffectiveWeightResponse {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::EffectiveWeight {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    assert_eq!(
+        effective(USER1),
+        EffectiveWeightResponse {
+            stored: Some(10),
+            computed: 10,
+            consistent: true,
+        }
+    );
+    assert_eq!(
+        effective(USER2),
+        EffectiveWeightResponse {
+            stored: Some(25),
+            computed: 20,
+            consistent: false,
+        }
+    );
+    assert!(effective(USER3).consistent);
+}
+
+#[test]
+fn migrate_stake_below_min_bond() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 1_000);
+    assert_eq!(
+        MEMBERS
+            .may_load(&deps.storage, &Addr::unchecked(USER1))
+            .unwrap(),
+        None
+    );
+
+    // forwarded with its token amount, despite having no weight here
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    let (migration, funds) = sent_migration(&res);
+    assert_eq!(
+        migration,
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes {
+            weights: vec![(Addr::unchecked(USER1), Uint128::new(1_000))],
+            claims: vec![],
+        }
+    );
+    assert_eq!(funds, coins(1_000, DENOM));
+
+    // pruned first, it is refunded and never reaches DAO DAO
+    stake(deps.as_mut(), &env, USER2, 1_000);
+    execute(
+        deps.as_mut(),
+        env,
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::PruneZeroWeightMembers {
+            num: 10,
+            start_after: None,
+        },
+    )
+    .unwrap();
+    let res = migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(res.messages, vec![]);
+}
+
+#[test]
+fn estimated_completion() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..5 {
+        stake(deps.as_mut(), &env, &format!("user{i}"), 10_000);
+    }
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    let estimate = |deps: Deps| -> Option<EstimatedCompletionResponse> {
+        from_json(query(deps, mock_env(), QueryMsg::EstimatedCompletion { num: 2 }).unwrap())
+            .unwrap()
+    };
+    assert_eq!(estimate(deps.as_ref()), None);
+
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    // three stakes and one claim address left, two per block
+    assert_eq!(
+        estimate(deps.as_ref()),
+        Some(EstimatedCompletionResponse {
+            height: env.block.height + 2,
+            truncated: false,
+        })
+    );
+}
+
+#[test]
+fn txs_to_finish() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..5 {
+        stake(deps.as_mut(), &env, &format!("user{i}"), 10_000);
+    }
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 100, Expiration::AtHeight(1_000));
+    let txs = |deps: Deps, num, num_claims| -> u64 {
+        let res: TxsToFinishResponse =
+            from_json(query(deps, mock_env(), QueryMsg::TxsToFinish { num, num_claims }).unwrap())
+                .unwrap();
+        assert!(!res.truncated);
+        res.txs
+    };
+
+    // five stakes two at a time, the claims fit alongside
+    assert_eq!(txs(deps.as_ref(), 2, 1), 3);
+    assert_eq!(txs(deps.as_ref(), 5, 1), 2);
+    query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::TxsToFinish {
+            num: 0,
+            num_claims: 2,
+        },
+    )
+    .unwrap_err();
+
+    // seven entries at most two per transaction
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateMaxPerBlock {
+            max_per_block: Some(2),
+        },
+    )
+    .unwrap();
+    assert_eq!(txs(deps.as_ref(), 5, 2), 4);
+}
+
+#[test]
+fn migrate_and_finalize() {
+    let setup_staked = || {
+        let mut deps = setup();
+        let env = mock_env();
+        stake(deps.as_mut(), &env, USER1, 10_000);
+        stake(deps.as_mut(), &env, USER2, 20_000);
+        add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+        HOOKS
+            .add_hook(deps.as_mut().storage, Addr::unchecked("hook"))
+            .unwrap();
+        deps
+    };
+    let finalize = |num, num_claims| ExecuteMsg::MigrateAndFinalize { num, num_claims };
+
+    let mut deps = setup_staked();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        finalize(2, 1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        finalize(2, 1),
+    )
+    .unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(30_100, DENOM));
+    assert!(res
+        .attributes
+        .contains(&Attribute::new("hooks_cleared", "1")));
+    assert!(HOOKS.query_hooks(deps.as_ref()).unwrap().hooks.is_empty());
+
+    // a stake would be left behind
+    let mut deps = setup_staked();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        finalize(1, 1),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MigrationNotComplete {});
+}
+
+#[test]
+fn storage_layout() {
+    let deps = mock_dependencies();
+    let layout: StorageLayoutResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::StorageLayout {}).unwrap()).unwrap();
+    assert_eq!(layout.claims, "claims");
+    assert_eq!(layout.stake, "stake");
+    assert_eq!(layout.members, cw4::MEMBERS_KEY);
+}
+
+#[cw_serde]
+enum FlakyDaoDaoMsg {
+    SetAccept {
+        accept: bool,
+    },
+    MigrateStakes {
+        weights: Vec<(Addr, Uint128)>,
+        claims: Vec<(Addr, Vec<Claim>)>,
+    },
+}
+
+/// A DAO DAO contract rejecting migrations until told to accept them.
+fn flaky_dao_dao() -> Box<dyn Contract<Empty>> {
+    const ACCEPT: Item<bool> = Item::new("accept");
+    Box::new(ContractWrapper::new(
+        |deps: DepsMut, _: Env, _: MessageInfo, msg: FlakyDaoDaoMsg| -> StdResult<Response> {
+            match msg {
+                FlakyDaoDaoMsg::SetAccept { accept } => ACCEPT.save(deps.storage, &accept)?,
+                FlakyDaoDaoMsg::MigrateStakes { .. } => {
+                    if !ACCEPT.may_load(deps.storage)?.unwrap_or_default() {
+                        return Err(StdError::generic_err("rejected"));
+                    }
+                }
+            }
+            Ok(Response::new())
+        },
+        |_: DepsMut, _: Env, _: MessageInfo, _: Empty| -> StdResult<Response> {
+            Ok(Response::new())
+        },
+        |_: Deps, _: Env, _: Empty| -> StdResult<Binary> { unimplemented!() },
+    ))
+}
+
+#[test]
+fn retry_failed() {
+    let (mut app, stake, automation) =
+        setup_automated_app(flaky_dao_dao(), &[(USER1, 10_000), (USER2, 20_000)]);
+    let dao_dao: Addr = from_json(
+        app.wrap()
+            .query_wasm_raw(&stake, b"dao_dao".as_slice())
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    let failed = |app: &App| -> Vec<Addr> {
+        let res: ListFailedResponse = app
+            .wrap()
+            .query_wasm_smart(
+                &stake,
+                &QueryMsg::ListFailed {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        res.failed
+    };
+
+    let msg = MigrateBatch {
+        num: 1,
+        num_claims: 0,
+        callback: Some(CallbackData(Binary::from(b"batch".as_slice()))),
+        ..Default::default()
+    }
+    .msg();
+    // the callback goes to the caller, which must be a contract
+    app.execute_contract(
+        Addr::unchecked(ADMIN),
+        automation.clone(),
+        &AutomationMsg::Run {
+            contract: stake.to_string(),
+            msg: to_json_binary(&msg).unwrap(),
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(failed(&app), vec![Addr::unchecked(USER1)]);
+
+    // a retry DAO DAO still rejects fails as a whole, keeping the failure
+    let retry = AutomationMsg::Run {
+        contract: stake.to_string(),
+        msg: to_json_binary(&ExecuteMsg::RetryFailed { num: 10 }).unwrap(),
+    };
+    app.execute_contract(Addr::unchecked(ADMIN), automation.clone(), &retry, &[])
+        .unwrap_err();
+    assert_eq!(failed(&app), vec![Addr::unchecked(USER1)]);
+
+    app.execute_contract(
+        Addr::unchecked("creator"),
+        dao_dao,
+        &FlakyDaoDaoMsg::SetAccept { accept: true },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(Addr::unchecked(ADMIN), automation, &retry, &[])
+        .unwrap();
+    assert!(failed(&app).is_empty());
+    let staked: StakedResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &stake,
+            &QueryMsg::Staked {
+                address: USER1.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(staked.stake, Uint128::zero());
+    // only the retried stake moved
+    assert_eq!(
+        app.wrap().query_balance(&stake, DENOM).unwrap(),
+        coin(20_000, DENOM)
+    );
+}
+
+#[test]
+fn migrated_total() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 500, Expiration::AtHeight(1_000));
+    let migrated_total = |deps: Deps| -> MigratedTotalResponse {
+        from_json(query(deps, mock_env(), QueryMsg::MigratedTotal {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        migrated_total(deps.as_ref()),
+        MigratedTotalResponse {
+            amount: Uint128::zero(),
+            stake_count: 0,
+            claim_count: 0,
+        }
+    );
+
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+    assert_eq!(
+        migrated_total(deps.as_ref()),
+        MigratedTotalResponse {
+            amount: Uint128::new(10_000),
+            stake_count: 1,
+            claim_count: 0,
+        }
+    );
+
+    migrate_batch(deps.as_mut(), 1, 1).unwrap();
+    assert_eq!(
+        migrated_total(deps.as_ref()),
+        MigratedTotalResponse {
+            amount: Uint128::new(30_500),
+            stake_count: 2,
+            claim_count: 1,
+        }
+    );
+}
+
+/// A DAO DAO contract reporting who sent it each migration.
+fn recording_dao_dao() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        |_: DepsMut,
+         _: Env,
+         info: MessageInfo,
+         _: dao_voting_token_staked::msg::ExecuteMsg|
+         -> StdResult<Response> {
+            Ok(Response::new().add_attribute("migration_source", info.sender))
+        },
+        |_: DepsMut, _: Env, _: MessageInfo, _: Empty| -> StdResult<Response> {
+            Ok(Response::new())
+        },
+        |_: Deps, _: Env, _: Empty| -> StdResult<Binary> { unimplemented!() },
+    ))
+}
+
+#[test]
+fn migration_source() {
+    let (mut app, stake) = setup_app(recording_dao_dao(), &[(USER1, 10_000)]);
+    let res = app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            stake.clone(),
+            &migrate_msg(1, 0),
+            &[],
+        )
+        .unwrap();
+    assert!(res.events.iter().any(|event| event
+        .attributes
+        .contains(&Attribute::new("migration_source", stake.as_str()))));
+}
+
+#[test]
+fn unmigrated_members() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    stake(deps.as_mut(), &env, USER3, 30_000);
+    let unmigrated = |deps: Deps| -> Vec<String> {
+        let res: MemberListResponse = from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::UnmigratedMembers {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        res.members.into_iter().map(|m| m.addr).collect()
+    };
+    assert_eq!(unmigrated(deps.as_ref()), vec![USER1, USER2, USER3]);
+
+    migrate_batch(deps.as_mut(), 2, 0).unwrap();
+    assert_eq!(unmigrated(deps.as_ref()), vec![USER3]);
+}
+
+#[test]
+fn incompatible_destination() {
+    let mock_destination = |deps: &mut MockDeps, contract: &'static str| {
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Raw { key, .. } if key.as_slice() == b"contract_info" => {
+                let version = cw2::ContractVersion {
+                    contract: contract.to_string(),
+                    version: "2.4.0".to_string(),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&version).unwrap()))
+            }
+            _ => unimplemented!(),
+        });
+    };
+
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    mock_destination(&mut deps, "crates.io:cw20-stake");
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::IncompatibleDestination {
+            found: "crates.io:cw20-stake".to_string()
+        }
+    );
+
+    mock_destination(&mut deps, "crates.io:dao-voting-token-staked");
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+}
+
+#[test]
+fn v2_destination_needs_newer_release() {
+    let mock_destination = |deps: &mut MockDeps, version: &'static str| {
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Raw { key, .. } if key.as_slice() == b"contract_info" => {
+                let version = cw2::ContractVersion {
+                    contract: "crates.io:dao-voting-token-staked".to_string(),
+                    version: version.to_string(),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&version).unwrap()))
+            }
+            _ => unimplemented!(),
+        });
+    };
+    let update = ExecuteMsg::UpdateDestinationVersion {
+        version: DestinationVersion::V2,
+    };
+
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    stake(deps.as_mut(), &mock_env(), USER2, 20_000);
+    mock_destination(&mut deps, "2.4.2");
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        update.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnsupportedDestinationVersion {
+            found: "2.4.2".to_string()
+        }
+    );
+
+    mock_destination(&mut deps, "2.5.0-beta");
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+    migrate_batch(deps.as_mut(), 1, 0).unwrap();
+
+    // a destination swapped back to the bundled release fails the batch
+    mock_destination(&mut deps, "2.4.0");
+    let err = migrate_batch(deps.as_mut(), 1, 0).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnsupportedDestinationVersion {
+            found: "2.4.0".to_string()
+        }
+    );
+}
+
+#[test]
+fn summary() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER3, 300, Expiration::AtHeight(1_000));
+    let summary = |deps: Deps| -> SummaryResponse {
+        from_json(query(deps, mock_env(), QueryMsg::Summary {}).unwrap()).unwrap()
+    };
+
+    assert_eq!(
+        summary(deps.as_ref()),
+        SummaryResponse {
+            total_weight: 30,
+            total_staked: Uint128::new(30_000),
+            member_count: 2,
+            total_claims: Uint128::new(600),
+            claim_count: 3,
+            dao_dao: Some(Addr::unchecked(DAO_DAO)),
+            migration_complete: false,
+            truncated: false,
+        }
+    );
+
+    migrate_batch(deps.as_mut(), 2, 2).unwrap();
+    let res = summary(deps.as_ref());
+    assert_eq!(res.total_staked, Uint128::zero());
+    assert!(res.migration_complete);
+}
+
+#[test]
+fn forfeit_claims_to_treasury() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER1, 200, Expiration::AtHeight(2_000));
+    add_claim(deps.as_mut(), USER2, 300, Expiration::AtHeight(1_000));
+    let forfeit = |num| {
+        MigrateBatch {
+            num,
+            num_claims: 2,
+            forfeit_claims: Some(true),
+            ..Default::default()
+        }
+        .msg()
+    };
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), forfeit(0)).unwrap_err();
+    assert_eq!(err, ContractError::TreasuryNotSet {});
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateTreasury {
+            treasury: Some("treasury".to_string()),
+        },
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mock_info(USER1, &[]), forfeit(0)).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), forfeit(1)).unwrap();
+    assert_eq!(
+        res.attributes
+            .iter()
+            .filter(|attr| attr.key == "forfeited")
+            .map(|attr| attr.value.as_str())
+            .collect::<Vec<_>>(),
+        vec!["user1:300", "user2:300"]
+    );
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: coins(600, DENOM),
+        })
+    );
+    // the stake still goes to DAO DAO, without any claims
+    let (migration, funds) = match &res.messages[1].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) => {
+            (from_json(msg).unwrap(), funds.clone())
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    };
+    assert_eq!(funds, coins(10_000, DENOM));
+    match migration {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+            assert!(claims.is_empty())
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+    for user in [USER1, USER2] {
+        let claims = CLAIMS
+            .query_claims(deps.as_ref(), &Addr::unchecked(user))
+            .unwrap();
+        assert!(claims.claims.is_empty());
+    }
+}
+
+#[test]
+fn funds_routed_per_recipient() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER2, 300, Expiration::AtHeight(1_000));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::UpdateTreasury {
+            treasury: Some("treasury".to_string()),
+        },
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        MigrateBatch {
+            num: 1,
+            num_claims: 1,
+            forfeit_claims: Some(true),
+            ..Default::default()
+        }
+        .msg(),
+    )
+    .unwrap();
+    let routed = |recipient: &str, amount: u128| {
+        Event::new("funds_routed")
+            .add_attribute("recipient", recipient)
+            .add_attribute("denom", DENOM)
+            .add_attribute("amount", amount.to_string())
+    };
+    assert_eq!(
+        res.events
+            .into_iter()
+            .filter(|event| event.ty == "funds_routed")
+            .collect::<Vec<_>>(),
+        vec![routed("treasury", 300), routed(DAO_DAO, 10_000)]
+    );
+}
+
+#[test]
+fn claims_map_stats() {
+    let mut deps = setup();
+    for (addr, count) in [(USER1, 1), (USER2, 3), (USER3, 2)] {
+        for i in 0..count {
+            add_claim(deps.as_mut(), addr, 100, Expiration::AtHeight(1_000 + i));
+        }
+    }
+    let stats = |sample: u32| -> ClaimsMapStatsResponse {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ClaimsMapStats { sample },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // a claim serializes to 48 bytes, plus the brackets and commas
+    assert_eq!(
+        stats(10),
+        ClaimsMapStatsResponse {
+            sampled: 3,
+            average_size: 99,
+            max_size: 148,
+        }
+    );
+    assert_eq!(
+        stats(1),
+        ClaimsMapStatsResponse {
+            sampled: 1,
+            average_size: 50,
+            max_size: 50,
+        }
+    );
+}
+
+#[test]
+fn top_claim_holders() {
+    let mut deps = setup();
+    for (addr, count) in [(USER1, 1), (USER2, 3), (USER3, 2), ("user4", 3)] {
+        for i in 0..count {
+            add_claim(deps.as_mut(), addr, 100, Expiration::AtHeight(1_000 + i));
+        }
+    }
+
+    let res: TopClaimHoldersResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::TopClaimHolders { limit: 3 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        TopClaimHoldersResponse {
+            holders: vec![
+                (Addr::unchecked(USER2), 3),
+                (Addr::unchecked("user4"), 3),
+                (Addr::unchecked(USER3), 2),
+            ],
+            truncated: false,
+        }
+    );
+}
+
+#[test]
+fn dual_holders() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER1, 500, Expiration::AtHeight(1_000));
+    stake(deps.as_mut(), &env, USER2, 20_000);
+    add_claim(deps.as_mut(), USER3, 300, Expiration::AtHeight(1_000));
+
+    let res: DualHoldersResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::DualHolders {}).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        DualHoldersResponse {
+            count: 1,
+            truncated: false,
+        }
+    );
+}
+
+#[test]
+fn export_members() {
+    let mut deps = setup();
+    let env = mock_env();
+    for i in 0..50 {
+        stake(
+            deps.as_mut(),
+            &env,
+            &format!("member{i:02}"),
+            10_000 + i * 1_000,
+        );
+    }
+    let export = |start_after: Option<Addr>| -> ExportMembersResponse {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ExportMembers {
+                    start_after: start_after.map(String::from),
+                    limit: Some(30),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let first = export(None);
+    assert_eq!(first.members.len(), 30);
+    assert_eq!(first.next, Some(Addr::unchecked("member29")));
+    let second = export(first.next);
+    assert_eq!(second.members.len(), 20);
+    assert_eq!(second.next, None);
+
+    let exported: Vec<_> = first.members.into_iter().chain(second.members).collect();
+    let expected: Vec<_> = (0..50)
+        .map(|i| (Addr::unchecked(format!("member{i:02}")), 10 + i))
+        .collect();
+    assert_eq!(exported, expected);
+}
+
+#[test]
+fn top_k_concentration() {
+    let mut deps = setup();
+    let env = mock_env();
+    for (addr, amount) in [
+        (USER1, 10_000),
+        (USER2, 40_000),
+        (USER3, 20_000),
+        ("user4", 20_000),
+        ("user5", 10_000),
+    ] {
+        stake(deps.as_mut(), &env, addr, amount);
+    }
+
+    let res: TopKConcentrationResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::TopKConcentration { k: 2 },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    // 60 of 100 weight
+    assert_eq!(
+        res,
+        TopKConcentrationResponse {
+            top_k_weight: 60,
+            total_weight: 100,
+            fraction: Decimal::percent(60),
+            truncated: false,
+        }
+    );
+}
+
+#[test]
+fn orphan_claims() {
+    let mut deps = setup();
+    let env = mock_env();
+    stake(deps.as_mut(), &env, USER1, 10_000);
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(deps.as_mut(), USER2, 200, Expiration::AtHeight(1_000));
+    // a stake below min_bond has no membership, but is still a stake
+    stake(deps.as_mut(), &env, USER3, 1_000);
+    add_claim(deps.as_mut(), USER3, 300, Expiration::AtHeight(1_000));
+
+    let res: OrphanClaimsResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::OrphanClaims {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.claims,
+        vec![(
+            Addr::unchecked(USER2),
+            vec![Claim {
+                amount: Uint128::new(200),
+                release_at: Expiration::AtHeight(1_000),
+            }]
+        )]
+    );
+    assert_eq!(res.next_start_after, None);
+
+    // a page stopping early tells where the next one starts
+    add_claim(deps.as_mut(), "user4", 400, Expiration::AtHeight(1_000));
+    let page = |start_after: Option<String>| -> OrphanClaimsResponse {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OrphanClaims {
+                    start_after,
+                    limit: Some(1),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let first = page(None);
+    assert_eq!(first.claims[0].0, Addr::unchecked(USER2));
+    assert_eq!(first.next_start_after, Some(Addr::unchecked(USER2)));
+    let second = page(first.next_start_after.map(Addr::into_string));
+    assert_eq!(second.claims[0].0, Addr::unchecked("user4"));
+    assert_eq!(second.next_start_after, None);
+}
+
+#[test]
+fn add_and_remove_hooks() {
+    let mut deps = setup();
+    let hooks = |deps: Deps| -> Vec<String> {
+        let res: HooksResponse =
+            from_json(query(deps, mock_env(), QueryMsg::Hooks {}).unwrap()).unwrap();
+        res.hooks
+    };
+    let add = ExecuteMsg::AddHook {
+        addr: "hook".to_string(),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(USER1, &[]),
+        add.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Hook(HookError::Admin(AdminError::NotAdmin {}))
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        add.clone(),
+    )
+    .unwrap();
+    assert_eq!(hooks(deps.as_ref()), vec!["hook".to_string()]);
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), add).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Hook(HookError::HookAlreadyRegistered {})
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        ExecuteMsg::RemoveHook {
+            addr: "hook".to_string(),
+        },
+    )
+    .unwrap();
+    assert!(hooks(deps.as_ref()).is_empty());
+}
+
+#[test]
+fn has_hooks() {
+    let mut deps = setup();
+    let has_hooks = |deps: &MockDeps| -> bool {
+        let res: HasHooksResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::HasHooks {}).unwrap()).unwrap();
+        res.has_hooks
+    };
+    assert!(!has_hooks(&deps));
+
+    for addr in ["hook1", "hook2"] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddHook {
+                addr: addr.to_string(),
+            },
+        )
+        .unwrap();
+    }
+    assert!(has_hooks(&deps));
+}
+
+#[test]
+fn far_future_claims_are_rejected() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    add_claim(
+        deps.as_mut(),
+        USER2,
+        200,
+        Expiration::AtHeight(1_000_000_000),
+    );
+    let bounded = |num_claims| {
+        MigrateBatch {
+            num: 0,
+            num_claims,
+            max_release: Some(Expiration::AtHeight(100_000)),
+            ..Default::default()
+        }
+        .msg()
+    };
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), bounded(2)).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ClaimReleaseTooFar {
+            addr: USER2.to_string()
+        }
+    );
+
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), bounded(1)).unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(100, DENOM));
+}
+
+/// A DAO DAO contract crediting migrated stakes as voting power, capped at
+/// 15_000 per address.
+fn capping_dao_dao() -> Box<dyn Contract<Empty>> {
+    const POWER: Map<&Addr, Uint128> = Map::new("power");
+    Box::new(ContractWrapper::new(
+        |deps: DepsMut,
+         _: Env,
+         _: MessageInfo,
+         msg: dao_voting_token_staked::msg::ExecuteMsg|
+         -> StdResult<Response> {
+            if let dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { weights, .. } = msg {
+                for (addr, weight) in weights {
+                    POWER.save(deps.storage, &addr, &weight.min(Uint128::new(15_000)))?;
+                }
+            }
+            Ok(Response::new())
+        },
+        |_: DepsMut, _: Env, _: MessageInfo, _: Empty| -> StdResult<Response> {
+            Ok(Response::new())
+        },
+        |deps: Deps, _: Env, msg: dao_voting_token_staked::msg::QueryMsg| -> StdResult<Binary> {
+            match msg {
+                dao_voting_token_staked::msg::QueryMsg::VotingPowerAtHeight { address, height } => {
+                    to_json_binary(&dao_interface::voting::VotingPowerAtHeightResponse {
+                        power: POWER
+                            .may_load(deps.storage, &Addr::unchecked(address))?
+                            .unwrap_or_default(),
+                        height: height.unwrap_or_default(),
+                    })
+                }
+                _ => unimplemented!(),
+            }
+        },
+    ))
+}
+
+#[test]
+fn confirm_receipts() {
+    let (mut app, stake) = setup_app(capping_dao_dao(), &[(USER1, 10_000), (USER2, 20_000)]);
+    let msg = MigrateBatch {
+        num: 2,
+        num_claims: 0,
+        confirm: Some(true),
+        ..Default::default()
+    }
+    .msg();
+    app.execute_contract(Addr::unchecked(ADMIN), stake.clone(), &msg, &[])
+        .unwrap();
+
+    let res: ListMigratedResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &stake,
+            &QueryMsg::ListMigrated {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.migrated
+            .iter()
+            .map(|receipt| (receipt.addr.as_str(), receipt.confirmed))
+            .collect::<Vec<_>>(),
+        vec![(USER1, Some(true)), (USER2, Some(false))]
+    );
+}
+
+#[test]
+fn forward_denom_override() {
+    let mut deps = setup();
+    stake(deps.as_mut(), &mock_env(), USER1, 10_000);
+    let forward = |denom: &str| {
+        MigrateBatch {
+            num: 1,
+            num_claims: 0,
+            forward_denom: Some(denom.to_string()),
+            ..Default::default()
+        }
+        .msg()
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        forward("1bad denom"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidForwardDenom {
+            denom: "1bad denom".to_string()
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        forward("factory/kujira1xyz/ukuji"),
+    )
+    .unwrap();
+    let (_, funds) = sent_migration(&res);
+    assert_eq!(funds, coins(10_000, "factory/kujira1xyz/ukuji"));
+    // accounting keeps the configured denom
+    assert_eq!(
+        CONFIG.load(&deps.storage).unwrap().denom,
+        Denom::Native(DENOM.to_string())
+    );
+}
+
+#[test]
+fn interleaved_batch() {
+    let interleaved = |num: u64, num_claims: u64| {
+        MigrateBatch {
+            num,
+            num_claims,
+            interleave: Some(true),
+            ..Default::default()
+        }
+        .msg()
+    };
+    let counts = |deps: &MockDeps| {
+        let batch: Option<LastBatch> =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::LastBatch {}).unwrap()).unwrap();
+        let batch = batch.unwrap();
+        (batch.stake_count, batch.claim_count)
+    };
+
+    let mut deps = setup();
+    for i in 0..10 {
+        stake(deps.as_mut(), &mock_env(), &format!("staker{i}"), 10_000);
+        add_claim(
+            deps.as_mut(),
+            &format!("claimer{i}"),
+            100,
+            Expiration::AtHeight(1_000),
+        );
+    }
+    // the combined budget of 6 is split evenly, wherever it was requested
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        interleaved(6, 0),
+    )
+    .unwrap();
+    assert_eq!(counts(&deps), (3, 3));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        interleaved(1, 4),
+    )
+    .unwrap();
+    assert_eq!(counts(&deps), (3, 2));
+    let status: MigrationStatusResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap();
+    assert_eq!(status.claims_cursor, Some(Addr::unchecked("claimer4")));
+
+    // a tiny claims map leaves the rest of the budget to stakes
+    let mut deps = setup();
+    for i in 0..10 {
+        stake(deps.as_mut(), &mock_env(), &format!("staker{i}"), 10_000);
+    }
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(1_000));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        interleaved(3, 3),
+    )
+    .unwrap();
+    assert_eq!(counts(&deps), (5, 1));
+
+    // claims cut by the split are not exhausted, even past the end of the map
+    let exhausted = |res: &Response| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == "claims_exhausted")
+            .map(|attr| attr.value.clone())
+    };
+    let mut deps = setup();
+    for i in 0..10 {
+        stake(deps.as_mut(), &mock_env(), &format!("staker{i}"), 10_000);
+    }
+    for i in 0..8 {
+        add_claim(
+            deps.as_mut(),
+            &format!("claimer{i}"),
+            100,
+            Expiration::AtHeight(1_000),
+        );
+    }
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        interleaved(5, 5),
+    )
+    .unwrap();
+    assert_eq!(counts(&deps), (5, 5));
+    assert_eq!(exhausted(&res), Some("false".to_string()));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        interleaved(5, 5),
+    )
+    .unwrap();
+    assert_eq!(counts(&deps), (5, 3));
+    assert_eq!(exhausted(&res), Some("true".to_string()));
+}
+
+#[test]
+fn soonest_maturing_claims_first() {
+    let mut deps = setup();
+    add_claim(deps.as_mut(), USER1, 100, Expiration::AtHeight(3_000));
+    add_claim(deps.as_mut(), USER2, 200, Expiration::AtHeight(1_000));
+    add_claim(
+        deps.as_mut(),
+        USER3,
+        300,
+        Expiration::AtTime(Timestamp::from_seconds(1_000)),
+    );
+    // ordered by the earliest of its claims
+    add_claim(deps.as_mut(), "user4", 400, Expiration::AtHeight(5_000));
+    add_claim(deps.as_mut(), "user4", 500, Expiration::AtHeight(500));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(ADMIN, &[]),
+        MigrateBatch {
+            num: 0,
+            num_claims: 2,
+            soonest_first: Some(true),
+            ..Default::default()
+        }
+        .msg(),
+    )
+    .unwrap();
+    match sent_migration(&res).0 {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+            let addrs: Vec<_> = claims.iter().map(|(addr, _)| addr.as_str()).collect();
+            assert_eq!(addrs, vec!["user4", USER2]);
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+    // the cursor of address ordered batches is left alone
+    let status: MigrationStatusResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::MigrationStatus {}).unwrap()).unwrap();
+    assert_eq!(status.claims_cursor, None);
+
+    // heights before times
+    let res = migrate_batch(deps.as_mut(), 0, 2).unwrap();
+    match sent_migration(&res).0 {
+        dao_voting_token_staked::msg::ExecuteMsg::MigrateStakes { claims, .. } => {
+            let addrs: Vec<_> = claims.iter().map(|(addr, _)| addr.as_str()).collect();
+            assert_eq!(addrs, vec![USER1, USER3]);
+        }
+        msg => panic!("unexpected message {msg:?}"),
+    }
+}